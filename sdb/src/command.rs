@@ -2,41 +2,2021 @@ use ariadne::{Label, Report, ReportKind, Source};
 use chumsky::prelude::*;
 use color_eyre::Result;
 
+use crate::expression::Expression;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum ErrorKind {
     UnexpectedCommand(String),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HandleAction {
+    Stop,
+    NoStop,
+    Print,
+    NoPrint,
+    Pass,
+    NoPass,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameCount {
+    Innermost(usize),
+    Outermost(usize),
+}
+
+/// Where a breakpoint goes, or what a watchpoint watches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Location {
+    Address(u64),
+    /// A function for breakpoints, a global variable for watchpoints.
+    Symbol(String),
+    /// `symbol+offset`, somewhere past the start of a function.
+    SymbolOffset(String, u64),
+    /// A line of the source file the program is stopped in.
+    Line(u64),
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "*{address:#x}"),
+            Self::Symbol(name) => write!(f, "{name}"),
+            Self::SymbolOffset(name, offset) => write!(f, "{name}+{offset:#x}"),
+            Self::Line(line) => write!(f, "{line}"),
+        }
+    }
+}
+
+/// What `set $name = ...` assigns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SetValue {
+    Expression(Expression),
+    /// The address of a function, maybe past its start.
+    Code(Location),
+}
+
+/// How `print/F` shows a value, or `x/F` each unit of memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Hex,
+    Decimal,
+    Unsigned,
+    /// The byte value, then the character.
+    Char,
+    Binary,
+    /// A NUL terminated string per unit, cut at [`MAX_STRING_LENGTH`]. Only for `x`.
+    String,
+}
+
+impl OutputFormat {
+    const fn from_letter(letter: char) -> Option<Self> {
+        Some(match letter {
+            'x' => Self::Hex,
+            'd' => Self::Decimal,
+            'u' => Self::Unsigned,
+            'c' => Self::Char,
+            't' => Self::Binary,
+            's' => Self::String,
+            _ => return None,
+        })
+    }
+}
+
+/// How much of a string `x/s` shows, so a missing NUL does not read on and on.
+const MAX_STRING_LENGTH: usize = 200;
+
+/// The `/NFU` of `x`, what is not given coming from the last `x`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ExamineSpec {
+    count: Option<usize>,
+    format: Option<OutputFormat>,
+    /// In bytes: 1, 2, 4 or 8.
+    size: Option<usize>,
+}
+
+/// A memory dump as `x` makes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Examine {
+    address: u64,
+    count: usize,
+    format: OutputFormat,
+    size: usize,
+    /// Where a bare `x` after this one goes on, past the strings for `x/s`.
+    end: u64,
+}
+
+/// What the front end should do once a command has run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// Keep reading commands.
+    Continue,
+    /// End the session.
+    Quit,
+    /// Wrap long input over several lines, or keep it on one that scrolls.
+    WrapInput(bool),
+}
+
+/// `help`: the commands by name, aliases after a comma, and what they do. The first line is the
+/// summary `help` lists.
+const COMMAND_HELP: &[(&str, &str)] = &[
+    (
+        "file",
+        "Load a program to debug, without running it.\n\
+         Its symbols are read so breakpoints can be set on its functions first; \"run\", \
+         \"start\" and \"starti\" launch it, with the arguments given to \"set args\".",
+    ),
+    (
+        "run",
+        "Start the program from the beginning and let it run.\n\
+         The dynamic loader maps the shared libraries and runs their initializers on the way, \
+         without stopping unless a breakpoint, caught system call or signal says otherwise.",
+    ),
+    (
+        "start",
+        "Start the program from the beginning and stop at main.\n\
+         The dynamic loader runs first without stopping, as with \"run\"; the stop at main is \
+         not one of the numbered breakpoints.",
+    ),
+    (
+        "starti",
+        "Start the program from the beginning and stop at its very first instruction.\n\
+         For a dynamically linked program that is the entry point of the dynamic loader \
+         (AT_BASE in \"info auxv\"), before any library is mapped; the program's own entry \
+         point is AT_ENTRY.",
+    ),
+    (
+        "continue",
+        "Continue the current process, or every process being debugged: \"continue [all]\".",
+    ),
+    ("wait", "Wait for the program to stop on its own."),
+    (
+        "step, s",
+        "Step to the next source line, going into calls. Takes a repeat count.",
+    ),
+    ("stepi, si", "Step one instruction. Takes a repeat count."),
+    (
+        "nexti, ni",
+        "Step one instruction, running through calls and rep string instructions. Takes a \
+         repeat count.",
+    ),
+    (
+        "break, b",
+        "Set a breakpoint at a function, function+offset, *address, or a line of the current \
+         source file.\n\
+         A function no library loaded so far defines leaves the breakpoint pending, until one \
+         that does is loaded.",
+    ),
+    ("enable", "Enable a breakpoint by number, or all of them."),
+    (
+        "disable",
+        "Disable a breakpoint by number, or all of them, keeping it.",
+    ),
+    ("watch", "Stop when an expression or *address is written."),
+    ("rwatch", "Stop when an expression or *address is read."),
+    (
+        "awatch",
+        "Stop when an expression or *address is read or written.",
+    ),
+    ("delete", "Delete a watchpoint: \"delete watchpoint N\"."),
+    (
+        "catch",
+        "Stop at system calls: \"catch syscall [name|number]...\".",
+    ),
+    (
+        "handle",
+        "Say what to do with a signal: stop, print, pass or their no- forms.",
+    ),
+    (
+        "backtrace, bt",
+        "Show the call stack, the innermost N frames or the outermost -N.",
+    ),
+    (
+        "frame",
+        "Select a frame by number, or show the selected one.",
+    ),
+    ("up", "Select the frame that called this one."),
+    ("down", "Select the frame this one called."),
+    (
+        "return",
+        "Pop the innermost frame now, returning a value if given, without running the rest of \
+         its function.",
+    ),
+    (
+        "print, p",
+        "Show a variable, register or expression.\n\
+         \"print/F\" shows an integer in format F whatever its type: x hex, d signed decimal, \
+         u unsigned decimal, c character, t binary.",
+    ),
+    (
+        "x",
+        "Examine memory: \"x/NFU address\", going on from the last one without an address.",
+    ),
+    (
+        "find",
+        "Search memory: \"find [/max] start, end|+length, pattern\".",
+    ),
+    (
+        "info",
+        "Show things about the program: breakpoints, registers, stack, threads, signals...",
+    ),
+    (
+        "set",
+        "Set a register, convenience variable or debugger setting, or a variable of the \
+         program with \"set var name = value\".",
+    ),
+    ("show", "Show a debugger setting."),
+    (
+        "define",
+        "Define a command running others, $arg0... being its arguments.",
+    ),
+    (
+        "if",
+        "Run commands if an expression is not zero, up to \"else\" or \"end\".",
+    ),
+    (
+        "while",
+        "Run commands up to \"end\" while an expression is not zero.",
+    ),
+    ("help", "Show this list, or the documentation of a command."),
+    (
+        "exit, quit, q",
+        "Leave the debugger, asking first if that kills the program (see \"set confirm\").",
+    ),
+];
+
+/// How deep macros may run macros before we take it for endless recursion.
+const MAX_MACRO_DEPTH: usize = 64;
+
+/// Where `set logging on` copies the scrollback unless told otherwise, like gdb's `gdb.txt`.
+const DEFAULT_LOG_FILE: &str = "sdb.txt";
+
+/// What lasts between commands: the macros the user has `define`d, convenience variables and
+/// logging.
+// The flags are settings the user turns on and off one by one.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug)]
+pub struct Session {
+    macros: std::collections::BTreeMap<String, String>,
+    /// Where the macros are kept between runs, as `define` commands.
+    macro_file: Option<std::path::PathBuf>,
+    /// Set by the front end on Ctrl-C, to stop a `while` loop.
+    interrupted: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// `$_` and `$__`, the last two values printed, and whatever the user `set`.
+    convenience: crate::expression::Convenience,
+    /// Where `set logging on` copies the scrollback, open while it does.
+    log_file: Option<std::fs::File>,
+    log_path: std::path::PathBuf,
+    /// Whether `set logging on` truncates the file rather than append to it.
+    log_overwrite: bool,
+    /// The last `x`, which a bare `x` carries on from.
+    last_examine: Option<Examine>,
+    /// Whether `step` and `stepi` show the registers they changed.
+    step_show_changes: bool,
+    /// Whether to also say what happened to the signals that were not passed on.
+    verbose: bool,
+    /// Whether the terminal interface asks before quitting kills the program.
+    pub confirm: bool,
+    /// Whether each command is followed by how long it took.
+    timing: bool,
+    /// What the last `info time` read, which the next one measures the CPU use since from.
+    last_times: Option<(sdblib::Pid, sdblib::ProcessTimes)>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            macros: std::collections::BTreeMap::new(),
+            macro_file: None,
+            interrupted: std::sync::Arc::default(),
+            convenience: crate::expression::Convenience::new(),
+            log_file: None,
+            log_path: DEFAULT_LOG_FILE.into(),
+            log_overwrite: false,
+            last_examine: None,
+            step_show_changes: true,
+            verbose: false,
+            confirm: true,
+            timing: false,
+            last_times: None,
+        }
+    }
+}
+
+impl Session {
+    /// A session whose macros live in `macro_file`, starting with the ones already there.
+    pub fn with_macro_file(macro_file: std::path::PathBuf) -> Self {
+        let macros = std::fs::read_to_string(&macro_file)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| match parse_line(line, &mut std::io::sink()) {
+                Some(Commands::Sequence(commands)) => match <[_; 1]>::try_from(commands) {
+                    Ok([Commands::Define(name, Some(body))]) => Some((name, body)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        Self {
+            macros,
+            macro_file: Some(macro_file),
+            ..Self::default()
+        }
+    }
+
+    /// The flag to raise when the user asks to interrupt the running command.
+    pub fn interrupt_flag(&self) -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+        self.interrupted.clone()
+    }
+
+    /// `$XDG_CONFIG_HOME/sdb/macros`, or `~/.config/sdb/macros`.
+    pub fn default_macro_file() -> Option<std::path::PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(std::path::PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+            })
+            .map(|config| config.join("sdb").join("macros"))
+    }
+
+    /// Copy `text`, as shown in the scrollback, to the log if `set logging` is on. Logging
+    /// stops should the file fail.
+    pub fn log(&mut self, text: &str) {
+        if let Some(file) = &mut self.log_file {
+            let line_end = if text.ends_with('\n') { "" } else { "\n" };
+            if std::io::Write::write_all(file, format!("{text}{line_end}").as_bytes()).is_err() {
+                self.log_file = None;
+            }
+        }
+    }
+
+    /// The registers as they are before a step, to show what it changed, if `set
+    /// step-show-changes` is on and there is a stopped process to read them from.
+    fn register_snapshot(&self, debugger: &sdblib::Debugger) -> Option<Vec<sdblib::Register>> {
+        self.step_show_changes
+            .then(|| debugger.registers().ok())
+            .flatten()
+    }
+
+    /// Remember `value` as `$_`, the previous one becoming `$__`.
+    fn set_last_value(&mut self, value: u64) {
+        if let Some(previous) = self.convenience.insert("_".to_string(), value) {
+            self.convenience.insert("__".to_string(), previous);
+        }
+    }
+
+    /// The dump `x` asked for, given the address it was told if any. Without one it goes on past
+    /// the last `x`, or from `$_`, with that `x`'s count; format and size always default to the
+    /// last ones.
+    fn examine(&mut self, spec: ExamineSpec, address: Option<u64>) -> Result<Examine> {
+        let last = self.last_examine;
+        let (address, repeated) = match (address, last) {
+            (Some(address), _) => (address, None),
+            (None, Some(last)) => (last.end, Some(last)),
+            (None, None) => (
+                self.convenience.get("_").copied().ok_or_else(|| {
+                    color_eyre::eyre::eyre!("Argument required (starting display address).")
+                })?,
+                None,
+            ),
+        };
+        let format = spec
+            .format
+            .or_else(|| last.map(|last| last.format))
+            .unwrap_or(OutputFormat::Hex);
+        let size = spec
+            .size
+            .or_else(|| matches!(format, OutputFormat::Char | OutputFormat::String).then_some(1))
+            .or_else(|| last.map(|last| last.size))
+            .unwrap_or(4);
+        let count = spec
+            .count
+            .or_else(|| repeated.map(|last| last.count))
+            .unwrap_or(1);
+        let examine = Examine {
+            address,
+            count,
+            format,
+            size,
+            end: unit_address(address, count, size)?,
+        };
+        self.last_examine = Some(examine);
+        Ok(examine)
+    }
+
+    fn save_macros(&self) -> Result<()> {
+        let Some(macro_file) = &self.macro_file else {
+            return Ok(());
+        };
+        if let Some(dir) = macro_file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut contents = self
+            .macros
+            .iter()
+            .map(|(name, body)| format!("define {name} {body}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        contents.push('\n');
+        std::fs::write(macro_file, contents)?;
+        Ok(())
+    }
+
+    /// `line` with every command naming a macro replaced by the macro's body, `$argN` and
+    /// `$argc` standing for the words after the name.
+    fn expand_macros(&self, line: &str, depth: usize) -> Result<String> {
+        // A definition runs macros when it is used, not when it is made.
+        if self.macros.is_empty() || line.split_whitespace().next() == Some("define") {
+            return Ok(line.to_string());
+        }
+        let mut expanded = Vec::new();
+        for command in split_commands(line) {
+            let mut words = command.split_whitespace();
+            let Some(body) = words.next().and_then(|name| self.macros.get(name)) else {
+                expanded.push(command.to_string());
+                continue;
+            };
+            if depth >= MAX_MACRO_DEPTH {
+                return Err(color_eyre::eyre::eyre!(
+                    "Max user call depth exceeded -- command aborted."
+                ));
+            }
+            let args: Vec<&str> = words.collect();
+            // Highest first, so `$arg1` does not eat the start of `$arg10`.
+            let mut body = body.replace("$argc", &args.len().to_string());
+            for (index, arg) in args.iter().enumerate().rev() {
+                body = body.replace(&format!("$arg{index}"), arg);
+            }
+            if let Some(index) = body.find("$arg") {
+                let missing: String = body[index + 4..]
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect();
+                return Err(color_eyre::eyre::eyre!(
+                    "Missing argument {missing} in user function."
+                ));
+            }
+            expanded.push(self.expand_macros(&body, depth + 1)?);
+        }
+        Ok(expanded.join(";"))
+    }
+}
+
+/// The `;` separated commands of `line`, without its comment. A `;` or `#` inside double
+/// quotes is part of the command.
+fn split_commands(line: &str) -> Vec<&str> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut end = line.len();
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => {
+                end = index;
+                break;
+            }
+            ';' if !quoted => {
+                commands.push(&line[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    commands.push(&line[start..end]);
+    commands
+}
+
 #[derive(Clone, Debug)]
 enum Commands {
     Continue,
+    ContinueAll,
+    Wait,
+    Starti,
+    /// `start`: run to `main`.
+    Start,
+    Help(Option<String>),
+    Stepi(usize),
+    /// `nexti`: `stepi` running through calls and `rep` string instructions.
+    Nexti(usize),
+    /// `find [/max] start, end|+length, pattern`, the end being inclusive like gdb's.
+    Find(Option<usize>, std::ops::Range<u64>, Vec<u8>),
+    /// `x[/NFU] [address]`, going on from the last `x` without an address.
+    Examine(ExamineSpec, Option<Expression>),
+    Step(usize),
+    Run,
+    File(String),
+    Handle(sdblib::Signal, Vec<HandleAction>),
+    InfoSignals,
+    InfoSharedLibrary,
+    InfoArgs,
+    InfoAuxv,
+    InfoTime,
+    InfoThreads,
+    InfoStack(Option<usize>),
+    InfoRegisters(Option<String>),
+    /// `set $name = value`: a register if there is one by that name, else a convenience variable.
+    SetRegister(String, SetValue),
+    /// `set var name = value`: a parameter or global of the program.
+    SetVariable(String, Expression),
+    InfoFunctions(Option<regex::Regex>),
+    InfoVariables(Option<regex::Regex>),
+    /// `print[/F] name`, the format overriding the one of the type.
+    Print(Option<OutputFormat>, String),
+    /// `print` of anything but a name, with the expression as typed.
+    PrintExpression(Option<OutputFormat>, String, Expression),
+    Backtrace(Option<FrameCount>),
+    SetBacktraceLimit(Option<usize>),
+    SetPrintDemangle(bool),
+    SetInputWrap(bool),
+    /// `set logging on [file]`, the file staying the same if not given.
+    SetLoggingOn(Option<std::path::PathBuf>),
+    SetLoggingOff,
+    SetLoggingOverwrite(bool),
+    ShowLogging,
+    SetExitPolicy(sdblib::ExitPolicy),
+    ShowExitPolicy,
+    SetDisableRandomization(bool),
+    ShowDisableRandomization,
+    SetStepShowChanges(bool),
+    ShowStepShowChanges,
+    SetVerbose(bool),
+    ShowVerbose,
+    SetConfirm(bool),
+    ShowConfirm,
+    SetTiming(bool),
+    ShowTiming,
+    ShowPrintDemangle,
+    SetArgs(Vec<String>),
+    CatchSyscall(Vec<u64>),
+    Break(Location),
+    /// `enable`/`disable [id]`, every breakpoint without an id.
+    Enable(Option<usize>),
+    Disable(Option<usize>),
+    InfoBreakpoints,
+    Watch(sdblib::WatchKind, Location),
+    InfoWatchpoints,
+    DeleteWatchpoint(usize),
+    ShowArgs,
+    /// `define name [body]`: the body runs when `name` is typed as a command, an empty one
+    /// deletes the macro.
+    Define(String, Option<String>),
+    ShowUser(Option<String>),
+    ShowConvenience,
+    Frame(Option<usize>),
+    Up(usize),
+    Down(usize),
+    /// `return [value]`: pop the innermost frame, setting rax to the value if there is one.
+    Return(Option<Expression>),
     Exit,
+    /// The heads and ends of blocks as parsed; [`nest_blocks`] turns them into blocks.
+    If(Expression),
+    While(Expression),
+    Else,
+    End,
+    Conditional(Expression, Vec<Self>, Vec<Self>),
+    Loop(Expression, Vec<Self>),
     Sequence(Vec<Self>),
     Error(ErrorKind),
 }
 
+// One arm per command; splitting it up would only scatter the grammar.
+#[allow(clippy::too_many_lines)]
 fn parser<'a>() -> impl Parser<'a, &'a str, Commands, extra::Err<Rich<'a, char>>> {
+    // Anything up to the next separator, whatever script it is written in, so the reported span
+    // covers the whole offending word.
     let error_command = any()
-        .filter(|c: &char| c.is_ascii_alphabetic())
+        .filter(|c: &char| !c.is_whitespace() && !matches!(c, ';' | '#'))
         .repeated()
         .at_least(1)
         .collect::<String>()
         .map(|s: String| ErrorKind::UnexpectedCommand(s));
 
+    let signal = any()
+        .filter(|c: &char| c.is_ascii_alphanumeric())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .try_map(|name: String, span| {
+            parse_signal(&name).ok_or_else(|| Rich::custom(span, format!("unknown signal {name}")))
+        });
+
+    // A shell-like word: double quotes group spaces, `;` and `#`, and a backslash escapes the next
+    // character inside them.
+    let quoted = just('"')
+        .ignore_then(
+            choice((just('\\').ignore_then(any()), none_of("\\\"")))
+                .repeated()
+                .collect::<String>(),
+        )
+        .then_ignore(just('"'));
+    let bare = any()
+        .filter(|c: &char| !c.is_whitespace() && !matches!(c, ';' | '"' | '#'))
+        .repeated()
+        .at_least(1)
+        .collect::<String>();
+    let word = choice((quoted, bare))
+        .repeated()
+        .at_least(1)
+        .collect::<Vec<String>>()
+        .map(|parts| parts.concat());
+
+    let handle_action = choice((
+        just("nostop").to(HandleAction::NoStop),
+        just("stop").to(HandleAction::Stop),
+        just("noprint").to(HandleAction::NoPrint),
+        just("print").to(HandleAction::Print),
+        just("nopass").to(HandleAction::NoPass),
+        just("pass").to(HandleAction::Pass),
+    ))
+    .padded();
+
+    let number = text::int(10).try_map(|digits: &str, span| {
+        digits
+            .parse::<usize>()
+            .map_err(|e| Rich::custom(span, format!("invalid number {digits}: {e}")))
+    });
+
+    let address = choice((
+        just("0x")
+            .ignore_then(text::int(16))
+            .try_map(|digits: &str, span| {
+                u64::from_str_radix(digits, 16)
+                    .map_err(|e| Rich::custom(span, format!("invalid address 0x{digits}: {e}")))
+            }),
+        text::int(10).try_map(|digits: &str, span| {
+            digits
+                .parse::<u64>()
+                .map_err(|e| Rich::custom(span, format!("invalid address {digits}: {e}")))
+        }),
+    ));
+
+    // A symbol as the source spells it, namespaces included.
+    let symbol = any()
+        .filter(|c: &char| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '$'))
+        .repeated()
+        .at_least(1)
+        .collect::<String>();
+
+    // No symbol starts with a digit, so a bare number is a line.
+    let symbol_offset = symbol
+        .then(just('+').padded().ignore_then(address).or_not())
+        .try_map(|(name, offset), span| match offset {
+            Some(offset) => Ok(Location::SymbolOffset(name, offset)),
+            None if name.bytes().all(|c| c.is_ascii_digit()) => name
+                .parse()
+                .map(Location::Line)
+                .map_err(|e| Rich::custom(span, format!("invalid line {name}: {e}"))),
+            None => Ok(Location::Symbol(name)),
+        });
+
+    let location = choice((
+        just('*').ignore_then(address).map(Location::Address),
+        symbol_offset,
+    ));
+
+    let pattern = text::inline_whitespace()
+        .at_least(1)
+        .ignore_then(word.try_map(|pattern: String, span| {
+            regex::Regex::new(&pattern)
+                .map_err(|e| Rich::custom(span, format!("invalid regex: {e}")))
+        }))
+        .or_not();
+
+    let frame_count = just('-')
+        .or_not()
+        .then(number)
+        .map(|(minus, count)| {
+            if minus.is_some() {
+                FrameCount::Outermost(count)
+            } else {
+                FrameCount::Innermost(count)
+            }
+        })
+        .padded();
+
+    let repeat_count = text::inline_whitespace()
+        .at_least(1)
+        .ignore_then(number)
+        .or_not();
+
+    // A quoted string, or hex bytes in memory order.
+    let byte_pattern = choice((
+        quoted.map(String::into_bytes),
+        just("0x")
+            .ignore_then(text::digits(16).at_least(1).at_most(2).to_slice())
+            .map(|digits: &str| u8::from_str_radix(digits, 16).unwrap_or_default())
+            .separated_by(text::inline_whitespace().at_least(1))
+            .at_least(1)
+            .collect::<Vec<u8>>(),
+    ));
+
+    let search_range = address
+        .padded()
+        .then_ignore(just(','))
+        .then(
+            choice((
+                just('+').ignore_then(address).map(|length| (length, true)),
+                address.map(|end| (end, false)),
+            ))
+            .padded(),
+        )
+        .try_map(|(start, (end, is_length)), span| {
+            let end = if is_length {
+                start.checked_add(end)
+            } else {
+                end.checked_add(1)
+            };
+            end.filter(|end| *end > start)
+                .map(|end| start..end)
+                .ok_or_else(|| Rich::custom(span, "invalid search range"))
+        });
+
+    let on_off = choice((just("on").to(true), just("off").to(false)));
+
+    // `/NFU`, with the format and size letters in either order like gdb.
+    let print_format =
+        just('/').ignore_then(
+            any().try_map(
+                |letter: char, span| match OutputFormat::from_letter(letter) {
+                    Some(OutputFormat::String) => Err(Rich::custom(
+                        span,
+                        "Format letter \"s\" is meaningless in \"print\" command.",
+                    )),
+                    Some(format) => Ok(format),
+                    None => Err(Rich::custom(
+                        span,
+                        format!("Undefined output format \"{letter}\"."),
+                    )),
+                },
+            ),
+        );
+
+    let examine_spec = just('/')
+        .ignore_then(number.or_not())
+        .then(
+            any()
+                .filter(char::is_ascii_alphabetic)
+                .map_with(|letter, e| (letter, e.span()))
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .try_map(|(count, letters), _| {
+            let mut spec = ExamineSpec {
+                count,
+                ..ExamineSpec::default()
+            };
+            for (letter, span) in letters {
+                match letter {
+                    'b' => spec.size = Some(1),
+                    'h' => spec.size = Some(2),
+                    'w' => spec.size = Some(4),
+                    'g' => spec.size = Some(8),
+                    _ => {
+                        if let Some(format) = OutputFormat::from_letter(letter) {
+                            spec.format = Some(format);
+                            continue;
+                        }
+                        return Err(Rich::custom(
+                            span,
+                            format!("Undefined format letter '{letter}'"),
+                        ));
+                    }
+                }
+            }
+            Ok(spec)
+        });
+
+    let macro_name = any()
+        .filter(|c: &char| c.is_alphanumeric() || matches!(c, '_' | '-'))
+        .repeated()
+        .at_least(1)
+        .collect::<String>();
+
     let single_command = choice((
-        just("continue").padded().to(Commands::Continue),
-        just("exit").padded().to(Commands::Exit),
+        just("continue")
+            .ignore_then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(just("all"))
+                    .or_not(),
+            )
+            .padded()
+            .map(|all| {
+                if all.is_some() {
+                    Commands::ContinueAll
+                } else {
+                    Commands::Continue
+                }
+            }),
+        choice((
+            just("run").to(Commands::Run),
+            just("starti").to(Commands::Starti),
+            // After `starti`, which it starts.
+            just("start").to(Commands::Start),
+            just("file")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(word)
+                .map(Commands::File),
+        ))
+        .padded(),
+        just("help")
+            .ignore_then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(word)
+                    .or_not(),
+            )
+            .padded()
+            .map(Commands::Help),
+        choice((
+            choice((just("stepi"), just("si")))
+                .ignore_then(repeat_count)
+                .map(|count| Commands::Stepi(count.unwrap_or(1))),
+            choice((just("nexti"), just("ni")))
+                .ignore_then(repeat_count)
+                .map(|count| Commands::Nexti(count.unwrap_or(1))),
+        ))
+        .padded(),
+        just("find")
+            .ignore_then(just('/').padded().ignore_then(number).or_not())
+            .then(search_range)
+            .then_ignore(just(','))
+            .then(byte_pattern.padded())
+            .padded()
+            .map(|((max, range), pattern)| Commands::Find(max, range, pattern)),
+        just("x")
+            .ignore_then(examine_spec.or_not())
+            .then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(crate::expression::parser())
+                    .or_not(),
+            )
+            .padded()
+            .map(|(spec, address)| Commands::Examine(spec.unwrap_or_default(), address)),
+        just("wait").padded().to(Commands::Wait),
+        just("handle")
+            .padded()
+            .ignore_then(signal.padded())
+            .then(handle_action.repeated().at_least(1).collect::<Vec<_>>())
+            .map(|(signal, actions)| Commands::Handle(signal, actions)),
+        choice((just("backtrace"), just("bt")))
+            .padded()
+            .ignore_then(frame_count.or_not())
+            .map(Commands::Backtrace),
+        choice((just("break"), just("b")))
+            .then_ignore(text::inline_whitespace().at_least(1))
+            .ignore_then(location)
+            .padded()
+            .map(Commands::Break),
+        choice((just("enable").to(true), just("disable").to(false)))
+            .then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(number)
+                    .or_not(),
+            )
+            .padded()
+            .map(|(enable, id)| {
+                if enable {
+                    Commands::Enable(id)
+                } else {
+                    Commands::Disable(id)
+                }
+            }),
+        choice((
+            just("watch").to(sdblib::WatchKind::Write),
+            just("rwatch").to(sdblib::WatchKind::Read),
+            just("awatch").to(sdblib::WatchKind::Access),
+        ))
+        .then_ignore(text::inline_whitespace().at_least(1))
+        .then(location)
+        .padded()
+        .map(|(kind, location)| Commands::Watch(kind, location)),
+        just("delete")
+            .padded()
+            .ignore_then(just("watchpoint"))
+            .then_ignore(text::inline_whitespace().at_least(1))
+            .ignore_then(number)
+            .padded()
+            .map(Commands::DeleteWatchpoint),
+        choice((just("print"), just("p")))
+            .ignore_then(print_format.or_not())
+            .then_ignore(text::inline_whitespace().at_least(1))
+            .then(choice((
+                // Just a name, or it is the start of an expression, as numbers are.
+                symbol
+                    .filter(|name: &String| !name.starts_with(|c: char| c.is_ascii_digit()))
+                    .then_ignore(
+                        text::inline_whitespace()
+                            .then(choice((end(), just(';').ignored(), just('#').ignored())))
+                            .rewind(),
+                    )
+                    .map(|name| (name, None)),
+                crate::expression::parser().map_with(|expression, e| {
+                    let text: &str = e.slice();
+                    (text.trim().to_string(), Some(expression))
+                }),
+            )))
+            .padded()
+            .map(|(format, (text, expression))| match expression {
+                Some(expression) => Commands::PrintExpression(format, text, expression),
+                None => Commands::Print(format, text),
+            }),
+        just("frame")
+            .padded()
+            .ignore_then(number.padded().or_not())
+            .map(Commands::Frame),
+        just("up")
+            .padded()
+            .ignore_then(number.padded().or_not())
+            .map(|count| Commands::Up(count.unwrap_or(1))),
+        just("down")
+            .padded()
+            .ignore_then(number.padded().or_not())
+            .map(|count| Commands::Down(count.unwrap_or(1))),
+        just("return")
+            .ignore_then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(crate::expression::parser())
+                    .or_not(),
+            )
+            .padded()
+            .map(Commands::Return),
+        just("set").padded().ignore_then(choice((
+            just("backtrace-limit")
+                .padded()
+                .ignore_then(choice((
+                    just("unlimited").to(None),
+                    // Like gdb, a limit of 0 means no limit.
+                    number.map(|limit| Some(limit).filter(|limit| *limit != 0)),
+                )))
+                .padded()
+                .map(Commands::SetBacktraceLimit),
+            just('$')
+                .ignore_then(symbol)
+                .then_ignore(just('=').padded())
+                .then(choice((
+                    crate::expression::parser().map(SetValue::Expression),
+                    symbol_offset.map(SetValue::Code),
+                )))
+                .padded()
+                .map(|(name, value)| Commands::SetRegister(name, value)),
+            choice((just("variable"), just("var")))
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(symbol)
+                .then_ignore(just('=').padded())
+                .then(crate::expression::parser())
+                .padded()
+                .map(|(name, value)| Commands::SetVariable(name, value)),
+            just("input-wrap")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetInputWrap),
+            just("logging")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(choice((
+                    just("on")
+                        .ignore_then(
+                            text::inline_whitespace()
+                                .at_least(1)
+                                .ignore_then(word)
+                                .or_not(),
+                        )
+                        .map(|file| Commands::SetLoggingOn(file.map(Into::into))),
+                    just("off").to(Commands::SetLoggingOff),
+                    just("overwrite")
+                        .ignore_then(
+                            text::inline_whitespace()
+                                .at_least(1)
+                                .ignore_then(on_off)
+                                .or_not(),
+                        )
+                        .map(|overwrite| Commands::SetLoggingOverwrite(overwrite.unwrap_or(true))),
+                )))
+                .padded(),
+            just("exit-policy")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(choice((
+                    just("kill").to(sdblib::ExitPolicy::Kill),
+                    just("detach").to(sdblib::ExitPolicy::Detach),
+                    just("ask").to(sdblib::ExitPolicy::Ask),
+                )))
+                .padded()
+                .map(Commands::SetExitPolicy),
+            just("disable-randomization")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetDisableRandomization),
+            just("step-show-changes")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetStepShowChanges),
+            just("verbose")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetVerbose),
+            just("confirm")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetConfirm),
+            just("timing")
+                .then_ignore(text::inline_whitespace().at_least(1))
+                .ignore_then(on_off)
+                .padded()
+                .map(Commands::SetTiming),
+            just("print")
+                .padded()
+                .ignore_then(just("demangle"))
+                .ignore_then(
+                    text::inline_whitespace()
+                        .at_least(1)
+                        .ignore_then(on_off)
+                        .or_not(),
+                )
+                .padded()
+                // A bare `set print demangle` turns it on, as in gdb.
+                .map(|demangle| Commands::SetPrintDemangle(demangle.unwrap_or(true))),
+            just("args")
+                .ignore_then(
+                    text::inline_whitespace()
+                        .at_least(1)
+                        .ignore_then(
+                            word.separated_by(text::inline_whitespace().at_least(1))
+                                .collect::<Vec<_>>(),
+                        )
+                        .or_not(),
+                )
+                .padded()
+                .map(|args| Commands::SetArgs(args.unwrap_or_default())),
+        ))),
+        just("catch")
+            .padded()
+            .ignore_then(just("syscall"))
+            .ignore_then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(
+                        any()
+                            .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_')
+                            .repeated()
+                            .at_least(1)
+                            .collect::<String>()
+                            .try_map(|syscall: String, span| {
+                                parse_syscall(&syscall).ok_or_else(|| {
+                                    Rich::custom(span, format!("unknown syscall {syscall}"))
+                                })
+                            })
+                            .separated_by(text::inline_whitespace().at_least(1))
+                            .collect::<Vec<_>>(),
+                    )
+                    .or_not(),
+            )
+            .padded()
+            .map(|syscalls| Commands::CatchSyscall(syscalls.unwrap_or_default())),
+        just("show").padded().ignore_then(choice((
+            just("print")
+                .padded()
+                .ignore_then(just("demangle"))
+                .padded()
+                .to(Commands::ShowPrintDemangle),
+            just("args").padded().to(Commands::ShowArgs),
+            just("convenience").padded().to(Commands::ShowConvenience),
+            just("logging").padded().to(Commands::ShowLogging),
+            just("exit-policy").padded().to(Commands::ShowExitPolicy),
+            just("disable-randomization")
+                .padded()
+                .to(Commands::ShowDisableRandomization),
+            just("step-show-changes")
+                .padded()
+                .to(Commands::ShowStepShowChanges),
+            just("verbose").padded().to(Commands::ShowVerbose),
+            just("confirm").padded().to(Commands::ShowConfirm),
+            just("timing").padded().to(Commands::ShowTiming),
+            just("user")
+                .ignore_then(
+                    text::inline_whitespace()
+                        .at_least(1)
+                        .ignore_then(macro_name)
+                        .or_not(),
+                )
+                .padded()
+                .map(Commands::ShowUser),
+        ))),
+        just("define")
+            .then_ignore(text::inline_whitespace().at_least(1))
+            .ignore_then(macro_name)
+            .then(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(any().repeated().at_least(1).to_slice())
+                    .or_not(),
+            )
+            .padded()
+            .map(|(name, body): (String, Option<&str>)| {
+                Commands::Define(name, body.map(|body| body.trim_end().to_string()))
+            }),
+        just("info").padded().ignore_then(choice((
+            just("signals").padded().to(Commands::InfoSignals),
+            just("sharedlibrary")
+                .padded()
+                .to(Commands::InfoSharedLibrary),
+            just("args").padded().to(Commands::InfoArgs),
+            just("auxv").padded().to(Commands::InfoAuxv),
+            just("time").padded().to(Commands::InfoTime),
+            just("threads").padded().to(Commands::InfoThreads),
+            just("stack")
+                .ignore_then(repeat_count)
+                .padded()
+                .map(Commands::InfoStack),
+            just("functions")
+                .ignore_then(pattern)
+                .padded()
+                .map(Commands::InfoFunctions),
+            just("watchpoints").padded().to(Commands::InfoWatchpoints),
+            just("breakpoints").padded().to(Commands::InfoBreakpoints),
+            just("registers")
+                .ignore_then(
+                    text::inline_whitespace()
+                        .at_least(1)
+                        .ignore_then(word)
+                        .or_not(),
+                )
+                .padded()
+                .map(Commands::InfoRegisters),
+            just("variables")
+                .ignore_then(pattern)
+                .padded()
+                .map(Commands::InfoVariables),
+        ))),
+        choice((just("exit"), just("quit"), just("q")))
+            .padded()
+            .to(Commands::Exit),
+        just("end").padded().to(Commands::End),
+        // Last, as `s` starts so many other commands.
+        choice((just("step"), just("s")))
+            .ignore_then(repeat_count)
+            .padded()
+            .map(|count| Commands::Step(count.unwrap_or(1))),
     ))
     .recover_with(via_parser(error_command.map(Commands::Error)));
 
-    single_command
-        .separated_by(just(";"))
-        .collect::<Vec<_>>()
-        .map(Commands::Sequence)
+    // Everything from a `#` to the end of the line, like in a shell script.
+    let comment = just('#').then(any().repeated()).padded();
+
+    // The first line of a block may hold a command too: `while $rip < 0x401200 stepi; end`.
+    let block_head = choice((
+        just("while")
+            .then(text::inline_whitespace().at_least(1))
+            .ignore_then(crate::expression::parser())
+            .map(Commands::While),
+        just("if")
+            .then(text::inline_whitespace().at_least(1))
+            .ignore_then(crate::expression::parser())
+            .map(Commands::If),
+        just("else").to(Commands::Else),
+    ))
+    .padded();
+
+    // Empty commands, as in a blank line or `a;;b`, do nothing.
+    choice((
+        block_head
+            .then(single_command.clone().or_not())
+            .map(|(head, command)| vec![Some(head), command]),
+        single_command.or_not().map(|command| vec![command]),
+    ))
+    .separated_by(just(";"))
+    .collect::<Vec<_>>()
+    .then_ignore(comment.or_not())
+    .map(|commands| Commands::Sequence(commands.into_iter().flatten().flatten().collect()))
+}
+
+#[derive(Clone, Copy)]
+enum BlockKind {
+    If,
+    While,
+}
+
+/// An `if` or `while` whose `end` is still to come.
+struct OpenBlock {
+    kind: BlockKind,
+    condition: Expression,
+    body: Vec<Commands>,
+    /// The commands past `else`, once it is seen.
+    otherwise: Option<Vec<Commands>>,
+}
+
+/// Pairs every `if` and `while` with its `end`, moving the commands in between into the block.
+fn nest_blocks(commands: Vec<Commands>) -> std::result::Result<Vec<Commands>, &'static str> {
+    // Innermost last.
+    let mut open: Vec<OpenBlock> = Vec::new();
+    let mut outermost = Vec::new();
+    for command in commands {
+        let (kind, condition) = match command {
+            Commands::If(condition) => (BlockKind::If, condition),
+            Commands::While(condition) => (BlockKind::While, condition),
+            Commands::Else => {
+                match open.last_mut() {
+                    Some(OpenBlock {
+                        kind: BlockKind::If,
+                        otherwise: otherwise @ None,
+                        ..
+                    }) => *otherwise = Some(Vec::new()),
+                    _ => return Err("`else' without a matching `if'."),
+                }
+                continue;
+            }
+            Commands::End => {
+                let block = match open.pop() {
+                    Some(OpenBlock {
+                        kind: BlockKind::If,
+                        condition,
+                        body,
+                        otherwise,
+                    }) => Commands::Conditional(condition, body, otherwise.unwrap_or_default()),
+                    Some(OpenBlock {
+                        kind: BlockKind::While,
+                        condition,
+                        body,
+                        ..
+                    }) => Commands::Loop(condition, body),
+                    None => return Err("`end' without a matching `if' or `while'."),
+                };
+                push_command(&mut open, &mut outermost, block);
+                continue;
+            }
+            command => {
+                push_command(&mut open, &mut outermost, command);
+                continue;
+            }
+        };
+        open.push(OpenBlock {
+            kind,
+            condition,
+            body: Vec::new(),
+            otherwise: None,
+        });
+    }
+    if open.is_empty() {
+        Ok(outermost)
+    } else {
+        Err("Missing `end' for `if' or `while'.")
+    }
+}
+
+/// Adds `command` to the innermost open block, or to `outermost` outside of any.
+fn push_command(open: &mut [OpenBlock], outermost: &mut Vec<Commands>, command: Commands) {
+    match open.last_mut() {
+        Some(block) => block
+            .otherwise
+            .as_mut()
+            .unwrap_or(&mut block.body)
+            .push(command),
+        None => outermost.push(command),
+    }
+}
+
+/// Accepts signal names with or without the `SIG` prefix, in any case.
+fn parse_signal(name: &str) -> Option<sdblib::Signal> {
+    let name = name.to_ascii_uppercase();
+    if name.starts_with("SIG") {
+        name.parse().ok()
+    } else {
+        format!("SIG{name}").parse().ok()
+    }
+}
+
+/// How many words `info stack` shows unless told otherwise.
+const DEFAULT_STACK_WORDS: usize = 16;
+
+/// How many matches `find` reports unless told otherwise with `/max`.
+const DEFAULT_FIND_LIMIT: usize = 100;
+
+const fn apply_handle_action(
+    mut disposition: sdblib::SignalDisposition,
+    action: HandleAction,
+) -> sdblib::SignalDisposition {
+    // Same implications as gdb: stopping means printing, and not printing means not stopping.
+    match action {
+        HandleAction::Stop => {
+            disposition.stop = true;
+            disposition.print = true;
+        }
+        HandleAction::NoStop => disposition.stop = false,
+        HandleAction::Print => disposition.print = true,
+        HandleAction::NoPrint => {
+            disposition.print = false;
+            disposition.stop = false;
+        }
+        HandleAction::Pass => disposition.pass = true,
+        HandleAction::NoPass => disposition.pass = false,
+    }
+    disposition
+}
+
+fn write_signal_table<'a>(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    signals: impl IntoIterator<Item = &'a sdblib::Signal>,
+) -> Result<()> {
+    let yes_no = |b: bool| if b { "Yes" } else { "No" };
+    writeln!(
+        output,
+        "{:<12}{:<6}{:<7}{:<6}",
+        "Signal", "Stop", "Print", "Pass"
+    )?;
+    for signal in signals {
+        let disposition = debugger.signal_disposition(*signal);
+        writeln!(
+            output,
+            "{:<12}{:<6}{:<7}{:<6}",
+            signal.as_str(),
+            yes_no(disposition.stop),
+            yes_no(disposition.print),
+            yes_no(disposition.pass)
+        )?;
+    }
+    Ok(())
+}
+
+/// Start the program again, saying what became of the breakpoints.
+fn relaunch(output: &mut dyn std::io::Write, debugger: &mut sdblib::Debugger) -> Result<()> {
+    let rearmed = debugger.relaunch()?;
+    for (id, location, e) in rearmed.failures {
+        writeln!(output, "Deleted breakpoint {id} at {location}: {e}")?;
+    }
+    if rearmed.count > 0 {
+        writeln!(output, "Re-armed {} breakpoint(s).", rearmed.count)?;
+    }
+    Ok(())
+}
+
+/// Dump `words` words of the stack from the stack pointer up, pointing out the ones that look
+/// like return addresses: they point into the code, right after something a symbol covers.
+fn write_stack(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    words: usize,
+) -> Result<()> {
+    let sp = debugger.register("rsp")?.value;
+    let code = debugger
+        .memory_map()?
+        .into_iter()
+        .filter(sdblib::MemoryRegion::is_executable)
+        .map(|region| region.start..region.end)
+        .collect::<Vec<_>>();
+    let bytes = debugger.read_memory(sp, words * 8)?;
+    for (index, word) in bytes.chunks_exact(8).enumerate() {
+        let value = u64::from_le_bytes(word.try_into()?);
+        let offset = index * 8;
+        write!(
+            output,
+            "{:#x} (rsp+{offset:#04x}): {value:#018x}",
+            sp + offset as u64
+        )?;
+        if code.iter().any(|range| range.contains(&value))
+            && let Some(mut location) = debugger.symbolize(value - 1)
+        {
+            location.offset += 1;
+            write!(output, "  <- {location}")?;
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+/// `value` as the integer an expression can use, if it is one.
+#[allow(clippy::cast_sign_loss)]
+const fn integer_value(value: &sdblib::Value) -> Option<u64> {
+    match value {
+        sdblib::Value::Signed(value) => Some(*value as u64),
+        sdblib::Value::Unsigned(value) | sdblib::Value::Pointer(value) => Some(*value),
+        sdblib::Value::Bool(value) => Some(*value as u64),
+        sdblib::Value::Char(value) => Some(*value as u64),
+        sdblib::Value::Float(_) | sdblib::Value::Bytes(_) => None,
+    }
+}
+
+/// Where `pc` is in the source, gdb style: `heading`, the function and `file:line`, then the
+/// line itself.
+fn write_source_line(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    heading: &str,
+    pc: u64,
+) -> Result<()> {
+    let Some(location) = debugger.source_location(pc) else {
+        writeln!(output, "{heading}{}", describe_pc(debugger, pc))?;
+        return Ok(());
+    };
+    writeln!(
+        output,
+        "{heading}{} at {location}",
+        describe_pc(debugger, pc)
+    )?;
+    let text = std::fs::read_to_string(&location.file)
+        .ok()
+        .and_then(|source| {
+            let index = usize::try_from(location.line).ok()?.checked_sub(1)?;
+            source.lines().nth(index).map(str::to_string)
+        });
+    match text {
+        Some(text) => writeln!(output, "{}\t{text}", location.line)?,
+        None => writeln!(output, "{}\tin {}", location.line, location.file.display())?,
+    }
+    Ok(())
+}
+
+/// `cpu` as a percentage of `elapsed`, over a hundred when several threads ran at once.
+fn cpu_share(cpu: std::time::Duration, elapsed: std::time::Duration) -> String {
+    if elapsed.is_zero() {
+        return "none".to_string();
+    }
+    format!("{:.1}%", cpu.as_secs_f64() / elapsed.as_secs_f64() * 100.0)
+}
+
+fn set_breakpoint(debugger: &mut sdblib::Debugger, location: Location) -> Result<usize> {
+    Ok(match location {
+        Location::Symbol(name) => debugger.set_function_breakpoint(&name, 0)?,
+        Location::SymbolOffset(name, offset) => debugger.set_function_breakpoint(&name, offset)?,
+        Location::Address(address) => debugger.set_breakpoint(address)?,
+        Location::Line(line) => debugger.set_breakpoint(line_address(debugger, line)?)?,
+    })
+}
+
+/// Where the code of line `line` of the source file the program is stopped in starts.
+fn line_address(debugger: &sdblib::Debugger, line: u64) -> Result<u64> {
+    if !debugger.has_process() {
+        return Err(color_eyre::eyre::eyre!(
+            "No default source file: break on a function until the program runs."
+        ));
+    }
+    let location = debugger
+        .source_location(debugger.program_counter()?)
+        .ok_or_else(|| {
+            color_eyre::eyre::eyre!("No line number information for the current location.")
+        })?;
+    Ok(debugger.line_address(&location.file, line)?)
+}
+
+/// The address in the code `location` stands for, resolving functions.
+fn code_address(debugger: &sdblib::Debugger, location: Location) -> Result<u64> {
+    Ok(match location {
+        Location::Address(address) => address,
+        Location::Symbol(name) => debugger.resolve_function(&name)?,
+        Location::SymbolOffset(name, offset) => debugger
+            .resolve_function(&name)?
+            .checked_add(offset)
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!("Location {name}+{offset:#x} is out of range.")
+            })?,
+        Location::Line(line) => line_address(debugger, line)?,
+    })
+}
+
+/// The address `count` units of `size` bytes past `address`, which must not run past the end
+/// of memory.
+fn unit_address(address: u64, count: usize, size: usize) -> Result<u64> {
+    count
+        .checked_mul(size)
+        .and_then(|offset| address.checked_add(u64::try_from(offset).ok()?))
+        .ok_or_else(|| color_eyre::eyre::eyre!("Cannot access memory at address {address:#x}"))
+}
+
+/// Dump memory as `x` does, returning the address and value of the last unit.
+fn write_examine(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    examine: &mut Examine,
+) -> Result<(u64, u64)> {
+    if examine.format == OutputFormat::String {
+        return write_strings(output, debugger, examine);
+    }
+    let length = unit_address(examine.address, examine.count, examine.size)? - examine.address;
+    let bytes = debugger
+        .read_memory(examine.address, usize::try_from(length)?)
+        .map_err(|_| {
+            color_eyre::eyre::eyre!("Cannot access memory at address {:#x}", examine.address)
+        })?;
+    let per_line = match (examine.format, examine.size) {
+        (OutputFormat::Char, _) | (_, 1 | 2) => 8,
+        (_, 4) => 4,
+        _ => 2,
+    };
+    let mut last = (examine.address, 0);
+    for (line, units) in bytes.chunks(examine.size * per_line).enumerate() {
+        let address = unit_address(examine.address, line * per_line, examine.size)?;
+        let label = debugger
+            .symbolize(address)
+            .map_or_else(String::new, |location| format!(" <{location}>"));
+        write!(output, "{address:#x}{label}:")?;
+        for (index, unit) in units.chunks(examine.size).enumerate() {
+            let mut value = [0; 8];
+            value[..unit.len()].copy_from_slice(unit);
+            let value = u64::from_le_bytes(value);
+            write!(
+                output,
+                "\t{}",
+                format_unit(value, examine.format, examine.size)
+            )?;
+            last = (unit_address(address, index, examine.size)?, value);
+        }
+        writeln!(output)?;
+    }
+    Ok(last)
+}
+
+/// `x/s`: one string per line, `examine.end` becoming the address past the last NUL. The value
+/// returned for the last one is its first byte.
+fn write_strings(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    examine: &mut Examine,
+) -> Result<(u64, u64)> {
+    let mut address = examine.address;
+    let mut last = (address, 0);
+    for _ in 0..examine.count {
+        let mut bytes = debugger
+            .read_c_string(address, MAX_STRING_LENGTH + 1)
+            .map_err(|_| color_eyre::eyre::eyre!("Cannot access memory at address {address:#x}"))?;
+        let truncated = bytes.len() > MAX_STRING_LENGTH;
+        bytes.truncate(MAX_STRING_LENGTH);
+        let label = debugger
+            .symbolize(address)
+            .map_or_else(String::new, |location| format!(" <{location}>"));
+        writeln!(
+            output,
+            "{address:#x}{label}:\t{}",
+            sdblib::quote_bytes(&bytes, truncated)
+        )?;
+        last = (address, bytes.first().copied().map_or(0, u64::from));
+        // A cut string goes on where it was cut.
+        address = unit_address(address, bytes.len() + usize::from(!truncated), 1)?;
+    }
+    examine.end = address;
+    Ok(last)
+}
+
+/// One unit of `x` output, `value` being `size` bytes wide.
+fn format_unit(value: u64, format: OutputFormat, size: usize) -> String {
+    let bits = u32::try_from(size * 8).unwrap_or(64);
+    let shift = 64 - bits;
+    let signed = (value << shift).cast_signed() >> shift;
+    match format {
+        OutputFormat::Hex => format!("{value:#0width$x}", width = size * 2 + 2),
+        OutputFormat::Decimal => signed.to_string(),
+        OutputFormat::Unsigned => value.to_string(),
+        OutputFormat::Binary => format!("{value:0width$b}", width = size * 8),
+        OutputFormat::String => unreachable!("strings are written by write_strings"),
+        OutputFormat::Char => {
+            let byte = value.to_le_bytes()[0];
+            format!("{} '{}'", byte.cast_signed(), byte.escape_ascii())
+        }
+    }
+}
+
+/// A value as `print/F` shows it: as `x` would a giant word, without the zero padding.
+fn format_integer(value: u64, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Hex => format!("{value:#x}"),
+        OutputFormat::Binary => format!("{value:b}"),
+        format => format_unit(value, format, 8),
+    }
+}
+
+/// Where `pc` is, gdb style: `0x... in function ()`, or just the address when unknown.
+fn describe_pc(debugger: &sdblib::Debugger, pc: u64) -> String {
+    debugger.symbolize(pc).map_or_else(
+        || format!("{pc:#018x}"),
+        |location| format!("{pc:#018x} in {location} ()"),
+    )
+}
+
+/// Like [`describe_pc`], from what the backtrace found out about the frame.
+fn describe_frame(frame: &sdblib::Frame) -> String {
+    frame.symbol.as_ref().map_or_else(
+        || format!("{:#018x}", frame.pc),
+        |location| format!("{:#018x} in {location} ()", frame.pc),
+    )
+}
+
+fn write_functions(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    pattern: Option<&regex::Regex>,
+) -> Result<()> {
+    match pattern {
+        Some(pattern) => writeln!(
+            output,
+            "All functions matching regular expression \"{pattern}\":"
+        )?,
+        None => writeln!(output, "All defined functions:")?,
+    }
+    writeln!(output)?;
+    let mut count = 0;
+    for function in debugger.functions()? {
+        // Match what the user reads, so source names find mangled symbols.
+        let demangled = sdblib::demangle(&function.name);
+        if pattern.is_some_and(|pattern| !pattern.is_match(&demangled)) {
+            continue;
+        }
+        let name = if debugger.print_demangle() {
+            &demangled
+        } else {
+            &function.name
+        };
+        writeln!(output, "{:#018x}  {name}", function.address)?;
+        count += 1;
+    }
+    writeln!(output)?;
+    writeln!(
+        output,
+        "{count} function{} matched.",
+        if count == 1 { "" } else { "s" }
+    )?;
+    Ok(())
+}
+
+fn write_global_variables(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    pattern: Option<&regex::Regex>,
+) -> Result<()> {
+    match pattern {
+        Some(pattern) => writeln!(
+            output,
+            "All variables matching regular expression \"{pattern}\":"
+        )?,
+        None => writeln!(output, "All defined variables:")?,
+    }
+    writeln!(output)?;
+    let mut count = 0;
+    for variable in debugger.global_variables()? {
+        if pattern.is_some_and(|pattern| !pattern.is_match(&variable.name)) {
+            continue;
+        }
+        let address = variable.address.map_or_else(
+            || "<thread-local>".to_string(),
+            |address| format!("{address:#018x}"),
+        );
+        writeln!(
+            output,
+            "{address:<18}  {} {};",
+            variable.type_name, variable.name
+        )?;
+        count += 1;
+    }
+    writeln!(output)?;
+    writeln!(
+        output,
+        "{count} variable{} matched.",
+        if count == 1 { "" } else { "s" }
+    )?;
+    Ok(())
+}
+
+/// How gdb introduces a watchpoint of this sort.
+const fn watchpoint_title(watchpoint: &sdblib::Watchpoint) -> &'static str {
+    match (watchpoint.backend, watchpoint.kind) {
+        (sdblib::WatchBackend::Software, _) => "Watchpoint",
+        (_, sdblib::WatchKind::Write) => "Hardware watchpoint",
+        (_, sdblib::WatchKind::Read) => "Hardware read watchpoint",
+        (_, sdblib::WatchKind::Access) => "Hardware access (read/write) watchpoint",
+    }
+}
+
+fn find_watchpoint(debugger: &sdblib::Debugger, id: usize) -> Option<&sdblib::Watchpoint> {
+    debugger
+        .watchpoints()
+        .iter()
+        .find(|watchpoint| watchpoint.id == id)
+}
+
+/// Watched memory as an integer when it has the size of one, as bytes otherwise.
+fn format_watched(bytes: &[u8]) -> String {
+    match bytes.len() {
+        len @ (1 | 2 | 4 | 8) => {
+            let mut word = [0u8; 8];
+            word[..len].copy_from_slice(bytes);
+            // Sign extend from the watched size.
+            let shift = 64 - 8 * u32::try_from(len).unwrap_or(8);
+            (i64::from_le_bytes(word) << shift >> shift).to_string()
+        }
+        _ => format_value(&sdblib::Value::Bytes(bytes.to_vec())),
+    }
 }
 
-fn parse_command(command_str: &str, mut output: &mut dyn std::io::Write) -> Option<Commands> {
-    let (command, errs) = parser().parse(command_str.trim()).into_output_errors();
+/// Say which pending breakpoints were set, or deleted as they could not be, since last asked,
+/// now that a library defines their functions.
+fn write_activated_breakpoints(
+    output: &mut dyn std::io::Write,
+    debugger: &mut sdblib::Debugger,
+) -> Result<()> {
+    for (id, location, e) in debugger.take_failed_breakpoints() {
+        writeln!(output, "Deleted breakpoint {id} at {location}: {e}")?;
+    }
+    for breakpoint in debugger.take_activated_breakpoints() {
+        writeln!(
+            output,
+            "Breakpoint {} now active at {:#x}.",
+            breakpoint.id, breakpoint.address
+        )?;
+    }
+    Ok(())
+}
+
+fn write_stop_reason(
+    output: &mut dyn std::io::Write,
+    debugger: &mut sdblib::Debugger,
+    reason: sdblib::StopReason,
+) -> Result<()> {
+    write_activated_breakpoints(output, debugger)?;
+    match reason {
+        sdblib::StopReason::Breakpoint(_, id) => {
+            // The thread that hit it sits on the breakpoint, which is not necessarily the
+            // thread `program_counter` reads.
+            let pc = debugger
+                .breakpoints()
+                .iter()
+                .find(|breakpoint| breakpoint.id == id)
+                .map_or(0, |breakpoint| breakpoint.address);
+            write_source_line(output, debugger, &format!("Breakpoint {id}, "), pc)?;
+        }
+        sdblib::StopReason::Watchpoint(_, id) => {
+            if let Some(watchpoint) = find_watchpoint(debugger, id) {
+                writeln!(
+                    output,
+                    "{} {id}: {}",
+                    watchpoint_title(watchpoint),
+                    watchpoint.expression
+                )?;
+                writeln!(output)?;
+                if watchpoint.old_value() == watchpoint.value() {
+                    writeln!(output, "Value = {}", format_watched(watchpoint.value()))?;
+                } else {
+                    writeln!(
+                        output,
+                        "Old value = {}",
+                        format_watched(watchpoint.old_value())
+                    )?;
+                    writeln!(output, "New value = {}", format_watched(watchpoint.value()))?;
+                }
+            }
+        }
+        sdblib::StopReason::Exited(pid, code) => {
+            writeln!(output, "Process {pid} exited with code {code}")?;
+        }
+        sdblib::StopReason::Killed(pid, signal) => {
+            writeln!(output, "Process {pid} was killed by {signal}")?;
+        }
+        sdblib::StopReason::Interrupted(pid) => {
+            writeln!(output, "Process {pid} interrupted")?;
+            write_source_line(output, debugger, "", debugger.program_counter()?)?;
+        }
+        sdblib::StopReason::Stepped(_) => {
+            write_source_line(output, debugger, "", debugger.program_counter()?)?;
+        }
+        sdblib::StopReason::Signaled(pid, signal) => {
+            writeln!(output, "Process {pid} received {signal}")?;
+            if sdblib::is_fault(signal)
+                && let Ok(info) = debugger.signal_info(pid)
+            {
+                let description = sdblib::describe_si_code(signal, info.si_code)
+                    .map_or_else(String::new, |description| format!(" ({description})"));
+                match sdblib::fault_address(&info) {
+                    Some(address) => writeln!(
+                        output,
+                        "Fault address {address:#x}, si_code {}{description}",
+                        info.si_code
+                    )?,
+                    None => writeln!(output, "si_code {}{description}", info.si_code)?,
+                }
+            }
+        }
+        sdblib::StopReason::Syscall(pid, stop) => {
+            let name = syscall_display_name(stop.number());
+            match stop {
+                sdblib::SyscallStop::Entry { number, args } => writeln!(
+                    output,
+                    "Process {pid} called syscall {name}({})",
+                    sdblib::decode_syscall_arguments(pid, number, &args).join(", ")
+                )?,
+                sdblib::SyscallStop::Exit {
+                    number,
+                    return_value,
+                } => writeln!(
+                    output,
+                    "Process {pid} returned from syscall {name} with {}",
+                    sdblib::decode_syscall_return(number, return_value)
+                )?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn syscall_display_name(number: u64) -> String {
+    sdblib::syscall_name(number).map_or_else(|| number.to_string(), str::to_string)
+}
+
+/// Accepts syscall names as well as numbers, like gdb.
+fn parse_syscall(syscall: &str) -> Option<u64> {
+    syscall
+        .parse()
+        .ok()
+        .or_else(|| sdblib::syscall_number(syscall))
+}
+
+fn write_backtrace(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    count: Option<FrameCount>,
+) -> Result<()> {
+    let (backtrace, frames, more_follow) = match count {
+        Some(FrameCount::Innermost(count)) => {
+            // Ask for one more frame than we show to know whether the stack goes on.
+            let backtrace = debugger.backtrace(count.saturating_add(1))?;
+            let more_follow = backtrace.frames.len() > count;
+            (backtrace, 0..count, more_follow)
+        }
+        Some(FrameCount::Outermost(count)) => {
+            let backtrace = debugger.backtrace(usize::MAX)?;
+            let len = backtrace.frames.len();
+            (backtrace, len.saturating_sub(count)..len, false)
+        }
+        None => {
+            let backtrace = debugger.backtrace(usize::MAX)?;
+            let more_follow = debugger
+                .backtrace_limit()
+                .is_some_and(|limit| backtrace.frames.len() >= limit && !backtrace.corrupted);
+            (backtrace, 0..usize::MAX, more_follow)
+        }
+    };
+    for (index, frame) in backtrace
+        .frames
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| frames.contains(index))
+    {
+        writeln!(output, "#{index:<3}{}", describe_frame(frame))?;
+    }
+    if more_follow {
+        writeln!(output, "(More stack frames follow...)")?;
+    }
+    if backtrace.corrupted {
+        writeln!(
+            output,
+            "Backtrace stopped: previous frame inner to this frame (corrupt stack?)"
+        )?;
+    }
+    Ok(())
+}
+
+fn select_frame(
+    output: &mut dyn std::io::Write,
+    debugger: &mut sdblib::Debugger,
+    index: usize,
+) -> Result<()> {
+    let frame = debugger.select_frame(index)?;
+    writeln!(output, "#{index:<3}{}", describe_frame(&frame))?;
+    Ok(())
+}
+
+fn format_value(value: &sdblib::Value) -> String {
+    match value {
+        sdblib::Value::Signed(value) => value.to_string(),
+        sdblib::Value::Unsigned(value) => value.to_string(),
+        sdblib::Value::Float(value) => value.to_string(),
+        sdblib::Value::Bool(value) => value.to_string(),
+        sdblib::Value::Char(value) => format!("{value} {:?}", char::from(*value)),
+        sdblib::Value::Pointer(value) => format!("{value:#x}"),
+        sdblib::Value::Bytes(bytes) => format!(
+            "{{{}}}",
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:#04x}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// A register's value the way its kind reads best.
+fn format_register(debugger: &sdblib::Debugger, register: &sdblib::Register) -> String {
+    let value = register.value;
+    match register.kind {
+        sdblib::RegisterKind::Integer => value.cast_signed().to_string(),
+        sdblib::RegisterKind::Pointer => format!("{value:#x}"),
+        sdblib::RegisterKind::CodePointer => debugger.symbolize(value).map_or_else(
+            || format!("{value:#x}"),
+            |location| format!("{value:#x} <{location}>"),
+        ),
+        sdblib::RegisterKind::Flags => {
+            format!("[ {} ]", sdblib::decode_rflags(value).join(" "))
+        }
+    }
+}
+
+/// The registers that differ from `before`, one per line as `rax: 0 -> 5`.
+fn write_register_changes(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    before: &[sdblib::Register],
+) -> Result<()> {
+    // Nothing to compare with once the program is gone.
+    let Ok(after) = debugger.registers() else {
+        return Ok(());
+    };
+    for (old, new) in before.iter().zip(&after) {
+        if old.name == new.name && old.value != new.value {
+            writeln!(
+                output,
+                "{}: {} -> {}",
+                new.name,
+                format_register_change(old),
+                format_register_change(new)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// A register value in a change, shorter than [`format_register`]: code pointers go without
+/// their symbol, the step already told where it stopped.
+fn format_register_change(register: &sdblib::Register) -> String {
+    let value = register.value;
+    match register.kind {
+        sdblib::RegisterKind::Integer => value.cast_signed().to_string(),
+        sdblib::RegisterKind::Pointer | sdblib::RegisterKind::CodePointer => format!("{value:#x}"),
+        sdblib::RegisterKind::Flags => {
+            format!("[ {} ]", sdblib::decode_rflags(value).join(" "))
+        }
+    }
+}
+
+/// One register, gdb style: name, hex, and the value the way its kind reads best.
+fn write_register(
+    output: &mut dyn std::io::Write,
+    debugger: &sdblib::Debugger,
+    register: &sdblib::Register,
+) -> Result<()> {
+    writeln!(
+        output,
+        "{:<15}{:<19}{}",
+        register.name,
+        format!("{:#x}", register.value),
+        format_register(debugger, register)
+    )?;
+    Ok(())
+}
+
+fn write_variables(output: &mut dyn std::io::Write, variables: &[sdblib::Variable]) -> Result<()> {
+    for variable in variables {
+        match &variable.value {
+            Some(value) => writeln!(output, "{} = {}", variable.name, format_value(value))?,
+            None => writeln!(
+                output,
+                "{} = <not available at the current location>",
+                variable.name
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Parses every line of `command_str`, as a script or a pasted block has several, into one
+/// sequence. `None` if no line parsed at all.
+fn parse_command(command_str: &str, output: &mut dyn std::io::Write) -> Option<Commands> {
+    let mut parsed = None;
+    for line in command_str.lines() {
+        if let Some(Commands::Sequence(commands)) = parse_line(line, output) {
+            parsed.get_or_insert_with(Vec::new).extend(commands);
+        }
+    }
+    match nest_blocks(parsed?) {
+        Ok(commands) => Some(Commands::Sequence(commands)),
+        Err(e) => {
+            let _ = writeln!(output, "{e}");
+            None
+        }
+    }
+}
+
+fn parse_line(command_str: &str, mut output: &mut dyn std::io::Write) -> Option<Commands> {
+    // Spans are byte offsets into the parsed text, so report against exactly that text.
+    let command_str = command_str.trim();
+    let (command, errs) = parser().parse(command_str).into_output_errors();
 
     for e in errs {
         Report::build(ReportKind::Error, ((), e.span().into_range()))
@@ -57,40 +2037,1769 @@ fn parse_command(command_str: &str, mut output: &mut dyn std::io::Write) -> Opti
     command
 }
 
+#[allow(clippy::too_many_lines)]
 fn run_command_ast(
     command: Commands,
+    session: &mut Session,
     debugger: &mut sdblib::Debugger,
     mut output: &mut dyn std::io::Write,
-) -> Result<bool> {
+) -> Result<CommandOutcome> {
     match command {
-        Commands::Continue => {
-            debugger.continue_execution()?;
+        command @ (Commands::Continue | Commands::ContinueAll) => {
+            let reason = if matches!(command, Commands::ContinueAll) {
+                debugger.continue_all()?
+            } else {
+                debugger.continue_current()?
+            };
+            for resumed in debugger.take_resumed_signals() {
+                if resumed.passed {
+                    writeln!(
+                        output,
+                        "delivering {} to process {}",
+                        resumed.signal, resumed.pid
+                    )?;
+                } else if session.verbose {
+                    writeln!(
+                        output,
+                        "dropping {}, not passed to process {}",
+                        resumed.signal, resumed.pid
+                    )?;
+                }
+            }
+            for report in debugger.take_signal_reports() {
+                writeln!(output, "Process {} received {}", report.pid, report.signal)?;
+            }
+            write_stop_reason(output, debugger, reason)?;
+        }
+        Commands::Wait => {
+            // Resuming blocks until the next stop, so by the time a command runs the inferior
+            // is never running.
+            if debugger.has_process() {
+                writeln!(output, "The program is already stopped.")?;
+            } else if debugger.launched_program().is_some() {
+                writeln!(output, "The program is not being run.")?;
+            } else {
+                writeln!(output, "No program is being debugged.")?;
+            }
+        }
+        Commands::Handle(signal, actions) => {
+            let disposition = actions
+                .into_iter()
+                .fold(debugger.signal_disposition(signal), apply_handle_action);
+            debugger.set_signal_disposition(signal, disposition);
+            write_signal_table(output, debugger, &[signal])?;
+        }
+        Commands::InfoSignals => {
+            write_signal_table(
+                output,
+                debugger,
+                sdblib::Signal::iterator().collect::<Vec<_>>().iter(),
+            )?;
+        }
+        Commands::Starti => {
+            relaunch(output, debugger)?;
+            writeln!(
+                output,
+                "stopped at entry point {:#x}",
+                debugger.program_counter()?
+            )?;
+        }
+        Commands::Start => {
+            relaunch(output, debugger)?;
+            if let Some(reason) = debugger.run_to_function("main")? {
+                write_stop_reason(output, debugger, reason)?;
+            } else {
+                write_activated_breakpoints(output, debugger)?;
+                write_source_line(output, debugger, "", debugger.program_counter()?)?;
+            }
+        }
+        Commands::Help(None) => {
+            writeln!(output, "List of commands:")?;
+            writeln!(output)?;
+            for (name, help) in COMMAND_HELP {
+                let summary = help.lines().next().unwrap_or_default();
+                writeln!(output, "{name} -- {summary}")?;
+            }
+            writeln!(output)?;
+            writeln!(
+                output,
+                "Type \"help\" followed by command name for full documentation."
+            )?;
+        }
+        Commands::Help(Some(name)) => {
+            let help = COMMAND_HELP
+                .iter()
+                .find(|(command, _)| command.split(", ").any(|alias| alias == name))
+                .map(|(_, help)| help)
+                .ok_or_else(|| {
+                    color_eyre::eyre::eyre!("Undefined command: \"{name}\".  Try \"help\".")
+                })?;
+            writeln!(output, "{help}")?;
+        }
+        command @ (Commands::Stepi(count) | Commands::Nexti(count)) => {
+            let before = session.register_snapshot(debugger);
+            let stepped = if matches!(command, Commands::Nexti(_)) {
+                debugger.next_instructions(count)?
+            } else {
+                debugger.step_instructions(count)?
+            };
+            match stepped.reason {
+                Some(reason) => {
+                    write_stop_reason(output, debugger, reason)?;
+                    if stepped.steps < count {
+                        writeln!(output, "Stopped after {} of {count} steps.", stepped.steps)?;
+                    }
+                }
+                None => writeln!(
+                    output,
+                    "{}",
+                    describe_pc(debugger, debugger.program_counter()?)
+                )?,
+            }
+            if let Some(before) = before {
+                write_register_changes(output, debugger, &before)?;
+            }
+        }
+        Commands::Step(count) => {
+            let before = session.register_snapshot(debugger);
+            let pid = debugger.current_process()?;
+            let mut interrupted = false;
+            for step in 0..count {
+                let stepped = debugger.source_step(pid)?;
+                if let Some(reason) = stepped.reason {
+                    write_stop_reason(output, debugger, reason)?;
+                    if step + 1 < count {
+                        writeln!(output, "Stopped after {step} of {count} steps.")?;
+                    }
+                    interrupted = true;
+                    break;
+                }
+            }
+            if !interrupted {
+                write_source_line(output, debugger, "", debugger.program_counter()?)?;
+            }
+            if let Some(before) = before {
+                write_register_changes(output, debugger, &before)?;
+            }
+        }
+        Commands::Examine(spec, address) => {
+            let address = address
+                .map(|address| address.evaluate(debugger, &session.convenience))
+                .transpose()?;
+            let mut examine = session.examine(spec, address)?;
+            let last = write_examine(output, debugger, &mut examine)?;
+            session.last_examine = Some(examine);
+            // Like gdb, `$_` is the last unit shown and `$__` what it holds.
+            session.convenience.insert("_".to_string(), last.0);
+            session.convenience.insert("__".to_string(), last.1);
+        }
+        Commands::Find(max, range, pattern) => {
+            let pid = debugger.current_process()?;
+            let matches =
+                debugger.search_memory(pid, range, &pattern, max.unwrap_or(DEFAULT_FIND_LIMIT))?;
+            for address in &matches {
+                match debugger.symbolize(*address) {
+                    Some(location) => writeln!(output, "{address:#x} <{location}>")?,
+                    None => writeln!(output, "{address:#x}")?,
+                }
+            }
+            if matches.is_empty() {
+                writeln!(output, "Pattern not found.")?;
+            } else {
+                writeln!(output, "{} pattern(s) found.", matches.len())?;
+            }
+        }
+        Commands::File(path) => {
+            writeln!(output, "Reading symbols from {path}...")?;
+            let file = debugger.load_program(&path)?;
+            if !file.has_debug_info {
+                writeln!(output, "(No debugging symbols found in {path})")?;
+            }
+            writeln!(output, "Entry point: {:#x}", file.entry_point)?;
+        }
+        Commands::Run => {
+            relaunch(output, debugger)?;
+            let reason = debugger.continue_current()?;
+            write_stop_reason(output, debugger, reason)?;
+        }
+        Commands::InfoThreads => {
+            let pid = debugger.current_process()?;
+            let current = debugger.current_thread()?;
+            writeln!(output, "  {:<18}{:<18}Where", "Thread", "Name")?;
+            for thread in debugger.threads()? {
+                let id = if thread.tid == pid {
+                    pid.to_string()
+                } else {
+                    format!("{pid}.{}", thread.tid)
+                };
+                writeln!(
+                    output,
+                    "{} {id:<18}{:<18}{}",
+                    if thread.tid == current { '*' } else { ' ' },
+                    thread.name,
+                    thread
+                        .pc
+                        .map_or_else(|| "(running)".to_string(), |pc| describe_pc(debugger, pc))
+                )?;
+            }
+        }
+        Commands::InfoSharedLibrary => {
+            let libraries = debugger.shared_libraries()?;
+            if libraries.is_empty() {
+                writeln!(output, "No shared libraries loaded at this time.")?;
+            }
+            writeln!(output, "{:<20}{:<10}Shared Object", "From", "Symbols")?;
+            for library in libraries {
+                writeln!(
+                    output,
+                    "{:<20}{:<10}{}",
+                    format!("{:#x}", library.load_address),
+                    if library.has_symbol_table {
+                        "Yes"
+                    } else {
+                        "stripped"
+                    },
+                    library.path
+                )?;
+            }
+        }
+        Commands::InfoFunctions(pattern) => {
+            write_functions(output, debugger, pattern.as_ref())?;
+        }
+        Commands::InfoVariables(pattern) => {
+            write_global_variables(output, debugger, pattern.as_ref())?;
+        }
+        Commands::Print(format, name)
+            if sdblib::is_register_name(&name) && name.starts_with('$') =>
+        {
+            let register = debugger.register(&name)?;
+            session.set_last_value(register.value);
+            let value = format.map_or_else(
+                || format_register(debugger, &register),
+                |format| format_integer(register.value, format),
+            );
+            writeln!(output, "{name} = {value}")?;
+        }
+        Commands::Print(format, name) if name.starts_with('$') => {
+            let value = crate::expression::Expression::Register(name[1..].to_string())
+                .evaluate(debugger, &session.convenience)?;
+            session.set_last_value(value);
+            let value = format_integer(value, format.unwrap_or(OutputFormat::Unsigned));
+            writeln!(output, "{name} = {value}")?;
+        }
+        Commands::PrintExpression(format, text, expression) => {
+            let value = expression.evaluate(debugger, &session.convenience)?;
+            session.set_last_value(value);
+            let value = format_integer(value, format.unwrap_or(OutputFormat::Unsigned));
+            writeln!(output, "{text} = {value}")?;
+        }
+        Commands::Print(format, name) => {
+            // Arguments shadow globals, but without a frame there are only globals.
+            let argument = debugger
+                .arguments()
+                .ok()
+                .and_then(|arguments| arguments.into_iter().find(|arg| arg.name == name));
+            let variable = match argument {
+                Some(argument) => argument,
+                None => debugger.global_value(&name)?,
+            };
+            let integer = variable.value.as_ref().and_then(integer_value);
+            if let Some(value) = integer {
+                session.set_last_value(value);
+            }
+            // Formats are for integers; anything else is shown as its type says.
+            match (format, integer) {
+                (Some(format), Some(value)) => {
+                    writeln!(output, "{name} = {}", format_integer(value, format))?;
+                }
+                _ => write_variables(output, &[variable])?,
+            }
+        }
+        Commands::InfoRegisters(name) => match name {
+            Some(name) => write_register(output, debugger, &debugger.register(&name)?)?,
+            None => {
+                for register in &debugger.registers()? {
+                    write_register(output, debugger, register)?;
+                }
+            }
+        },
+        Commands::SetRegister(name, value) => {
+            let value = match value {
+                SetValue::Expression(expression) => {
+                    expression.evaluate(debugger, &session.convenience)?
+                }
+                SetValue::Code(location) => code_address(debugger, location)?,
+            };
+            if sdblib::is_register_name(&name) {
+                debugger.set_register(&name, value)?;
+            } else {
+                session.convenience.insert(name, value);
+            }
+        }
+        Commands::SetVariable(name, value) => {
+            let value = value.evaluate(debugger, &session.convenience)?;
+            debugger.set_variable(&name, value)?;
+        }
+        Commands::InfoTime => {
+            let pid = debugger.current_process()?;
+            let times = sdblib::process_times(pid)?;
+            writeln!(
+                output,
+                "CPU time: {:.2}s user, {:.2}s system.",
+                times.user.as_secs_f64(),
+                times.system.as_secs_f64()
+            )?;
+            writeln!(
+                output,
+                "Running for {:.2}s, {} of it on a CPU.",
+                times.elapsed.as_secs_f64(),
+                cpu_share(times.cpu(), times.elapsed)
+            )?;
+            // Stopped, the program uses no CPU: this is the use while it ran in between.
+            if let Some((last_pid, last)) = session.last_times
+                && last_pid == pid
+            {
+                let cpu = times.cpu().saturating_sub(last.cpu());
+                let elapsed = times.elapsed.saturating_sub(last.elapsed);
+                writeln!(
+                    output,
+                    "Since the last \"info time\": {:.2}s of CPU in {:.2}s, {}.",
+                    cpu.as_secs_f64(),
+                    elapsed.as_secs_f64(),
+                    cpu_share(cpu, elapsed)
+                )?;
+            }
+            session.last_times = Some((pid, times));
+        }
+        Commands::InfoAuxv => {
+            for entry in debugger.auxv()? {
+                let value = match entry.format() {
+                    sdblib::AuxvFormat::Decimal => entry.value.to_string(),
+                    sdblib::AuxvFormat::Hex => format!("{:#x}", entry.value),
+                    sdblib::AuxvFormat::String => {
+                        debugger.read_c_string(entry.value, 4096).map_or_else(
+                            |_| format!("{:#x}", entry.value),
+                            |bytes| {
+                                format!(
+                                    "{:#x} \"{}\"",
+                                    entry.value,
+                                    String::from_utf8_lossy(&bytes)
+                                )
+                            },
+                        )
+                    }
+                };
+                writeln!(
+                    output,
+                    "{:<4} {:<20} {:<40} {value}",
+                    entry.key,
+                    entry.name().unwrap_or("???"),
+                    entry.description().unwrap_or("")
+                )?;
+            }
+        }
+        Commands::InfoStack(words) => {
+            write_stack(output, debugger, words.unwrap_or(DEFAULT_STACK_WORDS))?;
+        }
+        Commands::InfoArgs => {
+            let arguments = debugger.arguments()?;
+            if arguments.is_empty() {
+                writeln!(output, "No arguments.")?;
+            }
+            write_variables(output, &arguments)?;
+        }
+        Commands::Backtrace(count) => {
+            write_backtrace(output, debugger, count)?;
+        }
+        Commands::SetBacktraceLimit(limit) => {
+            debugger.set_backtrace_limit(limit);
+        }
+        Commands::SetInputWrap(wrap) => {
+            return Ok(CommandOutcome::WrapInput(wrap));
+        }
+        Commands::SetLoggingOn(file) => {
+            if let Some(file) = file {
+                session.log_path = file;
+            }
+            let mut options = std::fs::File::options();
+            if session.log_overwrite {
+                options.write(true).create(true).truncate(true);
+            } else {
+                options.append(true).create(true);
+            }
+            session.log_file = Some(options.open(&session.log_path)?);
+            writeln!(output, "Copying output to {}.", session.log_path.display())?;
+        }
+        Commands::SetLoggingOff => {
+            if session.log_file.take().is_some() {
+                writeln!(output, "Done logging to {}.", session.log_path.display())?;
+            }
+        }
+        Commands::SetLoggingOverwrite(overwrite) => session.log_overwrite = overwrite,
+        Commands::SetExitPolicy(policy) => {
+            debugger.set_exit_policy(policy);
+        }
+        Commands::ShowExitPolicy => {
+            let policy = match debugger.exit_policy() {
+                sdblib::ExitPolicy::Kill => "kill",
+                sdblib::ExitPolicy::Detach => "detach",
+                sdblib::ExitPolicy::Ask => "ask",
+            };
+            writeln!(
+                output,
+                "Programs launched by sdb are handled by \"{policy}\" on exit; attached ones are detached."
+            )?;
+        }
+        Commands::SetDisableRandomization(disable) => {
+            debugger.set_disable_randomization(disable);
+        }
+        Commands::ShowDisableRandomization => {
+            writeln!(
+                output,
+                "Disabling randomization of the address space of launched programs is {}.",
+                if debugger.disable_randomization() {
+                    "on"
+                } else {
+                    "off"
+                }
+            )?;
+        }
+        Commands::SetStepShowChanges(show) => session.step_show_changes = show,
+        Commands::SetVerbose(verbose) => session.verbose = verbose,
+        Commands::SetConfirm(confirm) => session.confirm = confirm,
+        Commands::ShowConfirm => {
+            writeln!(
+                output,
+                "Asking before quitting kills the program is {}.",
+                if session.confirm { "on" } else { "off" }
+            )?;
+        }
+        Commands::SetTiming(timing) => session.timing = timing,
+        Commands::ShowTiming => {
+            writeln!(
+                output,
+                "Showing how long each command takes is {}.",
+                if session.timing { "on" } else { "off" }
+            )?;
+        }
+        Commands::ShowVerbose => {
+            writeln!(
+                output,
+                "Verbosity is {}.",
+                if session.verbose { "on" } else { "off" }
+            )?;
+        }
+        Commands::ShowStepShowChanges => {
+            writeln!(
+                output,
+                "Showing the registers a step changes is {}.",
+                if session.step_show_changes {
+                    "on"
+                } else {
+                    "off"
+                }
+            )?;
+        }
+        Commands::ShowLogging => {
+            if session.log_file.is_some() {
+                writeln!(
+                    output,
+                    "Currently logging to \"{}\".",
+                    session.log_path.display()
+                )?;
+            } else {
+                writeln!(
+                    output,
+                    "Future logs will be written to {}.",
+                    session.log_path.display()
+                )?;
+            }
+            writeln!(
+                output,
+                "Logs will be {} the log file.",
+                if session.log_overwrite {
+                    "written over"
+                } else {
+                    "appended to"
+                }
+            )?;
+        }
+        Commands::SetPrintDemangle(demangle) => {
+            debugger.set_print_demangle(demangle);
+        }
+        Commands::ShowPrintDemangle => {
+            writeln!(
+                output,
+                "Demangling of encoded C++/ObjC names when displaying symbols is {}.",
+                if debugger.print_demangle() {
+                    "on"
+                } else {
+                    "off"
+                }
+            )?;
+        }
+        Commands::SetArgs(args) => {
+            debugger.set_launch_args(args)?;
+        }
+        Commands::CatchSyscall(numbers) => {
+            if numbers.is_empty() {
+                debugger.catch_syscall(None);
+                writeln!(output, "Catchpoint (any syscall)")?;
+            }
+            for number in numbers {
+                debugger.catch_syscall(Some(number));
+                writeln!(
+                    output,
+                    "Catchpoint (syscall '{}' [{number}])",
+                    syscall_display_name(number)
+                )?;
+            }
+        }
+        Commands::Break(location) if !debugger.has_process() => {
+            let id = set_breakpoint(debugger, location.clone())?;
+            writeln!(
+                output,
+                "Breakpoint {id} at {location}, set when the program starts."
+            )?;
+        }
+        Commands::Break(
+            ref location @ (Location::Symbol(ref name) | Location::SymbolOffset(ref name, _)),
+        ) if debugger.resolve_function(name).is_err() => {
+            let id = set_breakpoint(debugger, location.clone())?;
+            writeln!(output, "Function \"{name}\" not defined yet.")?;
+            writeln!(output, "Breakpoint {id} ({location}) pending.")?;
+        }
+        Commands::Break(location) => {
+            let address = code_address(debugger, location.clone())?;
+            let line = matches!(location, Location::Line(_))
+                .then(|| debugger.source_location(address))
+                .flatten();
+            let id = set_breakpoint(debugger, location)?;
+            match line {
+                Some(line) => writeln!(
+                    output,
+                    "Breakpoint {id} at {address:#x}: file {}, line {}.",
+                    line.file.display(),
+                    line.line
+                )?,
+                None => writeln!(output, "Breakpoint {id} at {address:#x}")?,
+            }
+        }
+        Commands::Watch(kind, location) => {
+            let (expression, address, length) = match location {
+                Location::Address(address) => (format!("*{address:#x}"), address, 8),
+                Location::Symbol(name) => {
+                    let global = debugger
+                        .global_variables()?
+                        .into_iter()
+                        .find(|global| global.name == name)
+                        .ok_or_else(|| {
+                            color_eyre::eyre::eyre!("No symbol \"{name}\" in current context.")
+                        })?;
+                    let address = global.address.ok_or_else(|| {
+                        color_eyre::eyre::eyre!(
+                            "Cannot watch thread-local variable \"{name}\" yet."
+                        )
+                    })?;
+                    (name, address, global.size)
+                }
+                Location::SymbolOffset(name, offset) => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "Cannot watch \"{name}+{offset:#x}\": watch the variable, or an address with *."
+                    ));
+                }
+                Location::Line(line) => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "Cannot watch line {line}: watch a variable, or an address with *."
+                    ));
+                }
+            };
+            let id = debugger.set_watchpoint(&expression, address, length, kind)?;
+            if let Some(watchpoint) = find_watchpoint(debugger, id) {
+                writeln!(
+                    output,
+                    "{} {id}: {expression}",
+                    watchpoint_title(watchpoint)
+                )?;
+                if watchpoint.backend == sdblib::WatchBackend::Software {
+                    writeln!(
+                        output,
+                        "No debug register can take it, so the program is single-stepped while it is set; this is very slow."
+                    )?;
+                }
+            }
+        }
+        Commands::Enable(id) => {
+            debugger.enable_breakpoints(id)?;
+        }
+        Commands::Disable(id) => {
+            debugger.disable_breakpoints(id)?;
+        }
+        Commands::InfoBreakpoints => {
+            let mut breakpoints =
+                debugger
+                    .breakpoints()
+                    .iter()
+                    .filter(|breakpoint| breakpoint.is_user_breakpoint())
+                    .map(|breakpoint| {
+                        (
+                            breakpoint.id,
+                            breakpoint.enabled,
+                            format!("{:#018x}", breakpoint.address),
+                            &breakpoint.location,
+                        )
+                    })
+                    .chain(debugger.pending_breakpoints().into_iter().map(
+                        |(id, location, enabled)| (id, enabled, "<PENDING>".to_string(), location),
+                    ))
+                    .collect::<Vec<_>>();
+            breakpoints.sort_by_key(|(id, ..)| *id);
+            if breakpoints.is_empty() {
+                writeln!(output, "No breakpoints.")?;
+            } else {
+                writeln!(
+                    output,
+                    "{:<8}{:<12}{:<5}{:<20}What",
+                    "Num", "Type", "Enb", "Address"
+                )?;
+            }
+            for (id, enabled, address, location) in breakpoints {
+                writeln!(
+                    output,
+                    "{:<8}{:<12}{:<5}{:<20}{}",
+                    id,
+                    "breakpoint",
+                    if enabled { "y" } else { "n" },
+                    address,
+                    location
+                )?;
+            }
+        }
+        Commands::InfoWatchpoints => {
+            let watchpoints = debugger.watchpoints();
+            if watchpoints.is_empty() {
+                writeln!(output, "No watchpoints.")?;
+            } else {
+                writeln!(
+                    output,
+                    "{:<8}{:<8}{:<10}{:<20}{:<5}{:<6}What",
+                    "Num", "Type", "Backend", "Address", "Len", "Hits"
+                )?;
+            }
+            for watchpoint in watchpoints {
+                writeln!(
+                    output,
+                    "{:<8}{:<8}{:<10}{:<20}{:<5}{:<6}{}",
+                    watchpoint.id,
+                    match watchpoint.kind {
+                        sdblib::WatchKind::Write => "write",
+                        sdblib::WatchKind::Read => "read",
+                        sdblib::WatchKind::Access => "access",
+                    },
+                    match watchpoint.backend {
+                        sdblib::WatchBackend::Hardware { .. } => "hardware",
+                        sdblib::WatchBackend::Software => "software",
+                    },
+                    format!("{:#018x}", watchpoint.address),
+                    watchpoint.length,
+                    watchpoint.hit_count,
+                    watchpoint.expression
+                )?;
+            }
+        }
+        Commands::DeleteWatchpoint(id) => {
+            debugger.delete_watchpoint(id)?;
+        }
+        Commands::ShowArgs => {
+            let args = debugger.launch_args().unwrap_or_default();
+            writeln!(
+                output,
+                "Argument list to give program being debugged when it is started is \"{}\".",
+                args.iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )?;
+        }
+        Commands::Define(name, Some(body)) => {
+            session.macros.insert(name, body);
+            session.save_macros()?;
+        }
+        Commands::Define(name, None) => {
+            if session.macros.remove(&name).is_none() {
+                writeln!(output, "Undefined user command: \"{name}\".")?;
+            }
+            session.save_macros()?;
+        }
+        Commands::ShowConvenience => {
+            if session.convenience.is_empty() {
+                writeln!(output, "No debugger convenience values now defined.")?;
+            }
+            for (name, value) in &session.convenience {
+                writeln!(output, "${name} = {value}")?;
+            }
+        }
+        Commands::ShowUser(name) => {
+            if let Some(name) = &name
+                && !session.macros.contains_key(name)
+            {
+                writeln!(output, "Not a user command: \"{name}\".")?;
+            }
+            for (macro_name, body) in &session.macros {
+                if name.as_ref().is_none_or(|name| name == macro_name) {
+                    writeln!(output, "User command \"{macro_name}\":")?;
+                    for command in split_commands(body) {
+                        writeln!(output, "  {}", command.trim())?;
+                    }
+                }
+            }
+        }
+        Commands::Frame(index) => {
+            select_frame(
+                output,
+                debugger,
+                index.unwrap_or_else(|| debugger.selected_frame_index()),
+            )?;
+        }
+        Commands::Up(count) => {
+            let index = debugger.selected_frame_index().saturating_add(count);
+            select_frame(output, debugger, index)?;
+        }
+        Commands::Down(count) => {
+            let Some(index) = debugger.selected_frame_index().checked_sub(count) else {
+                writeln!(
+                    output,
+                    "Bottom (innermost) frame selected; you cannot go down."
+                )?;
+                return Ok(CommandOutcome::Continue);
+            };
+            select_frame(output, debugger, index)?;
+        }
+        Commands::Return(value) => {
+            let value = value
+                .map(|value| value.evaluate(debugger, &session.convenience))
+                .transpose()?;
+            let frame = debugger.return_from_frame(value)?;
+            writeln!(
+                output,
+                "Warning: the rest of the function was skipped; nothing it had left to do, like \
+                 cleaning up, was done."
+            )?;
+            writeln!(output, "#0  {}", describe_frame(&frame))?;
         }
         Commands::Exit => {
-            return Ok(false);
+            return Ok(CommandOutcome::Quit);
+        }
+        Commands::If(_) | Commands::While(_) | Commands::Else | Commands::End => {
+            unreachable!("blocks are nested right after parsing")
+        }
+        Commands::Conditional(condition, then, otherwise) => {
+            let branch = if condition.evaluate(debugger, &session.convenience)? == 0 {
+                otherwise
+            } else {
+                then
+            };
+            return run_command_ast(Commands::Sequence(branch), session, debugger, output);
+        }
+        Commands::Loop(condition, body) => {
+            let mut outcome = CommandOutcome::Continue;
+            while condition.evaluate(debugger, &session.convenience)? != 0 {
+                if session
+                    .interrupted
+                    .swap(false, std::sync::atomic::Ordering::Relaxed)
+                {
+                    writeln!(output, "Quit")?;
+                    break;
+                }
+                match run_command_ast(
+                    Commands::Sequence(body.clone()),
+                    session,
+                    debugger,
+                    &mut output,
+                )? {
+                    CommandOutcome::Quit => return Ok(CommandOutcome::Quit),
+                    CommandOutcome::Continue => {}
+                    other @ CommandOutcome::WrapInput(_) => outcome = other,
+                }
+            }
+            return Ok(outcome);
         }
         Commands::Sequence(commands) => {
+            // The front end hears about the last thing it has to do, unless it has to quit.
+            let mut outcome = CommandOutcome::Continue;
             for cmd in commands {
-                if !run_command_ast(cmd, debugger, &mut output)? {
-                    return Ok(false);
+                match run_command_ast(cmd, session, debugger, &mut output)? {
+                    CommandOutcome::Quit => return Ok(CommandOutcome::Quit),
+                    CommandOutcome::Continue => {}
+                    other @ CommandOutcome::WrapInput(_) => outcome = other,
                 }
             }
+            return Ok(outcome);
         }
         Commands::Error(err) => {
             writeln!(output, "Error: {err:?}")?;
         }
     }
 
-    Ok(true)
+    Ok(CommandOutcome::Continue)
 }
 
 pub fn run_command(
     command: &str,
+    session: &mut Session,
     debugger: &mut sdblib::Debugger,
     mut output: &mut dyn std::io::Write,
-) -> Result<bool> {
-    let Some(command) = parse_command(command, output) else {
-        return Ok(true);
+) -> Result<CommandOutcome> {
+    // A Ctrl-C while no command ran is not meant for this one.
+    session
+        .interrupted
+        .store(false, std::sync::atomic::Ordering::Relaxed);
+    let expanded = command
+        .lines()
+        .map(|line| session.expand_macros(line, 0))
+        .collect::<Result<Vec<_>>>()?
+        .join("\n");
+    let Some(command) = parse_command(&expanded, output) else {
+        return Ok(CommandOutcome::Continue);
     };
-    run_command_ast(command, debugger, &mut output)
+    // Checked before running, so turning timing on or off is not timed itself.
+    let started = session.timing.then(std::time::Instant::now);
+    let outcome = run_command_ast(command, session, debugger, &mut output)?;
+    write_activated_breakpoints(output, debugger)?;
+    if let Some(started) = started
+        && session.timing
+    {
+        writeln!(output, "Command took {:.3?}.", started.elapsed())?;
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The column, in characters, of the first `┬` marker under the source line.
+    fn caret_column(report: &str) -> Option<usize> {
+        report
+            .lines()
+            .find_map(|line| line.chars().position(|c| c == '┬'))
+    }
+
+    #[test]
+    fn unicode_command_caret_lands_on_the_offending_character() {
+        for input in ["cøntinue", "  cøntinue "] {
+            let mut output = Vec::new();
+            let command = parse_command(input, &mut output);
+            assert!(matches!(
+                command,
+                Some(Commands::Sequence(commands))
+                    if matches!(&commands[..], [Commands::Error(ErrorKind::UnexpectedCommand(word))] if word == "cøntinue")
+            ));
+            let report = String::from_utf8(output).unwrap();
+            let source_line = report
+                .lines()
+                .find(|line| line.contains("cøntinue"))
+                .unwrap();
+            let expected = source_line.chars().position(|c| c == 'ø');
+            assert_eq!(caret_column(&report), expected, "{report}");
+        }
+    }
+
+    #[test]
+    fn set_args_groups_quoted_words() {
+        let command = parse_command(
+            r#"set args a "b c"  d\e "say \"hi\""x ; show args "#,
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::SetArgs(args), Commands::ShowArgs]
+                    if *args == ["a", "b c", "d\\e", "say \"hi\"x"]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn return_takes_an_optional_value() {
+        let command = parse_command("return; return 0; return $rax + 1", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Return(None),
+                    Commands::Return(Some(Expression::Number(0))),
+                    Commands::Return(Some(Expression::Binary(..))),
+                ]
+            ),
+            "{commands:?}"
+        );
+        let error = run_command(
+            "return",
+            &mut Session::default(),
+            &mut sdblib::Debugger::new(),
+            &mut std::io::sink(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("No program"), "{error}");
+    }
+
+    #[test]
+    fn a_bare_number_is_a_line_of_the_current_file() {
+        let command = parse_command("break 12", &mut std::io::sink());
+        assert!(
+            matches!(&command, Some(Commands::Sequence(commands)) if matches!(&commands[..], [Commands::Break(Location::Line(12))])),
+            "{command:?}"
+        );
+        let command = parse_command("b 12abc", &mut std::io::sink());
+        assert!(
+            matches!(&command, Some(Commands::Sequence(commands)) if matches!(&commands[..], [Commands::Break(Location::Symbol(name))] if name == "12abc")),
+            "{command:?}"
+        );
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        let result = run_command(
+            "break 12",
+            &mut Session::default(),
+            &mut debugger,
+            &mut output,
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .starts_with("No default source file"),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let command = parse_command(
+            "# set a breakpoint\n\nbreak main\n  # then run\nrun # to main\n;",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::Break(Location::Symbol(main)), Commands::Run] if main == "main"
+            ),
+            "{commands:?}"
+        );
+
+        let command = parse_command("# set a breakpoint\nbreak main", &mut std::io::sink());
+        assert!(
+            matches!(
+                &command,
+                Some(Commands::Sequence(commands))
+                    if matches!(&commands[..], [Commands::Break(Location::Symbol(main))] if main == "main")
+            ),
+            "{command:?}"
+        );
+    }
+
+    #[test]
+    fn macros_expand_with_their_arguments() {
+        let command = parse_command(
+            "define stepprint stepi $arg0; info registers rip # and see where",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        let [Commands::Define(name, Some(body))] = &commands[..] else {
+            panic!("{commands:?}");
+        };
+
+        let mut session = Session::default();
+        session.macros.insert(name.clone(), body.clone());
+        session.macros.insert(
+            "twice".to_string(),
+            "stepprint $argc; stepprint 2".to_string(),
+        );
+        let expanded = session.expand_macros("twice x; continue", 0).unwrap();
+        let command = parse_command(&expanded, &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Stepi(1),
+                    Commands::InfoRegisters(Some(_)),
+                    Commands::Stepi(2),
+                    Commands::InfoRegisters(Some(_)),
+                    Commands::Continue,
+                ]
+            ),
+            "{commands:?}"
+        );
+        assert!(session.expand_macros("stepprint", 0).is_err());
+
+        session
+            .macros
+            .insert("forever".to_string(), "forever".to_string());
+        assert!(session.expand_macros("forever", 0).is_err());
+    }
+
+    #[test]
+    fn blocks_gather_the_commands_up_to_their_end() {
+        let command = parse_command("while $rip < 0x401200 stepi; end", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::Loop(Expression::Binary(..), body)]
+                    if matches!(&body[..], [Commands::Stepi(1)])
+            ),
+            "{commands:?}"
+        );
+
+        let command = parse_command(
+            "if $rax == 0\n  while 1 continue; end\nelse stepi\n  step\nend; run",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        let [Commands::Conditional(_, then, otherwise), Commands::Run] = &commands[..] else {
+            panic!("{commands:?}");
+        };
+        assert!(
+            matches!(&then[..], [Commands::Loop(Expression::Number(1), body)] if matches!(&body[..], [Commands::Continue])),
+            "{then:?}"
+        );
+        assert!(
+            matches!(&otherwise[..], [Commands::Stepi(1), Commands::Step(1)]),
+            "{otherwise:?}"
+        );
+
+        for unbalanced in ["while 1 stepi", "end", "else; end", "while 1; else; end"] {
+            assert!(
+                parse_command(unbalanced, &mut std::io::sink()).is_none(),
+                "{unbalanced}"
+            );
+        }
+    }
+
+    #[test]
+    fn break_takes_a_function_or_an_address() {
+        let command = parse_command(
+            "break main; b *0x401000; b std::rt::lang_start; b main+0x20; b f + 8",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Break(Location::Symbol(main)),
+                    Commands::Break(Location::Address(0x40_1000)),
+                    Commands::Break(Location::Symbol(lang_start)),
+                    Commands::Break(Location::SymbolOffset(main_offset, 0x20)),
+                    Commands::Break(Location::SymbolOffset(f, 8)),
+                ] if main == "main" && lang_start == "std::rt::lang_start" && main_offset == "main" && f == "f"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn enable_and_disable_take_an_optional_breakpoint() {
+        let command = parse_command(
+            "disable 2; enable; disable; enable 10; info breakpoints",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Disable(Some(2)),
+                    Commands::Enable(None),
+                    Commands::Disable(None),
+                    Commands::Enable(Some(10)),
+                    Commands::InfoBreakpoints,
+                ]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn bare_x_goes_on_past_the_last_one() {
+        let command = parse_command("x/4xg $rsp; x; x/2c; x/bx", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        let [
+            Commands::Examine(giants, Some(Expression::Register(rsp))),
+            Commands::Examine(bare, None),
+            Commands::Examine(chars, None),
+            Commands::Examine(bytes, None),
+        ] = &commands[..]
+        else {
+            panic!("{commands:?}");
+        };
+        assert_eq!(rsp, "rsp");
+        assert_eq!(*bare, ExamineSpec::default());
+        assert!(parse_command("x/4q $rsp", &mut std::io::sink()).is_none());
+
+        let mut session = Session::default();
+        assert!(session.examine(ExamineSpec::default(), None).is_err());
+        let first = session.examine(*giants, Some(0x1000)).unwrap();
+        assert_eq!((first.count, first.size), (4, 8));
+        let repeated = session.examine(ExamineSpec::default(), None).unwrap();
+        assert_eq!(
+            repeated,
+            Examine {
+                address: 0x1020,
+                end: 0x1040,
+                ..first
+            }
+        );
+        let chars = session.examine(*chars, None).unwrap();
+        assert_eq!((chars.address, chars.count, chars.size), (0x1040, 2, 1));
+        let bytes = session.examine(*bytes, Some(0x10)).unwrap();
+        assert_eq!((bytes.address, bytes.count), (0x10, 1));
+        assert_eq!(bytes.format, OutputFormat::Hex);
+        let strings = ExamineSpec {
+            count: Some(2),
+            format: Some(OutputFormat::String),
+            size: None,
+        };
+        assert!(matches!(
+            parse_command("x/2s $rdi", &mut std::io::sink()),
+            Some(Commands::Sequence(commands)) if matches!(&commands[..], [Commands::Examine(spec, Some(_))] if *spec == strings)
+        ));
+        assert_eq!(session.examine(strings, None).unwrap().size, 1);
+        for dump in ["x/2g 0xfffffffffffffff8", "x/3s 0xffffffffffffffff"] {
+            let error = run_command(
+                dump,
+                &mut Session::default(),
+                &mut sdblib::Debugger::new(),
+                &mut Vec::new(),
+            )
+            .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .starts_with("Cannot access memory at address 0xffff"),
+                "{dump}: {error}"
+            );
+        }
+
+        assert_eq!(format_unit(0xff, OutputFormat::Char, 1), "-1 '\\xff'");
+        assert_eq!(format_unit(0xfffe, OutputFormat::Decimal, 2), "-2");
+        assert_eq!(format_unit(0xfffe, OutputFormat::Unsigned, 2), "65534");
+        assert_eq!(format_unit(0x2a, OutputFormat::Hex, 4), "0x0000002a");
+    }
+
+    #[test]
+    fn exit_policy_is_kill_detach_or_ask() {
+        let command = parse_command(
+            "set exit-policy detach; set exit-policy ask; show exit-policy",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::SetExitPolicy(sdblib::ExitPolicy::Detach),
+                    Commands::SetExitPolicy(sdblib::ExitPolicy::Ask),
+                    Commands::ShowExitPolicy,
+                ]
+            ),
+            "{commands:?}"
+        );
+        assert!(parse_command("set exit-policy never", &mut std::io::sink()).is_none());
+    }
+
+    #[test]
+    fn step_show_changes_is_on_until_set_off() {
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        let script = "show step-show-changes\nset step-show-changes off\nshow step-show-changes";
+        run_command(script, &mut session, &mut debugger, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Showing the registers a step changes is on.\n\
+             Showing the registers a step changes is off.\n"
+        );
+        assert!(parse_command("set step-show-changes", &mut std::io::sink()).is_none());
+    }
+
+    #[test]
+    fn disable_randomization_is_on_until_set_off() {
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        let script = "show disable-randomization\nset disable-randomization off\n\
+                      show disable-randomization";
+        run_command(script, &mut session, &mut debugger, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Disabling randomization of the address space of launched programs is on.\n\
+             Disabling randomization of the address space of launched programs is off.\n"
+        );
+        assert!(!debugger.disable_randomization());
+    }
+
+    #[test]
+    fn quit_and_q_are_exit() {
+        let command = parse_command("quit; q; exit", &mut std::io::sink());
+        assert!(
+            matches!(&command, Some(Commands::Sequence(commands))
+                if matches!(&commands[..], [Commands::Exit, Commands::Exit, Commands::Exit])),
+            "{command:?}"
+        );
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        let script = "show confirm\nset confirm off\nshow confirm";
+        run_command(script, &mut session, &mut debugger, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Asking before quitting kills the program is on.\n\
+             Asking before quitting kills the program is off.\n"
+        );
+    }
+
+    #[test]
+    fn timing_follows_each_command_once_set_on() {
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        run_command(
+            "show timing\nset timing on",
+            &mut session,
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap();
+        run_command("show timing", &mut session, &mut debugger, &mut output).unwrap();
+        run_command("set timing off", &mut session, &mut debugger, &mut output).unwrap();
+        run_command("show timing", &mut session, &mut debugger, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert!(
+            matches!(
+                &lines[..],
+                [
+                    "Showing how long each command takes is off.",
+                    "Showing how long each command takes is on.",
+                    took,
+                    "Showing how long each command takes is off.",
+                ] if took.starts_with("Command took ")
+            ),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn verbose_is_off_until_set_on() {
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        let script = "show verbose\nset verbose on\nshow verbose";
+        run_command(script, &mut session, &mut debugger, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Verbosity is off.\nVerbosity is on.\n"
+        );
+    }
+
+    #[test]
+    fn file_takes_a_path() {
+        let command = parse_command("file /bin/true; file \"a b\"", &mut std::io::sink());
+        assert!(
+            matches!(
+                &command,
+                Some(Commands::Sequence(commands))
+                    if matches!(&commands[..], [Commands::File(first), Commands::File(second)]
+                        if first == "/bin/true" && second == "a b")
+            ),
+            "{command:?}"
+        );
+        let bare = parse_command("file", &mut std::io::sink());
+        assert!(
+            matches!(&bare, Some(Commands::Sequence(commands))
+                if matches!(&commands[..], [Commands::Error(_)])),
+            "{bare:?}"
+        );
+    }
+
+    #[test]
+    fn start_is_told_apart_from_starti() {
+        let command = parse_command("starti; start; run; help start", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Starti,
+                    Commands::Start,
+                    Commands::Run,
+                    Commands::Help(Some(name)),
+                ] if name == "start"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn info_functions_takes_an_optional_regex() {
+        let command = parse_command(
+            r#"info functions; info functions "^std::.* main""#,
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::InfoFunctions(None), Commands::InfoFunctions(Some(pattern))]
+                    if pattern.as_str() == "^std::.* main"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn print_and_info_variables_parse() {
+        let command = parse_command(
+            "p counter; print ns::value; info variables ^g_",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::Print(None, counter), Commands::Print(None, value), Commands::InfoVariables(Some(pattern))]
+                    if counter == "counter" && value == "ns::value" && pattern.as_str() == "^g_"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn print_takes_expressions_over_the_last_values() {
+        let command = parse_command("print $_; p *$_ + 8; p $__ # older", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        let [
+            Commands::Print(None, last),
+            Commands::PrintExpression(None, text, expression),
+            Commands::Print(None, older),
+        ] = &commands[..]
+        else {
+            panic!("{commands:?}");
+        };
+        assert_eq!((last.as_str(), older.as_str()), ("$_", "$__"));
+        assert_eq!(text, "*$_ + 8");
+
+        let mut session = Session::default();
+        session.set_last_value(0x10);
+        session.set_last_value(0x20);
+        let add = Expression::Binary(
+            crate::expression::BinaryOp::Add,
+            Box::new(Expression::Register("_".to_string())),
+            Box::new(Expression::Register("__".to_string())),
+        );
+        let debugger = sdblib::Debugger::new();
+        assert_eq!(add.evaluate(&debugger, &session.convenience).unwrap(), 0x30);
+        assert!(
+            expression
+                .evaluate(&debugger, &session.convenience)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn print_formats_override_the_type() {
+        let command = parse_command(
+            "p/x counter; print/t $rax + 1; p/c 65",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Print(Some(OutputFormat::Hex), counter),
+                    Commands::PrintExpression(Some(OutputFormat::Binary), text, _),
+                    Commands::PrintExpression(Some(OutputFormat::Char), _, Expression::Number(65)),
+                ] if counter == "counter" && text == "$rax + 1"
+            ),
+            "{commands:?}"
+        );
+        for invalid in ["p/q counter", "print/s counter"] {
+            assert!(
+                parse_command(invalid, &mut std::io::sink()).is_none(),
+                "{invalid}"
+            );
+        }
+
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        run_command(
+            "p/x 255; p/d -1; p/u 3; p/t 5; p/c 65",
+            &mut session,
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "255 = 0xff\n-1 = -1\n3 = 3\n5 = 101\n65 = 65 'A'\n"
+        );
+    }
+
+    #[test]
+    fn logging_copies_what_it_is_given_while_on() {
+        let path = std::env::temp_dir().join(format!("sdb-logging-{}.txt", std::process::id()));
+        std::fs::write(&path, "old\n").unwrap();
+        let mut session = Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        run_command(
+            &format!("set logging on {}", path.display()),
+            &mut session,
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap();
+        session.log(&String::from_utf8(std::mem::take(&mut output)).unwrap());
+        session.log("sdb rules");
+        run_command("set logging off", &mut session, &mut debugger, &mut output).unwrap();
+        session.log("not logged");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("old\nCopying output to {}.\nsdb rules\n", path.display())
+        );
+
+        run_command(
+            "set logging overwrite; set logging on",
+            &mut session,
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap();
+        session.log("fresh");
+        run_command("set logging off", &mut session, &mut debugger, &mut output).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fresh\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn info_registers_takes_an_optional_register() {
+        let command = parse_command(
+            "info registers; info registers rflags",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::InfoRegisters(None), Commands::InfoRegisters(Some(name))]
+                    if name == "rflags"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn registers_are_set_by_name_or_alias() {
+        let command = parse_command(
+            "set $pc = main; set $rax=0x10; info registers pc; set $base = $rbp; show convenience",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::SetRegister(pc, SetValue::Code(Location::Symbol(main))),
+                    Commands::SetRegister(rax, SetValue::Expression(Expression::Number(0x10))),
+                    Commands::InfoRegisters(Some(alias)),
+                    Commands::SetRegister(base, SetValue::Expression(Expression::Register(rbp))),
+                    Commands::ShowConvenience,
+                ] if pc == "pc" && main == "main" && rax == "rax" && alias == "pc"
+                    && base == "base" && rbp == "rbp"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn set_var_assigns_an_expression_to_a_variable() {
+        let command = parse_command(
+            "set var x = 42; set variable total=$rax + 1",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::SetVariable(x, Expression::Number(42)),
+                    Commands::SetVariable(total, Expression::Binary(..)),
+                ] if x == "x" && total == "total"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn stepi_takes_an_optional_count() {
+        let command = parse_command("stepi; si 20", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(&commands[..], [Commands::Stepi(1), Commands::Stepi(20)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn continue_takes_an_optional_all() {
+        let command = parse_command(
+            "continue; continue all; continue  all ",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Continue,
+                    Commands::ContinueAll,
+                    Commands::ContinueAll
+                ]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn step_is_told_apart_from_commands_starting_with_s() {
+        let command = parse_command(
+            "s; step 3; si; set args x; show args; ni; nexti 2",
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Step(1),
+                    Commands::Step(3),
+                    Commands::Stepi(1),
+                    Commands::SetArgs(_),
+                    Commands::ShowArgs,
+                    Commands::Nexti(1),
+                    Commands::Nexti(2),
+                ]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn find_takes_an_end_or_a_length_and_a_pattern() {
+        let command = parse_command(
+            r#"find 0x1000, 0x1fff, "hi there"; find /2 0x1000, +16, 0x7f 0x45 0x4c 0x46"#,
+            &mut std::io::sink(),
+        );
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [
+                    Commands::Find(None, string_range, string),
+                    Commands::Find(Some(2), bytes_range, bytes),
+                ] if *string_range == (0x1000..0x2000)
+                    && string == b"hi there"
+                    && *bytes_range == (0x1000..0x1010)
+                    && bytes == b"\x7fELF"
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn info_breakpoints_lists_those_not_set_yet_as_pending() {
+        let mut debugger = sdblib::Debugger::new();
+        let mut output = Vec::new();
+        run_command(
+            "file /bin/true; break nowhere; info breakpoints",
+            &mut Session::default(),
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.contains("Breakpoint 1 at nowhere, set when the program starts."),
+            "{output}"
+        );
+        assert!(
+            output.lines().any(|line| line.starts_with('1')
+                && line.contains("<PENDING>")
+                && line.ends_with("nowhere")),
+            "{output}"
+        );
+    }
+
+    /// Tests share one process, and the debugger reaps whichever child changes state first, so
+    /// only one test may have inferiors at a time.
+    static TRACEE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn lock_tracees() -> std::sync::MutexGuard<'static, ()> {
+        TRACEE_LOCK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// A debugger stopped at the entry point of `/bin/true`, where only the loader is mapped.
+    fn debug_true() -> sdblib::Debugger {
+        let mut debugger = sdblib::Debugger::new();
+        let _stdout = debugger
+            .add_program("/bin/true", std::iter::empty::<&str>())
+            .unwrap();
+        debugger
+    }
+
+    #[test]
+    fn break_on_data_is_refused_with_only_the_reason() {
+        let _guard = lock_tracees();
+        let mut debugger = debug_true();
+        let stack = debugger.register("rsp").unwrap().value;
+        for (location, reason) in [
+            (stack, "[stack] is not executable."),
+            (0, "the address is not mapped."),
+        ] {
+            let mut output = Vec::new();
+            let error = run_command(
+                &format!("break *{location:#x}"),
+                &mut Session::default(),
+                &mut debugger,
+                &mut output,
+            )
+            .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Error: Cannot insert breakpoint at {location:#x}: {reason}")
+            );
+            assert!(output.is_empty(), "{}", String::from_utf8_lossy(&output));
+        }
+        assert!(debugger.breakpoints().is_empty());
+    }
+
+    #[test]
+    fn a_function_offset_past_the_end_of_memory_is_out_of_range() {
+        let _guard = lock_tracees();
+        let mut debugger = debug_true();
+        let mut output = Vec::new();
+        let error = run_command(
+            "break _dl_debug_state+0xffffffffffffffff",
+            &mut Session::default(),
+            &mut debugger,
+            &mut output,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Location _dl_debug_state+0xffffffffffffffff is out of range."
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn info_time_needs_a_program() {
+        let command = parse_command("info time", &mut std::io::sink());
+        assert!(
+            matches!(&command, Some(Commands::Sequence(commands))
+                if matches!(&commands[..], [Commands::InfoTime])),
+            "{command:?}"
+        );
+        let mut debugger = sdblib::Debugger::new();
+        let error = run_command(
+            "info time",
+            &mut Session::default(),
+            &mut debugger,
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Error: No program is being debugged.");
+        assert_eq!(
+            cpu_share(
+                std::time::Duration::from_millis(250),
+                std::time::Duration::from_secs(1)
+            ),
+            "25.0%"
+        );
+        assert_eq!(
+            cpu_share(std::time::Duration::ZERO, std::time::Duration::ZERO),
+            "none"
+        );
+    }
+
+    #[test]
+    fn info_stack_takes_an_optional_word_count() {
+        let command = parse_command("info stack; info stack 4", &mut std::io::sink());
+        let Some(Commands::Sequence(commands)) = command else {
+            panic!("{command:?}");
+        };
+        assert!(
+            matches!(
+                &commands[..],
+                [Commands::InfoStack(None), Commands::InfoStack(Some(4))]
+            ),
+            "{commands:?}"
+        );
+    }
 }
@@ -0,0 +1,28 @@
+use std::io::Write;
+
+/// Where `--events` sends the debugger's event stream, one JSON object per line.
+pub struct EventLog {
+    out: Box<dyn Write + Send>,
+}
+
+impl EventLog {
+    /// Open `path` for writing, `-` meaning stdout.
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        let out: Box<dyn Write + Send> = if path == std::path::Path::new("-") {
+            Box::new(std::io::stdout())
+        } else {
+            Box::new(std::io::BufWriter::new(std::fs::File::create(path)?))
+        };
+        Ok(Self { out })
+    }
+
+    /// Write out everything the debugger recorded since the last call.
+    pub fn drain(&mut self, debugger: &mut sdblib::Debugger) -> color_eyre::Result<()> {
+        for event in debugger.take_events() {
+            writeln!(self.out, "{}", crate::json::to_string(&event)?)?;
+        }
+        // Consumers follow the stream live, so do not let lines sit in a buffer.
+        self.out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use chumsky::prelude::*;
+use color_eyre::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+    /// `*address`, the 8 bytes of memory there.
+    Deref,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// Convenience variables by name, without the `$`.
+pub type Convenience = std::collections::BTreeMap<String, u64>;
+
+/// An integer expression over registers and memory, as conditions of `if` and `while`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression {
+    Number(u64),
+    /// `$rip`, aliases included, or a convenience variable such as `$_` if no register has the
+    /// name.
+    Register(String),
+    Unary(UnaryOp, Box<Self>),
+    Binary(BinaryOp, Box<Self>, Box<Self>),
+}
+
+/// C precedence and associativity, every value a 64 bit unsigned integer.
+pub fn parser<'a>() -> impl Parser<'a, &'a str, Expression, extra::Err<Rich<'a, char>>> + Clone {
+    recursive(|expression| {
+        let number = choice((
+            just("0x")
+                .ignore_then(text::digits(16).at_least(1).to_slice())
+                .try_map(|digits: &str, span| {
+                    u64::from_str_radix(digits, 16)
+                        .map_err(|e| Rich::custom(span, format!("invalid number 0x{digits}: {e}")))
+                }),
+            text::int(10).try_map(|digits: &str, span| {
+                digits
+                    .parse::<u64>()
+                    .map_err(|e| Rich::custom(span, format!("invalid number {digits}: {e}")))
+            }),
+        ))
+        .map(Expression::Number);
+
+        let register = just('$')
+            .ignore_then(
+                any()
+                    .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_')
+                    .repeated()
+                    .at_least(1)
+                    .collect::<String>(),
+            )
+            .map(Expression::Register);
+
+        let atom = choice((
+            number,
+            register,
+            expression.delimited_by(just('(').padded(), just(')')),
+        ))
+        .padded();
+
+        let unary = choice((
+            just('-').to(UnaryOp::Negate),
+            just('!').to(UnaryOp::Not),
+            just('*').to(UnaryOp::Deref),
+        ))
+        .padded()
+        .repeated()
+        .foldr(atom, |op, operand| Expression::Unary(op, Box::new(operand)));
+
+        let binary = |left: Expression, (op, right): (BinaryOp, Expression)| {
+            Expression::Binary(op, Box::new(left), Box::new(right))
+        };
+
+        let product = unary.clone().foldl(
+            choice((
+                just('*').to(BinaryOp::Mul),
+                just('/').to(BinaryOp::Div),
+                just('%').to(BinaryOp::Rem),
+            ))
+            .padded()
+            .then(unary)
+            .repeated(),
+            binary,
+        );
+        let sum = product.clone().foldl(
+            choice((just('+').to(BinaryOp::Add), just('-').to(BinaryOp::Sub)))
+                .padded()
+                .then(product)
+                .repeated(),
+            binary,
+        );
+        let comparison = sum.clone().foldl(
+            choice((
+                just("<=").to(BinaryOp::LessEqual),
+                just(">=").to(BinaryOp::GreaterEqual),
+                just('<').to(BinaryOp::Less),
+                just('>').to(BinaryOp::Greater),
+            ))
+            .padded()
+            .then(sum)
+            .repeated(),
+            binary,
+        );
+        let equality = comparison.clone().foldl(
+            choice((
+                just("==").to(BinaryOp::Equal),
+                just("!=").to(BinaryOp::NotEqual),
+            ))
+            .padded()
+            .then(comparison)
+            .repeated(),
+            binary,
+        );
+        let and = equality.clone().foldl(
+            just("&&")
+                .to(BinaryOp::And)
+                .padded()
+                .then(equality)
+                .repeated(),
+            binary,
+        );
+        and.clone()
+            .foldl(
+                just("||").to(BinaryOp::Or).padded().then(and).repeated(),
+                binary,
+            )
+            .boxed()
+    })
+}
+
+impl Expression {
+    /// The value in the stopped inferior, arithmetic wrapping around like C's unsigned.
+    /// # Errors
+    ///
+    /// Will return `Err` if a register or memory cannot be read, a convenience variable is not
+    /// set, or on a division by zero.
+    pub fn evaluate(&self, debugger: &sdblib::Debugger, convenience: &Convenience) -> Result<u64> {
+        let evaluate = |expression: &Self| expression.evaluate(debugger, convenience);
+        Ok(match self {
+            Self::Number(value) => *value,
+            Self::Register(name) if sdblib::is_register_name(name) => {
+                debugger.register(name)?.value
+            }
+            Self::Register(name) => *convenience
+                .get(name)
+                .ok_or_else(|| color_eyre::eyre::eyre!("Convenience variable ${name} is void."))?,
+            Self::Unary(op, operand) => {
+                let value = evaluate(operand)?;
+                match op {
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::Not => u64::from(value == 0),
+                    UnaryOp::Deref => {
+                        let bytes = debugger.read_memory(value, 8)?;
+                        u64::from_ne_bytes(bytes.try_into().unwrap_or_default())
+                    }
+                }
+            }
+            // Short-circuit, so `$rdi && *$rdi` does not read address 0.
+            Self::Binary(BinaryOp::And, left, right) => {
+                u64::from(evaluate(left)? != 0 && evaluate(right)? != 0)
+            }
+            Self::Binary(BinaryOp::Or, left, right) => {
+                u64::from(evaluate(left)? != 0 || evaluate(right)? != 0)
+            }
+            Self::Binary(op, left, right) => {
+                let left = evaluate(left)?;
+                let right = evaluate(right)?;
+                match op {
+                    BinaryOp::Mul => left.wrapping_mul(right),
+                    BinaryOp::Div => left
+                        .checked_div(right)
+                        .ok_or_else(|| color_eyre::eyre::eyre!("Division by zero"))?,
+                    BinaryOp::Rem => left
+                        .checked_rem(right)
+                        .ok_or_else(|| color_eyre::eyre::eyre!("Division by zero"))?,
+                    BinaryOp::Add => left.wrapping_add(right),
+                    BinaryOp::Sub => left.wrapping_sub(right),
+                    BinaryOp::Less => u64::from(left < right),
+                    BinaryOp::LessEqual => u64::from(left <= right),
+                    BinaryOp::Greater => u64::from(left > right),
+                    BinaryOp::GreaterEqual => u64::from(left >= right),
+                    BinaryOp::Equal => u64::from(left == right),
+                    BinaryOp::NotEqual => u64::from(left != right),
+                    BinaryOp::And | BinaryOp::Or => unreachable!("short-circuited above"),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_bind_like_c() {
+        let number = |value| Box::new(Expression::Number(value));
+        let parsed = parser()
+            .parse("$rip < 0x10 + 2 * 3 && !*$rsp")
+            .into_result();
+        assert_eq!(
+            parsed,
+            Ok(Expression::Binary(
+                BinaryOp::And,
+                Box::new(Expression::Binary(
+                    BinaryOp::Less,
+                    Box::new(Expression::Register("rip".to_string())),
+                    Box::new(Expression::Binary(
+                        BinaryOp::Add,
+                        number(0x10),
+                        Box::new(Expression::Binary(BinaryOp::Mul, number(2), number(3))),
+                    )),
+                )),
+                Box::new(Expression::Unary(
+                    UnaryOp::Not,
+                    Box::new(Expression::Unary(
+                        UnaryOp::Deref,
+                        Box::new(Expression::Register("rsp".to_string())),
+                    )),
+                )),
+            ))
+        );
+    }
+}
@@ -1,23 +1,31 @@
 use futures::{FutureExt, StreamExt};
+use std::io::Stdout;
 use std::io::Write;
-use std::{io::Stdout, u16};
-use tokio::{io::AsyncBufReadExt, sync::mpsc, task::JoinHandle};
+use tokio::{sync::mpsc, task::JoinHandle};
 use tracing::{Level, event};
 
 use ratatui::{
     DefaultTerminal, Frame, Terminal, TerminalOptions, Viewport,
-    crossterm::event::{KeyCode, KeyModifiers},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     layout::{Constraint, Layout, Rect},
     prelude::CrosstermBackend,
-    style::{Color, Style, Stylize},
+    style::{Color, Style},
     text::{Line, Span},
     widgets::{Paragraph, Widget},
 };
 
 use tui_input::{Input, backend::crossterm::EventHandler};
+use unicode_width::UnicodeWidthChar;
 
 use color_eyre::Result;
 
+use crate::command::CommandOutcome;
+
+const PROMPT: &str = "sdb> ";
+
+/// Asked in place of the prompt before quitting kills the program.
+const QUIT_QUESTION: &str = "A program is being debugged. Kill it? (y/n) ";
+
 struct Writer<'a>(
     &'a mut Terminal<CrosstermBackend<Stdout>>,
     std::vec::Vec<u8>,
@@ -51,103 +59,347 @@ impl std::io::Write for Writer<'_> {
 enum Event {
     Error,
     Tick,
-    ChildOutput(String),
+    /// The lines read from a child output pipe at once.
+    ChildOutput(Vec<String>),
+    /// A child output pipe will give nothing more. The child may well go on running.
+    ChildOutputEnded(OutputEnd),
     Crossterm(ratatui::crossterm::event::Event),
 }
 
+/// Why a child output pipe stopped being read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum OutputEnd {
+    /// The child closed it, or exited.
+    Closed,
+    /// It could not be read any more.
+    Error(String),
+}
+
+impl std::fmt::Display for OutputEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "The program closed its output."),
+            Self::Error(e) => write!(f, "Cannot read the program output any more: {e}"),
+        }
+    }
+}
+
+/// A child output pipe, read by whoever gets to it first: its task while the prompt waits,
+/// [`TokioEventHandler::drain_child_output`] once a command is done.
+#[derive(Debug)]
+struct ChildPipe {
+    /// Non-blocking.
+    file: std::fs::File,
+    /// The start of a line whose end is still to be read.
+    partial: Vec<u8>,
+    closed: bool,
+    /// Why reading failed, if that is why it is closed.
+    error: Option<String>,
+    /// Whether the end was told already, which happens once.
+    end_told: bool,
+    /// Whether every line read also goes to the tracing log.
+    log: bool,
+}
+
+impl ChildPipe {
+    /// The lines that can be read without blocking; the last one even if unended, once the
+    /// pipe is closed.
+    fn read_lines(&mut self) -> Vec<String> {
+        self.fill(usize::MAX);
+        self.take_lines()
+    }
+
+    /// Read what can be read without blocking, up to about `limit` bytes. Returns whether
+    /// that was all of it.
+    fn fill(&mut self, limit: usize) -> bool {
+        let mut buffer = [0; 4096];
+        let mut read = 0;
+        while !self.closed {
+            if read >= limit {
+                return false;
+            }
+            match std::io::Read::read(&mut self.file, &mut buffer) {
+                Ok(0) => self.closed = true,
+                Ok(len) => {
+                    self.partial.extend_from_slice(&buffer[..len]);
+                    read += len;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => self.fail(&e),
+            }
+        }
+        true
+    }
+
+    /// The lines read so far; the last one even if unended, once the pipe is closed.
+    fn take_lines(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            lines.push(self.logged(String::from_utf8_lossy(&line[..end]).into_owned()));
+        }
+        if self.closed
+            && let Some(line) = self.take_partial()
+        {
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn fail(&mut self, e: &std::io::Error) {
+        event!(Level::ERROR, "Cannot read the program output: {e}");
+        self.closed = true;
+        self.error = Some(e.to_string());
+    }
+
+    /// How the pipe ended, the first time this is asked once it has.
+    fn take_end(&mut self) -> Option<OutputEnd> {
+        if !self.closed || self.end_told {
+            return None;
+        }
+        self.end_told = true;
+        Some(
+            self.error
+                .take()
+                .map_or(OutputEnd::Closed, OutputEnd::Error),
+        )
+    }
+
+    /// The unended line read so far, if any.
+    fn take_partial(&mut self) -> Option<String> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).into_owned();
+        Some(self.logged(line))
+    }
+
+    fn logged(&self, line: String) -> String {
+        if self.log {
+            event!(Level::INFO, child_output = %line);
+        }
+        line
+    }
+}
+
+/// How many events from the terminal can wait to be handled before reading it waits too.
+const INPUT_CAPACITY: usize = 64;
+
+/// How many reads of child output can wait to be shown. Past that the pipes are left unread,
+/// so a program printing faster than it can be shown blocks in its writes.
+const OUTPUT_CAPACITY: usize = 64;
+
+/// How much of a pipe is read into one [`Event::ChildOutput`].
+const OUTPUT_READ_LIMIT: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub struct TokioEventHandler {
-    _tx: mpsc::UnboundedSender<Event>,
-    rx: mpsc::UnboundedReceiver<Event>,
+    /// Events from the terminal and the ticks, kept apart from the child output so that a
+    /// flood of output never holds up a keystroke.
+    rx: mpsc::Receiver<Event>,
+    /// Child output, and the end of it.
+    output_tx: mpsc::Sender<Event>,
+    output_rx: mpsc::Receiver<Event>,
     _task: Option<JoinHandle<()>>,
+    /// The child output pipes still open, shared with the tasks reading them.
+    child_pipes: Vec<std::sync::Arc<std::sync::Mutex<ChildPipe>>>,
+    /// Events taken out of the channel by [`Self::drain_child_output`], to be handed out first.
+    backlog: std::collections::VecDeque<Event>,
+    /// Whether the child output also goes to the tracing log, line by line.
+    log_child_output: bool,
 }
 
 impl TokioEventHandler {
-    pub fn new(child_output: Option<std::process::ChildStdout>) -> Self {
+    /// `interrupt` is raised on every Ctrl-C, even while a command keeps the front end busy.
+    /// With `log_child_output`, each line of the child output is logged as it is read.
+    pub fn new(
+        child_output: Option<std::process::ChildStdout>,
+        interrupt: std::sync::Arc<std::sync::atomic::AtomicBool>,
+        log_child_output: bool,
+    ) -> Self {
         let tick_rate = std::time::Duration::from_millis(250);
 
-        let (tx, rx) = mpsc::unbounded_channel();
-        let tx2 = tx.clone();
+        let (tx, rx) = mpsc::channel(INPUT_CAPACITY);
+        let (output_tx, output_rx) = mpsc::channel(OUTPUT_CAPACITY);
 
         let task = tokio::spawn(async move {
             let mut reader = ratatui::crossterm::event::EventStream::new();
             let mut interval = tokio::time::interval(tick_rate);
-            let mut child_output_reader = child_output.map(|stdout| {
-                tokio::io::BufReader::new(tokio::process::ChildStdout::from_std(stdout).unwrap())
-                    .lines()
-            });
             loop {
                 let delay = interval.tick();
                 let crossterm_event = reader.next().fuse();
-                if child_output_reader.is_none() {
-                    tokio::select! {
-                        maybe_event = crossterm_event => {
-                            match maybe_event {
-                                Some(Ok(evt)) => {
-                                    tx.send(Event::Crossterm(evt)).unwrap();
-                                }
-                                Some(Err(_)) => {
-                                    let _ = tx.send(Event::Error);
+                tokio::select! {
+                    maybe_event = crossterm_event => {
+                        match maybe_event {
+                            Some(Ok(evt)) => {
+                                if let ratatui::crossterm::event::Event::Key(key) = &evt
+                                    && key.code == KeyCode::Char('c')
+                                    && key.modifiers.contains(KeyModifiers::CONTROL)
+                                {
+                                    interrupt.store(true, std::sync::atomic::Ordering::Relaxed);
                                 }
-                                None => {},
+                                tx.send(Event::Crossterm(evt)).await.unwrap();
                             }
-                        },
-                        _ = delay => {
-                            let _ = tx.send(Event::Tick);
-                        },
-                    }
-                } else {
-                    let child_output_reader_unwrap = child_output_reader.as_mut().unwrap();
-                    let child_output = child_output_reader_unwrap.next_line();
-                    tokio::select! {
-                        maybe_event = crossterm_event => {
-                            match maybe_event {
-                                Some(Ok(evt)) => {
-                                    tx.send(Event::Crossterm(evt)).unwrap();
-                                }
-                                Some(Err(_)) => {
-                                    let _ = tx.send(Event::Error);
-                                }
-                                None => {},
-                            }
-                        },
-                        _ = delay => {
-                            let _ = tx.send(Event::Tick);
-                        },
-                        maybe_line = child_output => {
-                            if let Ok(line) = maybe_line {
-                                if let Some(line) = line {
-                                    tx.send(Event::ChildOutput(line)).unwrap();
-                                } else {
-                                    child_output_reader = None;
-                                }
-                            } else {
-                                let _ = tx.send(Event::Error);
-                                child_output_reader = None;
+                            Some(Err(_)) => {
+                                let _ = tx.send(Event::Error).await;
                             }
-                        },
-                    }
+                            None => {},
+                        }
+                    },
+                    _ = delay => {
+                        // A tick is only a prompt to redraw: with others still waiting, it
+                        // says nothing new.
+                        let _ = tx.try_send(Event::Tick);
+                    },
                 }
             }
         });
 
-        Self {
-            _tx: tx2,
+        let mut handler = Self {
             rx,
+            output_tx,
+            output_rx,
             _task: Some(task),
+            child_pipes: Vec::new(),
+            backlog: std::collections::VecDeque::new(),
+            log_child_output,
+        };
+        if let Some(stdout) = child_output {
+            handler.add_child_output(stdout);
         }
+        handler
+    }
+
+    /// Forward every line the child writes to `stdout` as an [`Event::ChildOutput`].
+    pub fn add_child_output(&mut self, stdout: std::process::ChildStdout) {
+        let fd = std::os::fd::OwnedFd::from(stdout);
+        let pipe = nix::fcntl::fcntl(&fd, nix::fcntl::F_SETFL(nix::fcntl::OFlag::O_NONBLOCK))
+            .map_err(std::io::Error::from)
+            .and_then(|_| fd.try_clone())
+            .and_then(|reader| tokio::io::unix::AsyncFd::new(reader).map(|ready| (ready, fd)));
+        let (ready, fd) = match pipe {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                event!(Level::ERROR, "Cannot read the program output: {e}");
+                let _ = self
+                    .output_tx
+                    .try_send(Event::ChildOutputEnded(OutputEnd::Error(e.to_string())));
+                return;
+            }
+        };
+        let pipe = std::sync::Arc::new(std::sync::Mutex::new(ChildPipe {
+            file: std::fs::File::from(fd),
+            partial: Vec::new(),
+            closed: false,
+            error: None,
+            end_told: false,
+            log: self.log_child_output,
+        }));
+        self.child_pipes.push(pipe.clone());
+
+        let tx = self.output_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                // Room for the lines and the end, before anything is read: while the channel
+                // is full, the pipe fills up instead.
+                let Ok(mut permits) = tx.reserve_many(2).await else {
+                    break;
+                };
+                let ready = ready.readable().await;
+                // Send under the lock, so a drain sees these lines in the channel already.
+                let mut pipe = pipe
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                let mut all_read = true;
+                match &ready {
+                    Ok(_) => {
+                        all_read = pipe.fill(OUTPUT_READ_LIMIT);
+                        let lines = pipe.take_lines();
+                        if !lines.is_empty()
+                            && let Some(permit) = permits.next()
+                        {
+                            permit.send(Event::ChildOutput(lines));
+                        }
+                    }
+                    Err(e) => pipe.fail(e),
+                }
+                if let Some(end) = pipe.take_end()
+                    && let Some(permit) = permits.next()
+                {
+                    permit.send(Event::ChildOutputEnded(end));
+                }
+                let closed = pipe.closed;
+                drop(pipe);
+                // Whatever was left unread keeps the pipe ready.
+                if all_read && let Ok(mut guard) = ready {
+                    guard.clear_ready();
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
     }
 
     async fn next(&mut self) -> Result<Event> {
-        self.rx
-            .recv()
-            .await
-            .ok_or_else(|| color_eyre::eyre::eyre!("Unable to get event"))
+        if let Some(event) = self.backlog.pop_front() {
+            return Ok(event);
+        }
+        // The terminal first, however much output waits.
+        let event = tokio::select! {
+            biased;
+            Some(event) = self.rx.recv() => Some(event),
+            Some(event) = self.output_rx.recv() => Some(event),
+            else => None,
+        };
+        event.ok_or_else(|| color_eyre::eyre::eyre!("Unable to get event"))
+    }
+
+    /// Every line the child wrote so far that nobody has shown yet, in order.
+    ///
+    /// The inferior is stopped between commands, so this is all it wrote while the last command
+    /// ran. A line it has not ended yet, like a prompt, comes too: it belongs to that run, not
+    /// to whatever the next command shows. The end of a pipe is left for [`Self::next`] to
+    /// hand out, after the rest.
+    fn drain_child_output(&mut self) -> Vec<String> {
+        let mut pipes: Vec<_> = self
+            .child_pipes
+            .iter()
+            .map(|pipe| {
+                pipe.lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+            })
+            .collect();
+        // Lines the readers sent came out of the pipes first.
+        let mut lines = Vec::new();
+        while let Ok(event) = self.output_rx.try_recv() {
+            match event {
+                Event::ChildOutput(read) => lines.extend(read),
+                other => self.backlog.push_back(other),
+            }
+        }
+        for pipe in &mut pipes {
+            lines.extend(pipe.read_lines());
+            lines.extend(pipe.take_partial());
+            if let Some(end) = pipe.take_end() {
+                self.backlog.push_back(Event::ChildOutputEnded(end));
+            }
+        }
+        drop(pipes);
+        self.child_pipes.retain(|pipe| {
+            !pipe
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .closed
+        });
+        lines
     }
 }
 
 pub struct Gui {
     debugger: sdblib::Debugger,
+    session: crate::command::Session,
 
     // Past commands
     history: Vec<String>,
@@ -156,33 +408,151 @@ pub struct Gui {
 
     // Current input
     input: Input,
+    // Whole lines pasted ahead of the input, run with it on the next Enter
+    pasted_lines: Vec<String>,
     // Child program output
     child_output: Option<std::process::ChildStdout>,
+    event_log: Option<crate::events::EventLog>,
+    log_child_output: bool,
+    // Output that did not fit on the screen, shown a page per Enter
+    pending_output: std::collections::VecDeque<String>,
+    // Whether long input wraps onto more lines instead of scrolling sideways
+    wrap_input: bool,
+    input_height: u16,
+    // The prompt, naming the process commands act on
+    prompt: String,
+    // Whether the prompt asks to confirm quitting, which takes the keys until answered
+    confirming_quit: bool,
 }
 
 impl Gui {
     pub fn new(
         debugger: sdblib::Debugger,
+        session: crate::command::Session,
         output_ran_command: Option<std::process::ChildStdout>,
+        event_log: Option<crate::events::EventLog>,
     ) -> Self {
-        Self {
+        let mut gui = Self {
             debugger,
+            session,
             history: Vec::new(),
             history_current: String::new(),
             index_history: 0,
             input: Input::default(),
+            pasted_lines: Vec::new(),
             child_output: output_ran_command,
+            event_log,
+            log_child_output: false,
+            pending_output: std::collections::VecDeque::new(),
+            wrap_input: false,
+            input_height: 1,
+            prompt: PROMPT.to_string(),
+            confirming_quit: false,
+        };
+        gui.refresh_prompt();
+        gui
+    }
+
+    /// Whether to leave now. Quitting would kill a launched program unless the exit policy is
+    /// to detach, so then the user is asked first, unless `set confirm off` says not to.
+    fn quit(&mut self) -> bool {
+        if !self.session.confirm
+            || !self.debugger.has_launched_process()
+            || self.debugger.exit_policy() == sdblib::ExitPolicy::Detach
+        {
+            return true;
         }
+        self.confirming_quit = true;
+        self.prompt = QUIT_QUESTION.to_string();
+        false
+    }
+
+    /// Take `key` as the answer to [`QUIT_QUESTION`]: whether to leave now, or `None` to
+    /// keep asking. Yes kills the program. No stays in the debugger, unless the exit policy
+    /// is to ask, which is what it asked: then no detaches the program instead.
+    fn answer_quit(&mut self, key: KeyEvent) -> Option<bool> {
+        let leave = match key.code {
+            KeyCode::Char('y' | 'Y') => true,
+            KeyCode::Char('n' | 'N') => {
+                let ask = self.debugger.exit_policy() == sdblib::ExitPolicy::Ask;
+                if ask {
+                    self.debugger.set_exit_policy(sdblib::ExitPolicy::Detach);
+                }
+                ask
+            }
+            KeyCode::Esc => false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => false,
+            _ => return None,
+        };
+        self.confirming_quit = false;
+        self.refresh_prompt();
+        Some(leave)
+    }
+
+    /// Follow the process and thread commands act on, which change as they run.
+    fn refresh_prompt(&mut self) {
+        self.prompt = prompt_for(
+            self.debugger
+                .current_process()
+                .ok()
+                .zip(self.debugger.current_thread().ok()),
+        );
+    }
+
+    /// Also write every line of the program output to the tracing log, for a log file that
+    /// tells the whole session.
+    pub const fn log_child_output(&mut self) {
+        self.log_child_output = true;
     }
 
     pub async fn run(&mut self) -> Result<()> {
         color_eyre::install()?;
-        let mut events = TokioEventHandler::new(self.child_output.take());
+        let mut events = TokioEventHandler::new(
+            self.child_output.take(),
+            self.session.interrupt_flag(),
+            self.log_child_output,
+        );
 
         let mut terminal = ratatui::init_with_options(TerminalOptions {
             viewport: Viewport::Inline(1),
         });
-        self.run_impl(&mut terminal, &mut events).await
+        // Pasted newlines then come as part of a paste, rather than as Enter key presses.
+        ratatui::crossterm::execute!(
+            std::io::stdout(),
+            ratatui::crossterm::event::EnableBracketedPaste
+        )?;
+        let mut writer = Writer::new(&mut terminal);
+        self.write_banner(&mut writer)?;
+        writer.flush()?;
+        let result = self.run_impl(&mut terminal, &mut events).await;
+        ratatui::crossterm::execute!(
+            std::io::stdout(),
+            ratatui::crossterm::event::DisableBracketedPaste
+        )?;
+        result
+    }
+
+    /// Say what is being debugged, once, above everything else.
+    fn write_banner(&self, output: &mut dyn Write) -> Result<()> {
+        let architecture = self
+            .debugger
+            .architecture()
+            .unwrap_or_else(|_| std::env::consts::ARCH.to_string());
+        writeln!(
+            output,
+            "sdb {} for {architecture}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        match (
+            self.debugger.current_process(),
+            self.debugger.launched_program(),
+        ) {
+            (Ok(pid), Some(program)) => writeln!(output, "Debugging {program} (process {pid}).")?,
+            (Ok(pid), None) => writeln!(output, "Attached to process {pid}.")?,
+            (Err(_), Some(_)) => writeln!(output, "The program is not being run.")?,
+            (Err(_), None) => writeln!(output, "No program is being debugged.")?,
+        }
+        Ok(())
     }
 
     async fn run_impl(
@@ -191,6 +561,7 @@ impl Gui {
         events: &mut TokioEventHandler,
     ) -> Result<()> {
         loop {
+            self.fit_viewport(terminal)?;
             terminal.draw(|frame| self.render(frame))?;
 
             let event = events.next().await?;
@@ -201,23 +572,71 @@ impl Gui {
                 Event::Tick => {
                     // Nothing to do on tick for now
                 }
-                Event::ChildOutput(str) => {
+                Event::ChildOutput(lines) => {
                     let mut writer = Writer::new(terminal);
-                    writeln!(writer, "{str}")?;
+                    for line in lines {
+                        self.session.log(&line);
+                        writeln!(writer, "{line}")?;
+                    }
                     writer.flush()?;
                 }
+                Event::ChildOutputEnded(end) => {
+                    let note = end.to_string();
+                    self.session.log(&note);
+                    let mut writer = Writer::new(terminal);
+                    writeln!(writer, "{note}")?;
+                    writer.flush()?;
+                }
+                Event::Crossterm(ratatui::crossterm::event::Event::Resize(width, height)) => {
+                    // Move the viewport into the new size before fitting it to the input,
+                    // which wraps differently at the new width; resizing also clears the last
+                    // frame, so the next draw repaints it all rather than diffing against
+                    // what the terminal reflowed.
+                    terminal.resize(Rect::new(0, 0, width, height))?;
+                }
+                Event::Crossterm(ratatui::crossterm::event::Event::Paste(text)) => {
+                    let lines = self.paste(&text);
+                    for line in &lines {
+                        self.session.log(line);
+                    }
+                    terminal.insert_before(
+                        u16::try_from(lines.len()).unwrap_or(u16::MAX),
+                        |buffer| {
+                            Paragraph::new(
+                                lines
+                                    .iter()
+                                    .map(|line| Line::from(line.as_str()))
+                                    .collect::<Vec<_>>(),
+                            )
+                            .style(Style::default().fg(Color::DarkGray))
+                            .render(buffer.area, buffer);
+                        },
+                    )?;
+                }
                 Event::Crossterm(crossterm) => {
                     let ratatui::crossterm::event::Event::Key(key) = crossterm else {
                         continue;
                     };
+                    if self.confirming_quit {
+                        if self.answer_quit(key) == Some(true) {
+                            break;
+                        }
+                        continue;
+                    }
                     match key.code {
-                        KeyCode::Enter => {
-                            if !self.run_command(terminal)? {
-                                break;
+                        KeyCode::Enter => match self.run_command(terminal, events)? {
+                            CommandOutcome::Quit => {
+                                if self.quit() {
+                                    break;
+                                }
                             }
-                        }
+                            CommandOutcome::WrapInput(wrap) => self.wrap_input = wrap,
+                            CommandOutcome::Continue => {}
+                        },
                         KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                            break;
+                            if self.quit() {
+                                break;
+                            }
                         }
                         KeyCode::Up => {
                             self.move_history(-1);
@@ -265,40 +684,212 @@ impl Gui {
             .with_cursor(usize::MAX);
     }
 
-    fn run_command(&mut self, terminal: &mut DefaultTerminal) -> Result<bool> {
+    /// Insert pasted text at the cursor. Its newlines do not run anything as Enter would: the
+    /// lines they end are held, to run ahead of the input on the next Enter, and returned to
+    /// be shown meanwhile.
+    fn paste(&mut self, text: &str) -> Vec<String> {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let value = self.input.value();
+        let cursor = value
+            .char_indices()
+            .nth(self.input.cursor())
+            .map_or(value.len(), |(index, _)| index);
+        let (before, after) = value.split_at(cursor);
+        let mut lines = format!("{before}{text}")
+            .split('\n')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let last = lines.pop().unwrap_or_default();
+        self.input = Input::default()
+            .with_value(format!("{last}{after}"))
+            .with_cursor(last.chars().count());
+        self.pasted_lines.extend(lines.iter().cloned());
+        lines
+    }
+
+    /// Run the input as a command, after any lines pasted ahead of it. The scrollback gets its
+    /// echo, then whatever the program wrote meanwhile, then what the commands have to say.
+    fn run_command(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        events: &mut TokioEventHandler,
+    ) -> Result<CommandOutcome> {
         let mut command = self.input.value_and_reset();
-        if command.is_empty() {
+        let pasted = std::mem::take(&mut self.pasted_lines);
+        if !self.pending_output.is_empty() {
+            if command.is_empty() && pasted.is_empty() {
+                Self::show_page(terminal, &mut self.pending_output)?;
+                return Ok(CommandOutcome::Continue);
+            }
+            // Anything else abandons the rest of the output, and `q` does only that.
+            self.pending_output.clear();
+            if command == "q" && pasted.is_empty() {
+                return Ok(CommandOutcome::Continue);
+            }
+        }
+        if command.is_empty() && pasted.is_empty() {
             if let Some(other_command) = self.history.last() {
-                command = other_command.clone();
+                command.clone_from(other_command);
             } else {
-                return Ok(true);
+                return Ok(CommandOutcome::Continue);
             }
         }
 
-        self.history.push(command.clone());
+        self.history.extend(
+            pasted
+                .iter()
+                .chain(std::iter::once(&command))
+                .filter(|line| !line.is_empty())
+                .cloned(),
+        );
         self.index_history = self.history.len();
         self.history_current.clear();
 
-        terminal.insert_before(1, |buffer| {
-            Paragraph::new(Line::from(Span::styled(
-                command.clone(),
-                Style::default().fg(Color::Green),
-            )))
-            .render(buffer.area, buffer);
-        })?;
+        // The pasted lines were shown as they came.
+        if !command.is_empty() {
+            terminal.insert_before(1, |buffer| {
+                Paragraph::new(Line::from(Span::styled(
+                    command.clone(),
+                    Style::default().fg(Color::Green),
+                )))
+                .render(buffer.area, buffer);
+            })?;
+            self.session.log(&command);
+        }
+        let script = pasted
+            .into_iter()
+            .chain(std::iter::once(command))
+            .collect::<Vec<_>>()
+            .join("\n");
 
         let mut writer = Writer::new(terminal);
-        let res = crate::command::run_command(command.as_str(), &mut self.debugger, &mut writer);
+        let res = crate::command::run_command(
+            script.as_str(),
+            &mut self.session,
+            &mut self.debugger,
+            &mut writer,
+        )
+        .or_else(|e| {
+            // A failed command is reported, the session goes on.
+            writeln!(writer, "{e}")?;
+            Ok(CommandOutcome::Continue)
+        });
+        let command_output = writer.1;
+        self.refresh_prompt();
+        // A relaunched program wrote to a new pipe.
+        if let Some(stdout) = self.debugger.take_program_output() {
+            events.add_child_output(stdout);
+        }
+        let mut writer = Writer::new(terminal);
+        for line in events.drain_child_output() {
+            self.session.log(&line);
+            writeln!(writer, "{line}")?;
+        }
         writer.flush()?;
+        self.session.log(&String::from_utf8_lossy(&command_output));
+        self.pending_output = String::from_utf8_lossy(&command_output)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        Self::show_page(terminal, &mut self.pending_output)?;
+        if let Some(event_log) = &mut self.event_log {
+            event_log.drain(&mut self.debugger)?;
+        }
         res
     }
 
+    /// Print as much of `pending` as fits on the screen, leaving a prompt if there is more.
+    fn show_page(
+        terminal: &mut DefaultTerminal,
+        pending: &mut std::collections::VecDeque<String>,
+    ) -> Result<()> {
+        // Keep the prompt line and the pager hint visible.
+        let page = usize::from(terminal.size()?.height.saturating_sub(2)).max(1);
+        let mut writer = Writer::new(terminal);
+        for line in pending.drain(..page.min(pending.len())) {
+            writeln!(writer, "{line}")?;
+        }
+        if !pending.is_empty() {
+            writeln!(writer, "--Type <RET> for more, q to quit--")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn prompt_width(&self) -> u16 {
+        // Columns, not bytes or chars, so wide and multi-byte prompts line up too.
+        u16::try_from(Span::raw(self.prompt.as_str()).width()).unwrap_or(u16::MAX)
+    }
+
+    /// Grow or shrink the inline viewport to the lines the wrapped input takes.
+    fn fit_viewport(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let size = terminal.size()?;
+        let height = if self.wrap_input {
+            let width = size.width.saturating_sub(self.prompt_width()).max(1);
+            let (lines, _) = wrap_input(self.input.value(), self.input.cursor(), width);
+            u16::try_from(lines.len())
+                .unwrap_or(u16::MAX)
+                .clamp(1, size.height.max(1))
+        } else {
+            1
+        };
+        if height == self.input_height {
+            return Ok(());
+        }
+        // An inline viewport keeps its height for life, so start a new one where this one
+        // begins, after wiping what it showed.
+        let top = terminal.get_frame().area().top();
+        terminal.clear()?;
+        terminal.set_cursor_position((0, top))?;
+        *terminal = Terminal::with_options(
+            CrosstermBackend::new(std::io::stdout()),
+            TerminalOptions {
+                viewport: Viewport::Inline(height),
+            },
+        )?;
+        self.input_height = height;
+        Ok(())
+    }
+
     fn render(&self, frame: &mut Frame) {
+        // The prompt needs a column of input after it to be any use.
+        if frame.area().width <= self.prompt_width() {
+            frame.render_widget(
+                Paragraph::new("terminal too small").style(Style::default().fg(Color::Red)),
+                frame.area(),
+            );
+            return;
+        }
         let [prompt_area, input_area] =
-            Layout::horizontal([Constraint::Length(5), Constraint::Min(1)]).areas(frame.area());
+            Layout::horizontal([Constraint::Length(self.prompt_width()), Constraint::Min(1)])
+                .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(
+                self.prompt.as_str(),
+                Style::default().fg(Color::Yellow),
+            ))),
+            prompt_area,
+        );
+        if self.wrap_input {
+            self.render_wrapped_input(frame, input_area);
+        } else {
+            self.render_input(frame, input_area);
+        }
+    }
 
-        frame.render_widget(Paragraph::new("sdb> ").style(Color::Yellow), prompt_area);
-        self.render_input(frame, input_area);
+    fn render_wrapped_input(&self, frame: &mut Frame, area: Rect) {
+        let (lines, (x, y)) = wrap_input(self.input.value(), self.input.cursor(), area.width);
+        // Should the input outgrow the screen, keep the cursor's line in view.
+        let scroll = y.saturating_sub(area.height.saturating_sub(1));
+        let input = Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+            .style(Style::bold(Color::White.into()))
+            .scroll((scroll, 0));
+        frame.render_widget(input, area);
+        frame.set_cursor_position((
+            area.x + x.min(area.width.saturating_sub(1)),
+            area.y + (y - scroll).min(area.height.saturating_sub(1)),
+        ));
     }
 
     fn render_input(&self, frame: &mut Frame, area: Rect) {
@@ -306,12 +897,310 @@ impl Gui {
         let scroll = self.input.visual_scroll(area.width as usize);
         let input = Paragraph::new(self.input.value())
             .style(Style::bold(Color::White.into()))
-            .scroll((0, scroll.try_into().unwrap()));
+            .scroll((0, u16::try_from(scroll).unwrap_or(u16::MAX)));
         frame.render_widget(input, area);
 
         // Ratatui hides the cursor unless it's explicitly set. Position the  cursor past the
         // end of the input text and one line down from the border to the input line
         let x = self.input.visual_cursor().max(scroll) - scroll;
-        frame.set_cursor_position((area.x + u16::try_from(x).unwrap(), area.y));
+        let x = u16::try_from(x)
+            .unwrap_or(u16::MAX)
+            .min(area.width.saturating_sub(1));
+        frame.set_cursor_position((area.x + x, area.y));
+    }
+}
+
+/// The prompt naming process `pid`, and its thread `tid` when that is not the main one:
+/// `sdb [1234]> ` or `sdb [1234.1240]> `, plain `sdb> ` without a process.
+fn prompt_for(current: Option<(sdblib::Pid, sdblib::Pid)>) -> String {
+    match current {
+        Some((pid, tid)) if tid != pid => format!("sdb [{pid}.{tid}]> "),
+        Some((pid, _)) => format!("sdb [{pid}]> "),
+        None => PROMPT.to_string(),
+    }
+}
+
+/// Break `input` into lines of at most `width` columns, and find where the cursor, before
+/// character `cursor`, lands among them.
+fn wrap_input(input: &str, cursor: usize, width: u16) -> (Vec<String>, (u16, u16)) {
+    let mut lines = vec![String::new()];
+    let mut column = 0;
+    let mut cursor_position = None;
+    for (index, c) in input.chars().enumerate() {
+        let char_width = u16::try_from(c.width().unwrap_or(0)).unwrap_or(u16::MAX);
+        if column + char_width > width && column > 0 {
+            lines.push(String::new());
+            column = 0;
+        }
+        if index == cursor {
+            cursor_position = Some((column, lines.len() - 1));
+        }
+        lines.last_mut().unwrap().push(c);
+        column += char_width;
+    }
+    // A cursor past the last character moves to the next line once this one is full.
+    let (x, y) = cursor_position.unwrap_or_else(|| {
+        if column >= width {
+            lines.push(String::new());
+            (0, lines.len() - 1)
+        } else {
+            (column, lines.len() - 1)
+        }
+    });
+    (lines, (x, u16::try_from(y).unwrap_or(u16::MAX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An event handler without the task reading the terminal, which tests do not have, and
+    /// what sends in its stead.
+    fn events_without_terminal() -> (TokioEventHandler, mpsc::Sender<Event>) {
+        let (tx, rx) = mpsc::channel(INPUT_CAPACITY);
+        let (output_tx, output_rx) = mpsc::channel(OUTPUT_CAPACITY);
+        let events = TokioEventHandler {
+            rx,
+            output_tx,
+            output_rx,
+            _task: None,
+            child_pipes: Vec::new(),
+            backlog: std::collections::VecDeque::new(),
+            log_child_output: false,
+        };
+        (events, tx)
+    }
+
+    #[tokio::test]
+    async fn child_output_is_all_there_once_the_child_stops() {
+        let (mut events, tx) = events_without_terminal();
+        let mut child = std::process::Command::new("/bin/sh")
+            .args(["-c", "echo one; echo two; printf three"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        events.add_child_output(child.stdout.take().unwrap());
+        // Like a `continue` to the exit: the command returns once the program has stopped.
+        child.wait().unwrap();
+        tx.try_send(Event::Tick).unwrap();
+        assert_eq!(events.drain_child_output(), ["one", "two", "three"]);
+        assert!(events.drain_child_output().is_empty());
+        // The reader may have told of the end before the tick was sent, or left it to the drain.
+        let mut ticks = 0;
+        let mut ends = Vec::new();
+        for _ in 0..2 {
+            match events.next().await.unwrap() {
+                Event::Tick => ticks += 1,
+                Event::ChildOutputEnded(end) => ends.push(end),
+                other => panic!("{other:?}"),
+            }
+        }
+        assert_eq!((ticks, ends), (1, vec![OutputEnd::Closed]));
+        assert!(events.rx.is_empty() && events.output_rx.is_empty() && events.backlog.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_flood_of_output_waits_in_the_pipe_behind_the_terminal() {
+        let (mut events, tx) = events_without_terminal();
+        let mut child = std::process::Command::new("seq")
+            .arg("1000000")
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        events.add_child_output(child.stdout.take().unwrap());
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        // A reader waits for room for two events.
+        while events.output_rx.len() < OUTPUT_CAPACITY - 1 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(events.output_rx.len(), OUTPUT_CAPACITY - 1);
+        // Nothing is read while nothing is shown, so the child blocks in its writes.
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(events.output_rx.len(), OUTPUT_CAPACITY - 1);
+        assert!(child.try_wait().unwrap().is_none());
+        tx.try_send(Event::Tick).unwrap();
+        assert!(matches!(events.next().await, Ok(Event::Tick)));
+
+        let mut count = 0;
+        let mut last = String::new();
+        loop {
+            match events.next().await.unwrap() {
+                Event::ChildOutput(lines) => {
+                    count += lines.len();
+                    last = lines.last().cloned().unwrap_or(last);
+                }
+                Event::ChildOutputEnded(end) => {
+                    assert_eq!(end, OutputEnd::Closed);
+                    break;
+                }
+                other => panic!("{other:?}"),
+            }
+        }
+        assert_eq!((count, last.as_str()), (1_000_000, "1000000"));
+        child.wait().unwrap();
+    }
+
+    #[tokio::test]
+    async fn closing_the_output_is_told_while_the_child_runs_on() {
+        let (mut events, _tx) = events_without_terminal();
+        let mut child = std::process::Command::new("/bin/sh")
+            .args(["-c", "echo bye; exec sleep 10 >&-"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        events.add_child_output(child.stdout.take().unwrap());
+        let mut seen = Vec::new();
+        while !matches!(seen.last(), Some(Event::ChildOutputEnded(_))) {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.next())
+                .await
+                .expect("the end of the output is told")
+                .unwrap();
+            seen.push(event);
+        }
+        assert!(
+            matches!(&seen[..], [Event::ChildOutput(lines), Event::ChildOutputEnded(OutputEnd::Closed)]
+                if lines == &["bye"]),
+            "{seen:?}"
+        );
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[tokio::test]
+    async fn unended_lines_are_flushed_with_the_rest() {
+        let (mut events, _tx) = events_without_terminal();
+        // Asks something, then waits with the pipe still open, like a stopped inferior.
+        let mut child = std::process::Command::new("/bin/sh")
+            .args(["-c", "printf 'one\\nName: '; exec sleep 10"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        events.add_child_output(child.stdout.take().unwrap());
+        let mut lines = Vec::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while lines.len() < 2 && std::time::Instant::now() < deadline {
+            lines.extend(events.drain_child_output());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(lines, ["one", "Name: "]);
+    }
+
+    #[test]
+    fn tiny_terminals_get_a_message_instead_of_a_panic() {
+        let mut gui = Gui::new(
+            sdblib::Debugger::new(),
+            crate::command::Session::default(),
+            None,
+            None,
+        );
+        gui.input = Input::default().with_value("x".repeat(100));
+        for (width, height) in [(1, 1), (5, 1), (6, 1), (7, 3)] {
+            for wrap in [false, true] {
+                gui.wrap_input = wrap;
+                let mut terminal =
+                    Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+                terminal.draw(|frame| gui.render(frame)).unwrap();
+                let first_line: String = terminal.backend().buffer().content()
+                    [..usize::from(width)]
+                    .iter()
+                    .map(ratatui::buffer::Cell::symbol)
+                    .collect();
+                if width <= 5 {
+                    assert_eq!(first_line, "terminal too small"[..usize::from(width)]);
+                } else {
+                    assert!(first_line.starts_with(PROMPT), "{first_line}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_prompt_names_the_current_process_and_thread() {
+        let gui = Gui::new(
+            sdblib::Debugger::new(),
+            crate::command::Session::default(),
+            None,
+            None,
+        );
+        assert_eq!(gui.prompt, PROMPT);
+        let pid = sdblib::Pid::from_raw(1234);
+        assert_eq!(prompt_for(Some((pid, pid))), "sdb [1234]> ");
+        assert_eq!(
+            prompt_for(Some((pid, sdblib::Pid::from_raw(1240)))),
+            "sdb [1234.1240]> "
+        );
+    }
+
+    #[test]
+    fn quitting_asks_before_it_kills_the_program() {
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let mut gui = Gui::new(
+            sdblib::Debugger::new(),
+            crate::command::Session::default(),
+            None,
+            None,
+        );
+        assert!(gui.quit());
+        let _stdout = gui.debugger.add_program("/bin/sleep", ["30"]).unwrap();
+        let pid = gui.debugger.current_process().unwrap();
+        assert!(!gui.quit());
+        assert_eq!(gui.prompt, QUIT_QUESTION);
+        assert_eq!(gui.answer_quit(key('x')), None);
+        // No stays, with the program killed on exit.
+        assert_eq!(gui.answer_quit(key('n')), Some(false));
+        assert_eq!(gui.prompt, format!("sdb [{pid}]> "));
+        assert!(!gui.quit());
+        assert_eq!(gui.answer_quit(key('y')), Some(true));
+
+        // Asked which, no detaches it.
+        gui.debugger.set_exit_policy(sdblib::ExitPolicy::Ask);
+        assert!(!gui.quit());
+        assert_eq!(gui.answer_quit(key('n')), Some(true));
+        assert_eq!(gui.debugger.exit_policy(), sdblib::ExitPolicy::Detach);
+        assert!(gui.quit());
+        gui.debugger.set_exit_policy(sdblib::ExitPolicy::Kill);
+        gui.session.confirm = false;
+        assert!(gui.quit());
+    }
+
+    #[test]
+    fn pasted_lines_are_held_for_the_next_enter() {
+        let mut gui = Gui::new(
+            sdblib::Debugger::new(),
+            crate::command::Session::default(),
+            None,
+            None,
+        );
+        gui.input = Input::default()
+            .with_value("break  # b".into())
+            .with_cursor(6);
+        assert!(gui.paste("main").is_empty());
+        assert_eq!(gui.input.value(), "break main # b");
+        assert_eq!(gui.input.cursor(), 10);
+        assert_eq!(gui.paste("\r\nrun\r\nbt\nfin"), ["break main", "run", "bt"]);
+        assert_eq!(gui.input.value(), "fin # b");
+        assert_eq!(gui.input.cursor(), 3);
+        // What follows the cursor stays in the input.
+        assert_eq!(gui.paste("ish\n"), ["finish"]);
+        assert_eq!(gui.input.value(), " # b");
+        assert_eq!(gui.input.cursor(), 0);
+        assert_eq!(gui.pasted_lines, ["break main", "run", "bt", "finish"]);
+    }
+
+    #[test]
+    fn wrapped_input_breaks_by_columns() {
+        let (lines, cursor) = wrap_input("abcdefg", 7, 3);
+        assert_eq!(lines, ["abc", "def", "g"]);
+        assert_eq!(cursor, (1, 2));
+        // Wide characters take two columns and never straddle a line end.
+        let (lines, cursor) = wrap_input("a漢字", 1, 4);
+        assert_eq!(lines, ["a漢", "字"]);
+        assert_eq!(cursor, (1, 0));
+        let (lines, cursor) = wrap_input("abc", 3, 3);
+        assert_eq!(lines, ["abc", ""]);
+        assert_eq!(cursor, (0, 1));
     }
 }
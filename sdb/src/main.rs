@@ -9,22 +9,57 @@
 
 use clap::{Args, Parser};
 use tracing::subscriber::set_global_default;
+use tracing_subscriber::layer::SubscriberExt;
 
 mod command;
+mod events;
+mod expression;
 mod gui;
+mod json;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Write a timestamped trace log to a new file in this directory, `~` being the home
+    /// directory. The path of the file is printed on startup
     #[arg(long)]
     log_dir: Option<std::path::PathBuf>,
 
+    /// The least severe messages logged: trace, debug, info, warn or error. Defaults to trace
+    /// in the `--log-dir` file and info on the terminal. `RUST_LOG` may set it per module,
+    /// as in `sdblib=debug,info`, this flag replacing its fallback level
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<tracing::Level>,
+
+    /// Write a JSON object per line for every process event to this file, `-` for stdout
+    #[arg(long)]
+    events: Option<std::path::PathBuf>,
+
+    /// Run the commands read from stdin without the terminal interface, then exit with the
+    /// status of the program: its exit code, or 128 plus the signal that killed it. The
+    /// interactive interface always exits with 0 once quit. With `--commands` or `--eval`,
+    /// those are run instead of stdin.
+    #[arg(long)]
+    batch: bool,
+
+    /// Run the commands in this file first, before any `--eval`. An `exit` in it ends sdb
+    /// there, skipping the rest of the file, the `--eval`s and the terminal interface
+    #[arg(long, value_name = "FILE")]
+    commands: Option<std::path::PathBuf>,
+
+    /// Run this command after the `--commands` file, in the order given; may be repeated.
+    /// Then the terminal interface starts, unless `--batch` is given
+    #[arg(long, value_name = "COMMAND")]
+    eval: Vec<String>,
+
     #[command(flatten)]
     attachment: Attachment,
 }
 
+/// What to debug from the start. Without either, sdb starts with no program, for `run` or an
+/// attach later on.
 #[derive(Args)]
-#[group(required = true, multiple = false)]
+#[group(multiple = false)]
 struct Attachment {
     program: Vec<String>,
 
@@ -50,24 +85,144 @@ impl std::io::Write for Writer {
 async fn main() {
     let args = Cli::parse();
 
-    let file_log_info = args
-        .log_dir
+    // Flushes the log file when dropped, at the end of main.
+    let log_to_file = args.log_dir.is_some();
+    let _log_guard = set_up_tracing(args.log_dir, args.log_level);
+
+    let mut debugger = sdblib::Debugger::new();
+    let mut event_log = args.events.map(|path| {
+        events::EventLog::open(&path).unwrap_or_else(|e| {
+            eprintln!("failed to open {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+    if event_log.is_some() {
+        debugger.record_events();
+    }
+
+    let mut output_ran_command = None;
+    if let Some(pid) = args.attachment.pid {
+        let state = i32::try_from(pid)
+            .ok()
+            .and_then(|raw| sdblib::process_state(sdblib::Pid::from_raw(raw)).ok());
+        match debugger.add_proc(pid).and_then(|()| debugger.wait()) {
+            Ok(()) => {
+                if let Some(state) = state {
+                    println!("process {pid} was {state} when attached");
+                }
+            }
+            Err(e) => start_failed(
+                &format!("failed to attach to process {pid}: {e}"),
+                args.batch,
+            ),
+        }
+    } else if !args.attachment.program.is_empty() {
+        match debugger.add_program(
+            args.attachment.program.first().unwrap(),
+            args.attachment.program[1..].iter(),
+        ) {
+            Ok(stdout) => {
+                output_ran_command = Some(stdout);
+                println!(
+                    "stopped at entry point {:#x}",
+                    debugger.program_counter().unwrap()
+                );
+            }
+            Err(e) => start_failed(&e.to_string(), args.batch),
+        }
+    }
+
+    if let Some(event_log) = &mut event_log
+        && let Err(e) = event_log.drain(&mut debugger)
+    {
+        eprintln!("failed to write the events: {e}");
+        // Exiting skips destructors, and the debugger's lets go of the programs.
+        drop(debugger);
+        std::process::exit(1);
+    }
+
+    let mut session = command::Session::default_macro_file()
+        .map(command::Session::with_macro_file)
+        .unwrap_or_default();
+    let startup = startup_commands(args.commands.as_deref(), args.eval);
+    let mut program_output = ProgramOutput {
+        pipe: output_ran_command,
+        forward: args.batch,
+    };
+    program_output.update(&mut debugger);
+    // Batch mode reads stdin unless it was given its commands already.
+    let commands: Box<dyn Iterator<Item = String>> = if args.batch && startup.is_empty() {
+        Box::new(std::io::stdin().lines().map_while(Result::ok))
+    } else {
+        Box::new(startup.into_iter())
+    };
+    let quit = run_commands(
+        commands,
+        &mut session,
+        &mut debugger,
+        &mut event_log,
+        &mut program_output,
+        &mut std::io::stdout(),
+    );
+    if args.batch || quit {
+        let code = debugger.exit_status().map_or(0, sdblib::ExitStatus::code);
+        // Exiting skips destructors, and the debugger's lets go of the programs.
+        drop(debugger);
+        std::process::exit(code);
+    }
+
+    let mut gui = gui::Gui::new(debugger, session, program_output.pipe, event_log);
+    if log_to_file {
+        gui.log_child_output();
+    }
+    gui.run().await.unwrap();
+}
+
+/// Report a program that could not be launched or attached to. In batch mode there is nothing
+/// left to do, but the terminal interface still comes up, with no program.
+fn start_failed(message: &str, batch: bool) {
+    eprintln!("{message}");
+    if batch {
+        std::process::exit(1);
+    }
+}
+
+/// Log to a new timestamped file in `log_dir`, everything down to traces, or else the
+/// information and up to the terminal, unless `log_level` or `RUST_LOG` say otherwise. The
+/// guard returned flushes the file when dropped.
+fn set_up_tracing(
+    log_dir: Option<std::path::PathBuf>,
+    log_level: Option<tracing::Level>,
+) -> Option<tracing_appender::non_blocking::WorkerGuard> {
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let file_log_info = log_dir
         .map(|log_dir| {
-            std::fs::create_dir_all(&log_dir).unwrap();
+            let log_dir = expand_home(&log_dir, std::env::var_os("HOME").as_deref())
+                .and_then(|log_dir| std::path::absolute(log_dir).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    eprintln!("invalid --log-dir {}: {e}", log_dir.display());
+                    std::process::exit(1);
+                });
+            if let Err(e) = std::fs::create_dir_all(&log_dir) {
+                eprintln!("failed to create {}: {e}", log_dir.display());
+                std::process::exit(1);
+            }
 
-            let file: std::fs::File = loop {
+            let (file, path) = loop {
                 // Create a file with the current timestamp to avoid overwriting previous logs
                 let file_name = format!("sdb-{}.log", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+                let path = log_dir.join(&file_name);
                 let file = std::fs::File::options()
                     .read(true)
                     .write(true)
                     .create_new(true)
-                    .open(log_dir.join(&file_name));
+                    .open(&path);
                 if let Ok(file) = file {
-                    break file;
+                    break (file, path);
                 }
                 std::thread::sleep(std::time::Duration::from_millis(1));
             };
+            eprintln!("logging to {}", path.display());
 
             tracing_appender::non_blocking(Writer(std::io::BufWriter::new(file)))
         })
@@ -82,36 +237,209 @@ async fn main() {
                 .with_level(true)
                 .with_ansi(false)
                 .with_max_level(tracing::Level::TRACE)
-                .finish(),
+                .finish()
+                .with(log_filter(
+                    log_level,
+                    tracing::Level::TRACE,
+                    rust_log.as_deref(),
+                )),
         )
     } else {
         set_global_default(
             tracing_subscriber::fmt()
-                .with_max_level(tracing::Level::INFO)
+                .with_max_level(tracing::Level::TRACE)
                 .with_ansi(true)
-                .finish(),
+                .finish()
+                .with(log_filter(
+                    log_level,
+                    tracing::Level::INFO,
+                    rust_log.as_deref(),
+                )),
         )
     }
     .expect("setting default subscriber failed");
+    file_log_info.1
+}
 
-    let mut debugger = sdblib::Debugger::new();
+/// What gets logged: the `RUST_LOG` directives if any, falling back to `level`, or else
+/// `default`. Directives that do not parse are reported and ignored.
+fn log_filter(
+    level: Option<tracing::Level>,
+    default: tracing::Level,
+    rust_log: Option<&str>,
+) -> tracing_subscriber::filter::Targets {
+    let targets = rust_log
+        .and_then(|rust_log| {
+            rust_log
+                .parse::<tracing_subscriber::filter::Targets>()
+                .inspect_err(|e| eprintln!("ignoring RUST_LOG={rust_log}: {e}"))
+                .ok()
+        })
+        .unwrap_or_else(|| tracing_subscriber::filter::Targets::new().with_default(default));
+    match level {
+        Some(level) => targets.with_default(level),
+        None => targets,
+    }
+}
 
-    let mut output_ran_command = None;
-    if let Some(pid) = args.attachment.pid {
-        debugger.add_proc(pid).unwrap();
-    } else if !args.attachment.program.is_empty() {
-        output_ran_command = Some(
-            debugger
-                .add_program(
-                    args.attachment.program.first().unwrap(),
-                    args.attachment.program[1..].iter(),
-                )
-                .unwrap(),
-        );
+/// `path` with a leading `~` replaced by `home`, as the shell would have done had it not been
+/// quoted or glued to `--log-dir=`.
+fn expand_home(
+    path: &std::path::Path,
+    home: Option<&std::ffi::OsStr>,
+) -> Result<std::path::PathBuf, String> {
+    path.strip_prefix("~").map_or_else(
+        |_| Ok(path.to_path_buf()),
+        |rest| {
+            home.map(|home| std::path::Path::new(home).join(rest))
+                .ok_or_else(|| "cannot expand ~, HOME is not set".to_string())
+        },
+    )
+}
+
+/// The `--commands` file, whole so blocks and `define`s may span lines, then the `--eval`s.
+fn startup_commands(file: Option<&std::path::Path>, evals: Vec<String>) -> Vec<String> {
+    let mut commands = Vec::new();
+    if let Some(path) = file {
+        match std::fs::read_to_string(path) {
+            Ok(script) => commands.push(script),
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
     }
+    commands.extend(evals);
+    commands
+}
 
-    debugger.wait().unwrap();
+/// The output of the program being debugged, for the terminal interface to show or, in batch
+/// mode, copied to our stdout as it comes.
+struct ProgramOutput {
+    pipe: Option<std::process::ChildStdout>,
+    forward: bool,
+}
 
-    let mut gui = gui::Gui::new(debugger, output_ran_command);
-    gui.run().await.unwrap();
+impl ProgramOutput {
+    /// Take over the output of the program if it was launched again.
+    fn update(&mut self, debugger: &mut sdblib::Debugger) {
+        if let Some(pipe) = debugger.take_program_output() {
+            self.pipe = Some(pipe);
+        }
+        if self.forward
+            && let Some(pipe) = self.pipe.take()
+        {
+            forward_output(pipe);
+        }
+    }
+}
+
+/// Copy everything the program writes to our own stdout, as nobody reads it otherwise.
+fn forward_output(mut child_output: std::process::ChildStdout) {
+    std::thread::spawn(move || std::io::copy(&mut child_output, &mut std::io::stdout()));
+}
+
+/// Run `commands` in order, as if typed. A failed one is reported on stderr and the others
+/// still run. Returns whether one of them quit, which ends the run there.
+fn run_commands(
+    commands: impl IntoIterator<Item = String>,
+    session: &mut command::Session,
+    debugger: &mut sdblib::Debugger,
+    event_log: &mut Option<events::EventLog>,
+    program_output: &mut ProgramOutput,
+    output: &mut dyn std::io::Write,
+) -> bool {
+    for line in commands {
+        let outcome = command::run_command(&line, session, debugger, output).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            command::CommandOutcome::Continue
+        });
+        program_output.update(debugger);
+        if let Some(event_log) = event_log
+            && let Err(e) = event_log.drain(debugger)
+        {
+            eprintln!("{e}");
+        }
+        if outcome == command::CommandOutcome::Quit {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn startup_commands_run_in_order_until_one_quits() {
+        let mut session = command::Session::default();
+        let mut debugger = sdblib::Debugger::new();
+        let mut program_output = ProgramOutput {
+            pipe: None,
+            forward: false,
+        };
+        let mut output = Vec::new();
+        let mut run = |commands: &[&str], output: &mut Vec<u8>| {
+            run_commands(
+                commands.iter().map(ToString::to_string),
+                &mut session,
+                &mut debugger,
+                &mut None,
+                &mut program_output,
+                output,
+            )
+        };
+        assert!(!run(&["p 1\ndefine two p 2", "two", "p 3"], &mut output));
+        assert_eq!(String::from_utf8(output).unwrap(), "1 = 1\n2 = 2\n3 = 3\n");
+
+        let mut output = Vec::new();
+        assert!(run(&["p 1\nexit\np 2", "p 3"], &mut output));
+        assert_eq!(String::from_utf8(output).unwrap(), "1 = 1\n");
+    }
+
+    #[test]
+    fn log_level_replaces_the_fallback_of_rust_log() {
+        use tracing::Level;
+        use tracing_subscriber::filter::LevelFilter;
+
+        let filter = log_filter(None, Level::INFO, None);
+        assert!(filter.would_enable("sdblib", &Level::INFO));
+        assert!(!filter.would_enable("sdblib", &Level::DEBUG));
+        let filter = log_filter(Some(Level::DEBUG), Level::INFO, None);
+        assert!(filter.would_enable("sdblib", &Level::DEBUG));
+
+        let rust_log = Some("sdblib::breakpoints=trace,warn");
+        let filter = log_filter(None, Level::INFO, rust_log);
+        assert!(filter.would_enable("sdblib::breakpoints", &Level::TRACE));
+        assert!(!filter.would_enable("sdb", &Level::INFO));
+        let filter = log_filter(Some(Level::ERROR), Level::INFO, rust_log);
+        assert!(filter.would_enable("sdblib::breakpoints", &Level::TRACE));
+        assert_eq!(filter.default_level(), Some(LevelFilter::ERROR));
+
+        let filter = log_filter(None, Level::TRACE, Some("sdb=loud"));
+        assert_eq!(filter.default_level(), Some(LevelFilter::TRACE));
+    }
+
+    #[test]
+    fn log_dir_tilde_is_the_home_directory() {
+        let home = Some(std::ffi::OsStr::new("/home/me"));
+        let expand = |path: &str, home| expand_home(std::path::Path::new(path), home);
+        assert_eq!(
+            expand("~/logs", home).unwrap(),
+            std::path::Path::new("/home/me/logs")
+        );
+        assert_eq!(expand("~", home).unwrap(), std::path::Path::new("/home/me"));
+        // Only a whole `~` component is the home directory.
+        assert_eq!(
+            expand("~logs", home).unwrap(),
+            std::path::Path::new("~logs")
+        );
+        assert_eq!(
+            expand("logs/~", home).unwrap(),
+            std::path::Path::new("logs/~")
+        );
+        assert!(expand("~/logs", None).is_err());
+        assert_eq!(expand("logs", None).unwrap(), std::path::Path::new("logs"));
+    }
 }
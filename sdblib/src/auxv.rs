@@ -0,0 +1,166 @@
+use crate::DebuggerError;
+
+/// `AT_NULL`, the type of the entry that ends the vector.
+const AT_NULL: u64 = 0;
+
+/// How the value of an auxiliary vector entry reads best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuxvFormat {
+    Decimal,
+    Hex,
+    /// The address of a NUL terminated string.
+    String,
+}
+
+/// The entry types the kernel passes, from `<elf.h>`: type, name, description and format.
+const AUXV_TYPES: [(u64, &str, &str, AuxvFormat); 28] = [
+    (1, "AT_IGNORE", "Special", AuxvFormat::Hex),
+    (
+        2,
+        "AT_EXECFD",
+        "File descriptor of program",
+        AuxvFormat::Decimal,
+    ),
+    (3, "AT_PHDR", "Program headers for program", AuxvFormat::Hex),
+    (
+        4,
+        "AT_PHENT",
+        "Size of program header entry",
+        AuxvFormat::Decimal,
+    ),
+    (
+        5,
+        "AT_PHNUM",
+        "Number of program headers",
+        AuxvFormat::Decimal,
+    ),
+    (6, "AT_PAGESZ", "System page size", AuxvFormat::Decimal),
+    (7, "AT_BASE", "Base address of interpreter", AuxvFormat::Hex),
+    (8, "AT_FLAGS", "Flags", AuxvFormat::Hex),
+    (9, "AT_ENTRY", "Entry point of program", AuxvFormat::Hex),
+    (10, "AT_NOTELF", "Program is not ELF", AuxvFormat::Decimal),
+    (11, "AT_UID", "Real user ID", AuxvFormat::Decimal),
+    (12, "AT_EUID", "Effective user ID", AuxvFormat::Decimal),
+    (13, "AT_GID", "Real group ID", AuxvFormat::Decimal),
+    (14, "AT_EGID", "Effective group ID", AuxvFormat::Decimal),
+    (
+        15,
+        "AT_PLATFORM",
+        "String identifying platform",
+        AuxvFormat::String,
+    ),
+    (
+        16,
+        "AT_HWCAP",
+        "Machine-dependent CPU capability hints",
+        AuxvFormat::Hex,
+    ),
+    (17, "AT_CLKTCK", "Frequency of times()", AuxvFormat::Decimal),
+    (
+        23,
+        "AT_SECURE",
+        "Boolean, was exec setuid-like?",
+        AuxvFormat::Decimal,
+    ),
+    (
+        24,
+        "AT_BASE_PLATFORM",
+        "String identifying base platform",
+        AuxvFormat::String,
+    ),
+    (
+        25,
+        "AT_RANDOM",
+        "Address of 16 random bytes",
+        AuxvFormat::Hex,
+    ),
+    (26, "AT_HWCAP2", "Extension of AT_HWCAP", AuxvFormat::Hex),
+    (
+        27,
+        "AT_RSEQ_FEATURE_SIZE",
+        "rseq supported feature size",
+        AuxvFormat::Decimal,
+    ),
+    (
+        28,
+        "AT_RSEQ_ALIGN",
+        "rseq allocation alignment",
+        AuxvFormat::Decimal,
+    ),
+    (29, "AT_HWCAP3", "Extension of AT_HWCAP", AuxvFormat::Hex),
+    (30, "AT_HWCAP4", "Extension of AT_HWCAP", AuxvFormat::Hex),
+    (
+        31,
+        "AT_EXECFN",
+        "File name of executable",
+        AuxvFormat::String,
+    ),
+    (
+        33,
+        "AT_SYSINFO_EHDR",
+        "System-supplied DSO's ELF header",
+        AuxvFormat::Hex,
+    ),
+    (
+        51,
+        "AT_MINSIGSTKSZ",
+        "Minimal stack size for signal delivery",
+        AuxvFormat::Hex,
+    ),
+];
+
+/// An entry of the ELF auxiliary vector the kernel hands a program at `execve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuxvEntry {
+    /// The `AT_*` type.
+    pub key: u64,
+    pub value: u64,
+}
+
+impl AuxvEntry {
+    fn describe(&self) -> Option<&'static (u64, &'static str, &'static str, AuxvFormat)> {
+        AUXV_TYPES.iter().find(|(key, ..)| *key == self.key)
+    }
+
+    /// The `AT_*` name of the type, `None` for types we do not know.
+    #[must_use]
+    pub fn name(&self) -> Option<&'static str> {
+        self.describe().map(|(_, name, ..)| *name)
+    }
+
+    /// What the entry tells, `None` for types we do not know.
+    #[must_use]
+    pub fn description(&self) -> Option<&'static str> {
+        self.describe().map(|(_, _, description, _)| *description)
+    }
+
+    /// How the value reads best; unknown types are shown in hex.
+    #[must_use]
+    pub fn format(&self) -> AuxvFormat {
+        self.describe()
+            .map_or(AuxvFormat::Hex, |(.., format)| *format)
+    }
+}
+
+/// Decode an auxiliary vector: pairs of native words, type then value, up to `AT_NULL`.
+fn parse_auxv(bytes: &[u8]) -> Vec<AuxvEntry> {
+    bytes
+        .chunks_exact(16)
+        .map(|pair| {
+            let (key, value) = pair.split_at(8);
+            AuxvEntry {
+                key: u64::from_ne_bytes(key.try_into().unwrap_or_default()),
+                value: u64::from_ne_bytes(value.try_into().unwrap_or_default()),
+            }
+        })
+        .take_while(|entry| entry.key != AT_NULL)
+        .collect()
+}
+
+/// Read the auxiliary vector of process `pid` from `/proc/<pid>/auxv`.
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read.
+pub fn read_auxv(pid: nix::unistd::Pid) -> Result<Vec<AuxvEntry>, DebuggerError> {
+    Ok(parse_auxv(&std::fs::read(format!("/proc/{pid}/auxv"))?))
+}
@@ -0,0 +1,98 @@
+use crate::DebuggerError;
+use crate::debug_info::SourceLocation;
+use crate::memory::read_word;
+use crate::symbols::SymbolLocation;
+use serde::Serialize;
+
+/// One frame of a call stack, innermost first.
+///
+/// Only data: how a frame reads is up to whoever shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Frame {
+    /// How far out the frame is, 0 being the innermost one.
+    pub index: usize,
+    /// Where execution is (innermost frame) or will resume (every other frame).
+    pub pc: u64,
+    /// The value of rsp while this frame is executing.
+    pub stack_pointer: u64,
+    /// The value of rbp while this frame is executing.
+    pub frame_pointer: u64,
+    /// The function `pc` is in, with the offset of `pc` into it, if the symbols say.
+    pub symbol: Option<SymbolLocation>,
+    /// The source line being executed, or the call being made for outer frames, if the
+    /// DWARF says.
+    pub source: Option<SourceLocation>,
+}
+
+impl Frame {
+    /// The address to look `pc` up at: outer frames resume after the call, which may already
+    /// be the next line or even the next function.
+    #[must_use]
+    pub const fn lookup_pc(&self) -> u64 {
+        if self.index == 0 {
+            self.pc
+        } else {
+            self.pc.saturating_sub(1)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Backtrace {
+    pub frames: Vec<Frame>,
+    /// The frame pointer chain went outwards-to-inwards, so walking stopped early rather than
+    /// risk looping forever over a corrupt stack.
+    pub corrupted: bool,
+}
+
+/// Walk the rbp chain of `pid` collecting at most `max_frames` frames, without symbols or
+/// source locations.
+///
+/// The stack grows down, so every caller's frame pointer must be above its callee's. A saved
+/// rbp that is not is treated as the end of a corrupt chain.
+pub fn frame_pointer_backtrace(
+    pid: nix::unistd::Pid,
+    max_frames: usize,
+) -> Result<Backtrace, DebuggerError> {
+    let regs = nix::sys::ptrace::getregs(pid)?;
+    let mut backtrace = Backtrace {
+        frames: vec![Frame {
+            index: 0,
+            pc: regs.rip,
+            stack_pointer: regs.rsp,
+            frame_pointer: regs.rbp,
+            symbol: None,
+            source: None,
+        }],
+        corrupted: false,
+    };
+    let mut frame_pointer = regs.rbp;
+    while backtrace.frames.len() < max_frames && frame_pointer != 0 {
+        // Unreadable memory just means we walked off the end of the chain.
+        let (Ok(return_address), Ok(caller_frame_pointer)) = (
+            read_word(pid, frame_pointer + 8),
+            read_word(pid, frame_pointer),
+        ) else {
+            break;
+        };
+        if return_address == 0 {
+            break;
+        }
+        backtrace.frames.push(Frame {
+            index: backtrace.frames.len(),
+            pc: return_address,
+            // Returning pops the saved rbp and the return address off the callee's frame.
+            stack_pointer: frame_pointer + 16,
+            frame_pointer: caller_frame_pointer,
+            symbol: None,
+            source: None,
+        });
+        if caller_frame_pointer != 0 && caller_frame_pointer <= frame_pointer {
+            backtrace.corrupted = true;
+            break;
+        }
+        frame_pointer = caller_frame_pointer;
+    }
+    backtrace.frames.truncate(max_frames);
+    Ok(backtrace)
+}
@@ -0,0 +1,163 @@
+use crate::DebuggerError;
+use crate::maps::read_memory_map;
+use crate::memory::{read_word, replace_byte, write_word};
+
+/// The `int3` instruction.
+const INT3: u8 = 0xcc;
+
+/// `si_code` of the `SIGTRAP` raised by executing `int3`.
+const SI_KERNEL: i32 = 0x80;
+
+/// The id of the breakpoint on [`LOADER_HOOK`], set while some breakpoint is pending. Like
+/// id 0, it is never given to the user's breakpoints.
+pub const LOADER_BREAKPOINT_ID: usize = usize::MAX;
+
+/// The function of the dynamic loader it calls whenever the libraries loaded change, there
+/// for debuggers to break on.
+pub const LOADER_HOOK: &str = "_dl_debug_state";
+
+/// Where the user asked for a breakpoint, which outlives the process it was patched into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointLocation {
+    /// A fixed address, set again at the same address.
+    Address(u64),
+    /// `offset` bytes into a function, looked up again in every new process.
+    Function { name: String, offset: u64 },
+}
+
+impl std::fmt::Display for BreakpointLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "*{address:#x}"),
+            Self::Function { name, offset: 0 } => write!(f, "{name}"),
+            Self::Function { name, offset } => write!(f, "{name}+{offset:#x}"),
+        }
+    }
+}
+
+/// What re-setting the breakpoints in a new process came to.
+#[derive(Debug, Default)]
+pub struct Rearmed {
+    /// How many breakpoints are set again.
+    pub count: usize,
+    /// The breakpoints that could not be, by id, which are deleted.
+    pub failures: Vec<(usize, BreakpointLocation, DebuggerError)>,
+}
+
+/// A software breakpoint: an `int3` written over the first byte of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// The number the user refers to it by, starting at 1.
+    pub id: usize,
+    /// What the user asked for, to find the address again after a relaunch.
+    pub location: BreakpointLocation,
+    /// The process whose code was patched.
+    pub pid: nix::unistd::Pid,
+    pub address: u64,
+    /// Whether it stops the program. A disabled breakpoint has its `int3` lifted, unless another
+    /// enabled one shares the address.
+    pub enabled: bool,
+    /// The byte the `int3` replaced.
+    original_byte: u8,
+}
+
+impl Breakpoint {
+    /// Patch the code of `pid` at `address`. `original_byte` is what another breakpoint at the
+    /// same address already saved, as the code there is patched already.
+    pub(crate) fn insert(
+        id: usize,
+        location: BreakpointLocation,
+        pid: nix::unistd::Pid,
+        address: u64,
+        original_byte: Option<u8>,
+    ) -> Result<Self, DebuggerError> {
+        let original_byte = if let Some(byte) = original_byte {
+            byte
+        } else {
+            check_code_address(pid, address)?;
+            replace_byte(pid, address, INT3)?
+        };
+        Ok(Self {
+            id,
+            location,
+            pid,
+            address,
+            enabled: true,
+            original_byte,
+        })
+    }
+
+    /// Whether the user set it, rather than the debugger for its own ends, like the temporary
+    /// breakpoint stepping over a call or `start` runs to, which is id 0.
+    #[must_use]
+    pub const fn is_user_breakpoint(&self) -> bool {
+        self.id != 0 && self.id != LOADER_BREAKPOINT_ID
+    }
+
+    pub(crate) const fn original_byte(&self) -> u8 {
+        self.original_byte
+    }
+
+    /// Put the original instruction back, to execute it or for good.
+    pub(crate) fn remove(&self) -> Result<(), DebuggerError> {
+        replace_byte(self.pid, self.address, self.original_byte).map(drop)
+    }
+
+    /// Patch the code again after [`Self::remove`].
+    pub(crate) fn reinsert(&self) -> Result<(), DebuggerError> {
+        replace_byte(self.pid, self.address, INT3).map(drop)
+    }
+
+    /// [`Self::remove`] for a moment, to execute the original instruction. Returns the patched
+    /// word to hand to [`Self::restore`], which then has nothing to read.
+    pub(crate) fn lift(&self) -> Result<u64, DebuggerError> {
+        let patched = read_word(self.pid, self.address)?;
+        write_word(
+            self.pid,
+            self.address,
+            (patched & !0xff) | u64::from(self.original_byte),
+        )?;
+        Ok(patched)
+    }
+
+    /// Put back the word [`Self::lift`] returned. Code is not written to while the original
+    /// instruction runs, so that is still the rest of the word.
+    pub(crate) fn restore(&self, patched: u64) -> Result<(), DebuggerError> {
+        write_word(self.pid, self.address, patched)
+    }
+}
+
+/// Refuse to patch `address` unless it is in an executable mapping of `pid`, where the `int3`
+/// can ever run. `ptrace` faults pages in that are not resident yet, so only the mapping counts.
+fn check_code_address(pid: nix::unistd::Pid, address: u64) -> Result<(), DebuggerError> {
+    let regions = read_memory_map(pid)?;
+    let region = regions
+        .iter()
+        .find(|region| (region.start..region.end).contains(&address))
+        .ok_or_else(|| {
+            DebuggerError::ErrorMessage(format!(
+                "Cannot insert breakpoint at {address:#x}: the address is not mapped."
+            ))
+        })?;
+    if !region.is_executable() {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "Cannot insert breakpoint at {address:#x}: {} is not executable.",
+            region.path.as_deref().unwrap_or("anonymous memory")
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the `SIGTRAP` `tid` stopped with was raised by an `int3`, rather than by a single
+/// step or sent by someone.
+pub fn stopped_by_int3(tid: nix::unistd::Pid) -> bool {
+    nix::sys::ptrace::getsiginfo(tid).is_ok_and(|info| info.si_code == SI_KERNEL)
+}
+
+/// Whether the `SIGTRAP` `tid` stopped with ends the single step it was making: raised by the
+/// processor after the instruction, rather than by an `int3` it executed or sent by someone,
+/// which have an `si_code` of their own or none above zero.
+pub fn stopped_by_step(tid: nix::unistd::Pid) -> bool {
+    nix::sys::ptrace::getsiginfo(tid)
+        .is_ok_and(|info| info.si_code > 0 && info.si_code != SI_KERNEL)
+}
@@ -0,0 +1,1137 @@
+use crate::DebuggerError;
+use crate::symbols::FunctionSymbol;
+use object::{Object, ObjectSection, ObjectSegment};
+
+type Reader<'a> = gimli::EndianSlice<'a, gimli::LittleEndian>;
+
+/// Number of registers DWARF numbers on x86-64 that we can track for a frame: the general
+/// purpose ones plus the return address column.
+const DWARF_REGISTER_COUNT: usize = 17;
+
+/// The registers of one frame, indexed by their DWARF number. Outer frames only know the
+/// registers the unwinder could recover.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameRegisters {
+    registers: [Option<u64>; DWARF_REGISTER_COUNT],
+}
+
+impl FrameRegisters {
+    #[must_use]
+    pub const fn from_user_regs(regs: &nix::libc::user_regs_struct) -> Self {
+        Self {
+            registers: [
+                Some(regs.rax),
+                Some(regs.rdx),
+                Some(regs.rcx),
+                Some(regs.rbx),
+                Some(regs.rsi),
+                Some(regs.rdi),
+                Some(regs.rbp),
+                Some(regs.rsp),
+                Some(regs.r8),
+                Some(regs.r9),
+                Some(regs.r10),
+                Some(regs.r11),
+                Some(regs.r12),
+                Some(regs.r13),
+                Some(regs.r14),
+                Some(regs.r15),
+                Some(regs.rip),
+            ],
+        }
+    }
+
+    #[must_use]
+    pub const fn from_frame(frame: &crate::Frame) -> Self {
+        let mut registers = [None; DWARF_REGISTER_COUNT];
+        registers[6] = Some(frame.frame_pointer);
+        registers[7] = Some(frame.stack_pointer);
+        registers[16] = Some(frame.pc);
+        Self { registers }
+    }
+
+    fn get(&self, register: gimli::Register) -> Option<u64> {
+        self.registers
+            .get(usize::from(register.0))
+            .copied()
+            .flatten()
+    }
+}
+
+/// A value read from the inferior, interpreted according to its DWARF type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Bool(bool),
+    Char(u8),
+    Pointer(u64),
+    /// Anything we do not know how to interpret, such as aggregates.
+    Bytes(Vec<u8>),
+}
+
+/// A named variable or parameter of the inferior.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub type_name: String,
+    /// `None` when the variable has no location at the current pc, e.g. it was optimized out
+    /// or lives in a register the unwinder could not recover.
+    pub value: Option<Value>,
+}
+
+/// A variable with static storage, at file scope or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalVariable {
+    pub name: String,
+    pub type_name: String,
+    /// The runtime address, `None` for thread-local variables: those live at an offset from
+    /// each thread's `fs_base`, which is not resolved yet.
+    pub address: Option<u64>,
+    /// In bytes.
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeKind {
+    Signed,
+    Unsigned,
+    Float,
+    Bool,
+    Char,
+    Pointer,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TypeInfo {
+    name: String,
+    kind: TypeKind,
+    size: usize,
+}
+
+impl TypeInfo {
+    fn decode(&self, bytes: &[u8]) -> Value {
+        let mut word = [0u8; 8];
+        let len = bytes.len().min(8);
+        word[..len].copy_from_slice(&bytes[..len]);
+        let unsigned = u64::from_le_bytes(word);
+        match self.kind {
+            TypeKind::Signed if (1..=8).contains(&len) => {
+                let shift = 64 - 8 * u32::try_from(len).unwrap_or(8);
+                Value::Signed(unsigned.cast_signed() << shift >> shift)
+            }
+            TypeKind::Unsigned => Value::Unsigned(unsigned),
+            TypeKind::Float if len == 4 => Value::Float(f64::from(f32::from_le_bytes([
+                word[0], word[1], word[2], word[3],
+            ]))),
+            TypeKind::Float if len == 8 => Value::Float(f64::from_le_bytes(word)),
+            TypeKind::Bool => Value::Bool(unsigned != 0),
+            TypeKind::Char => Value::Char(word[0]),
+            TypeKind::Pointer => Value::Pointer(unsigned),
+            _ => Value::Bytes(bytes.to_vec()),
+        }
+    }
+}
+
+/// What a location expression needs from the stopped inferior.
+struct EvaluationContext<'a> {
+    registers: FrameRegisters,
+    load_bias: u64,
+    cfa: Option<u64>,
+    frame_base: Option<u64>,
+    read_memory: &'a dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+}
+
+/// The canonical frame address `row` says how to compute.
+fn cfa(row: &gimli::UnwindTableRow<usize>, registers: &FrameRegisters) -> Option<u64> {
+    match row.cfa() {
+        gimli::CfaRule::RegisterAndOffset { register, offset } => {
+            registers.get(*register)?.checked_add_signed(*offset)
+        }
+        gimli::CfaRule::Expression(_) => None,
+    }
+}
+
+/// A line of source code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct SourceLocation {
+    pub file: std::path::PathBuf,
+    pub line: u64,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.file.file_name().unwrap_or(self.file.as_os_str());
+        write!(f, "{}:{}", name.to_string_lossy(), self.line)
+    }
+}
+
+/// Where the code of a source line starts, or where a sequence of code ends.
+#[derive(Debug, Clone, Copy)]
+struct LineRow {
+    address: u64,
+    /// An index into [`LineTable::files`].
+    file: usize,
+    line: u64,
+    end_sequence: bool,
+}
+
+/// The mapping from code addresses to source lines DWARF describes, at file addresses.
+#[derive(Debug, Default)]
+pub struct LineTable {
+    files: Vec<std::path::PathBuf>,
+    /// Sorted by address, and where a sequence ends as the next one starts, end first.
+    rows: Vec<LineRow>,
+}
+
+impl LineTable {
+    fn row(&self, address: u64) -> Option<&LineRow> {
+        let index = self.rows.partition_point(|row| row.address <= address);
+        let row = self.rows.get(index.checked_sub(1)?)?;
+        // Line 0 is code no line is responsible for, like compiler generated code.
+        (!row.end_sequence && row.line != 0).then_some(row)
+    }
+
+    /// The source line the code at file address `address` belongs to.
+    #[must_use]
+    pub fn location(&self, address: u64) -> Option<SourceLocation> {
+        self.row(address).map(|row| SourceLocation {
+            file: self.files[row.file].clone(),
+            line: row.line,
+        })
+    }
+
+    /// Whether file address `address` is where the code of a source line starts, rather than
+    /// somewhere in the middle of it.
+    #[must_use]
+    pub fn is_line_start(&self, address: u64) -> bool {
+        self.row(address).is_some_and(|row| row.address == address)
+    }
+
+    /// The file address where the code of line `line` of `file` starts. A line with no code of
+    /// its own, like a blank one or a comment, resolves to the next line of the file that has.
+    #[must_use]
+    pub fn line_address(&self, file: &std::path::Path, line: u64) -> Option<u64> {
+        self.rows
+            .iter()
+            .filter(|row| {
+                !row.end_sequence && row.line >= line.max(1) && self.files[row.file] == file
+            })
+            .min_by_key(|row| (row.line, row.address))
+            .map(|row| row.address)
+    }
+}
+
+/// A file mapped read-only into our memory: the kernel reads in the pages that are touched,
+/// so the sections never looked at are never read, and the file is not copied.
+///
+/// The mapping is private, but a file truncated while mapped would still make reading the
+/// lost pages fault. Binaries are normally replaced rather than rewritten in place, which
+/// leaves the mapped one intact.
+struct FileMapping {
+    /// `None` for an empty file, which cannot be mapped.
+    address: Option<std::ptr::NonNull<std::ffi::c_void>>,
+    len: usize,
+}
+
+// SAFETY: the mapping is read-only and owned by this value alone.
+unsafe impl Send for FileMapping {}
+// SAFETY: the mapping is never written, so shared references can read it from any thread.
+unsafe impl Sync for FileMapping {}
+
+impl FileMapping {
+    fn map(path: &std::path::Path) -> Result<Self, DebuggerError> {
+        let file = std::fs::File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| DebuggerError::ErrorMessage(format!("{} is too big", path.display())))?;
+        let Some(length) = std::num::NonZeroUsize::new(len) else {
+            return Ok(Self { address: None, len });
+        };
+        // SAFETY: a fresh private read-only mapping aliases nothing of ours; it stays valid
+        // after the file is closed, until it is unmapped on drop.
+        let address = unsafe {
+            nix::sys::mman::mmap(
+                None,
+                length,
+                nix::sys::mman::ProtFlags::PROT_READ,
+                nix::sys::mman::MapFlags::MAP_PRIVATE,
+                &file,
+                0,
+            )?
+        };
+        Ok(Self {
+            address: Some(address),
+            len,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self.address.map_or(&[], |address| {
+            // SAFETY: the mapping is `len` readable bytes for as long as `self` lives.
+            unsafe { std::slice::from_raw_parts(address.as_ptr().cast::<u8>(), self.len) }
+        })
+    }
+}
+
+impl Drop for FileMapping {
+    fn drop(&mut self) {
+        if let Some(address) = self.address {
+            // SAFETY: this is the mapping made in `map`, and nothing borrows it any more.
+            if let Err(e) = unsafe { nix::sys::mman::munmap(address, self.len) } {
+                tracing::event!(tracing::Level::WARN, "Could not unmap a file: {}", e);
+            }
+        }
+    }
+}
+
+/// The ELF file of an inferior, with whatever DWARF it carries.
+pub struct DebugInfo {
+    data: FileMapping,
+}
+
+/// The bytes of the file would drown the logs.
+impl std::fmt::Debug for DebugInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugInfo")
+            .field("len", &self.data.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DebugInfo {
+    /// Map the ELF file at `path`. Nothing past its headers is read until it is needed.
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or is not a 64-bit ELF file.
+    pub fn load(path: &std::path::Path) -> Result<Self, DebuggerError> {
+        let info = Self {
+            data: FileMapping::map(path)?,
+        };
+        info.elf()?;
+        Ok(info)
+    }
+
+    fn elf(&self) -> Result<object::read::elf::ElfFile64<'_>, DebuggerError> {
+        object::read::elf::ElfFile64::parse(self.data.as_slice())
+            .map_err(|e| DebuggerError::ErrorMessage(format!("Invalid ELF file: {e}")))
+    }
+
+    fn section_data(&self, name: &str) -> &[u8] {
+        self.elf()
+            .ok()
+            .and_then(|elf| elf.section_by_name(name)?.data().ok())
+            .unwrap_or_default()
+    }
+
+    /// The architecture the file was built for, like `x86_64`.
+    #[must_use]
+    pub fn architecture(&self) -> String {
+        self.elf().map_or_else(
+            |_| "unknown".to_string(),
+            |elf| format!("{:?}", elf.architecture()).to_lowercase(),
+        )
+    }
+
+    /// Where the program starts, as a file address.
+    #[must_use]
+    pub fn entry_point(&self) -> u64 {
+        self.elf().map_or(0, |elf| elf.entry())
+    }
+
+    #[must_use]
+    pub fn has_dwarf(&self) -> bool {
+        !self.section_data(".debug_info").is_empty()
+    }
+
+    /// The lowest address the file asks to be loaded at. The difference with where it was
+    /// actually mapped is the load bias.
+    #[must_use]
+    pub fn first_load_address(&self) -> u64 {
+        self.elf()
+            .ok()
+            .and_then(|elf| elf.segments().map(|segment| segment.address()).min())
+            .map_or(0, |address| address & !0xfff)
+    }
+
+    fn dwarf(&self) -> Result<gimli::Dwarf<Reader<'_>>, DebuggerError> {
+        gimli::Dwarf::load(|id| -> Result<Reader<'_>, DebuggerError> {
+            Ok(gimli::EndianSlice::new(
+                self.section_data(id.name()),
+                gimli::LittleEndian,
+            ))
+        })
+    }
+
+    /// Compute the canonical frame address of the frame executing at file address `pc` from
+    /// the call frame information in `.eh_frame`.
+    fn cfa(&self, pc: u64, registers: &FrameRegisters) -> Option<u64> {
+        self.unwind_row(pc, |row| cfa(row, registers))
+    }
+
+    /// Look up what `.eh_frame` says about the frame executing at file address `pc`.
+    fn unwind_row<T>(
+        &self,
+        pc: u64,
+        f: impl FnOnce(&gimli::UnwindTableRow<usize>) -> Option<T>,
+    ) -> Option<T> {
+        use gimli::UnwindSection;
+        let elf = self.elf().ok()?;
+        let eh_frame_section = elf.section_by_name(".eh_frame")?;
+        let text_section = elf.section_by_name(".text")?;
+        let eh_frame = gimli::EhFrame::new(eh_frame_section.data().ok()?, gimli::LittleEndian);
+        let bases = gimli::BaseAddresses::default()
+            .set_eh_frame(eh_frame_section.address())
+            .set_text(text_section.address());
+        let mut context = gimli::UnwindContext::new();
+        let row = eh_frame
+            .unwind_info_for_address(&bases, &mut context, pc, gimli::EhFrame::cie_from_offset)
+            .ok()?;
+        f(row)
+    }
+
+    /// The registers the caller of the frame executing at file address `pc` gets back when it
+    /// returns, unwound with the call frame information in `.eh_frame`: `None` if it does not
+    /// describe the frame, or says something we cannot follow.
+    pub fn caller_frame(
+        &self,
+        pc: u64,
+        registers: &FrameRegisters,
+        read_memory: &dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+    ) -> Option<crate::Frame> {
+        self.unwind_row(pc, |row| {
+            let cfa = cfa(row, registers)?;
+            let recover = |register: gimli::Register| match row.register(register) {
+                // An undefined return address marks the outermost frame.
+                gimli::RegisterRule::Undefined if register == gimli::X86_64::RA => None,
+                gimli::RegisterRule::Undefined | gimli::RegisterRule::SameValue => {
+                    registers.get(register)
+                }
+                gimli::RegisterRule::Offset(offset) => {
+                    let bytes = read_memory(cfa.checked_add_signed(offset)?, 8).ok()?;
+                    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+                }
+                _ => None,
+            };
+            Some(crate::Frame {
+                index: 1,
+                pc: recover(gimli::X86_64::RA)?,
+                // Returning pops everything down to the canonical frame address.
+                stack_pointer: cfa,
+                frame_pointer: recover(gimli::X86_64::RBP)?,
+                symbol: None,
+                source: None,
+            })
+        })
+    }
+
+    /// The named functions DWARF describes, at their file addresses.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed.
+    pub fn dwarf_functions(&self) -> Result<Vec<FunctionSymbol>, DebuggerError> {
+        let dwarf = self.dwarf()?;
+        let mut functions = Vec::new();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            while let Some((_, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_subprogram {
+                    continue;
+                }
+                // Declarations and inlined-only functions have no code of their own.
+                let (Some(name), Some(low_pc)) = (
+                    entry.attr_value(gimli::DW_AT_name)?,
+                    entry.attr_value(gimli::DW_AT_low_pc)?,
+                ) else {
+                    continue;
+                };
+                let Some(address) = dwarf.attr_address(&unit, low_pc)? else {
+                    continue;
+                };
+                // DW_AT_high_pc is either an address or, since DWARF 4, the size.
+                let size = match entry.attr_value(gimli::DW_AT_high_pc)? {
+                    Some(gimli::AttributeValue::Addr(high)) => high.saturating_sub(address),
+                    Some(value) => value.udata_value().unwrap_or_default(),
+                    None => 0,
+                };
+                functions.push(FunctionSymbol {
+                    name: dwarf
+                        .attr_string(&unit, name)?
+                        .to_string_lossy()
+                        .into_owned(),
+                    address,
+                    size,
+                });
+            }
+        }
+        Ok(functions)
+    }
+
+    /// The functions defined in `.symtab` and `.dynsym`, at their file addresses. This is all
+    /// there is to go by in binaries without DWARF.
+    #[must_use]
+    pub fn elf_functions(&self) -> Vec<FunctionSymbol> {
+        use object::ObjectSymbol;
+        let Ok(elf) = self.elf() else {
+            return Vec::new();
+        };
+        elf.symbols()
+            .chain(elf.dynamic_symbols())
+            .filter(|symbol| {
+                symbol.kind() == object::SymbolKind::Text
+                    && symbol.is_definition()
+                    && symbol.address() != 0
+            })
+            .filter_map(|symbol| {
+                Some(FunctionSymbol {
+                    name: symbol.name().ok()?.to_string(),
+                    address: symbol.address(),
+                    size: symbol.size(),
+                })
+            })
+            .collect()
+    }
+
+    /// Evaluate the parameters of the function executing at file address `pc`.
+    ///
+    /// Returns `None` if there is no debug information for `pc`.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed.
+    pub fn arguments(
+        &self,
+        pc: u64,
+        registers: &FrameRegisters,
+        load_bias: u64,
+        read_memory: &dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+    ) -> Result<Option<Vec<Variable>>, DebuggerError> {
+        let mut arguments = Vec::new();
+        let found = self.for_each_parameter(
+            pc,
+            registers,
+            load_bias,
+            read_memory,
+            |name, type_info, pieces, context| {
+                let value = pieces
+                    .and_then(|pieces| read_pieces(&pieces, type_info.size, context))
+                    .map(|bytes| type_info.decode(&bytes));
+                arguments.push(Variable {
+                    name,
+                    type_name: type_info.name,
+                    value,
+                });
+            },
+        )?;
+        Ok(found.then_some(arguments))
+    }
+
+    /// Where the parameter called `name` of the function executing at file address `pc`
+    /// lives, to write it. Returns `None` if there is no such parameter.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed, or the parameter has no single place it
+    /// can be written to at `pc`.
+    pub fn parameter_place(
+        &self,
+        pc: u64,
+        registers: &FrameRegisters,
+        load_bias: u64,
+        read_memory: &dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+        name: &str,
+    ) -> Result<Option<Assignable>, DebuggerError> {
+        let mut found = None;
+        self.for_each_parameter(
+            pc,
+            registers,
+            load_bias,
+            read_memory,
+            |parameter, type_info, pieces, _| {
+                if found.is_none() && parameter == name {
+                    found = Some(
+                        place(name, pieces.as_deref()).map(|place| Assignable { place, type_info }),
+                    );
+                }
+            },
+        )?;
+        found.transpose()
+    }
+
+    /// Call `visit` with each parameter of the function executing at file address `pc`, its
+    /// type, and where it lives there if it is anywhere. Returns `false` if there is no debug
+    /// information for `pc`.
+    fn for_each_parameter(
+        &self,
+        pc: u64,
+        registers: &FrameRegisters,
+        load_bias: u64,
+        read_memory: &dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+        mut visit: impl FnMut(
+            String,
+            TypeInfo,
+            Option<Vec<gimli::Piece<Reader<'_>>>>,
+            &EvaluationContext<'_>,
+        ),
+    ) -> Result<bool, DebuggerError> {
+        let dwarf = self.dwarf()?;
+        let Some((unit, function)) = find_function(&dwarf, pc)? else {
+            return Ok(false);
+        };
+        let mut context = EvaluationContext {
+            registers: *registers,
+            load_bias,
+            cfa: self.cfa(pc, registers),
+            frame_base: None,
+            read_memory,
+        };
+
+        let mut tree = unit.entries_tree(Some(function))?;
+        let root = tree.root()?;
+        context.frame_base = root
+            .entry()
+            .attr_value(gimli::DW_AT_frame_base)?
+            .and_then(|attr| attr.exprloc_value())
+            .and_then(|expression| frame_base(expression, unit.encoding(), &context));
+
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_formal_parameter {
+                continue;
+            }
+            let name = match entry.attr_value(gimli::DW_AT_name)? {
+                Some(attr) => dwarf
+                    .attr_string(&unit, attr)?
+                    .to_string_lossy()
+                    .into_owned(),
+                None => String::from("<anonymous>"),
+            };
+            let type_info = describe_type(&dwarf, &unit, entry.attr_value(gimli::DW_AT_type)?)?;
+            let pieces = match entry.attr_value(gimli::DW_AT_location)? {
+                Some(attr) => location_expression(&dwarf, &unit, attr, pc)?
+                    .and_then(|expression| evaluate(expression, unit.encoding(), &context).ok()),
+                None => None,
+            };
+            visit(name, type_info, pieces, &context);
+        }
+        Ok(true)
+    }
+}
+
+impl DebugInfo {
+    /// The variables with static storage DWARF describes, with their types.
+    fn globals(&self, load_bias: u64) -> Result<Vec<(GlobalVariable, TypeInfo)>, DebuggerError> {
+        let dwarf = self.dwarf()?;
+        let mut globals = Vec::new();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            while let Some((_, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_variable {
+                    continue;
+                }
+                // Locals are found relative to a frame, and declarations have no location.
+                let Some(gimli::AttributeValue::Exprloc(expression)) =
+                    entry.attr_value(gimli::DW_AT_location)?
+                else {
+                    continue;
+                };
+                let mut address = None;
+                let mut thread_local = false;
+                let mut operations = expression.operations(unit.encoding());
+                while let Ok(Some(operation)) = operations.next() {
+                    match operation {
+                        gimli::Operation::Address { address: file } if address.is_none() => {
+                            address = Some(file.wrapping_add(load_bias));
+                        }
+                        gimli::Operation::TLS => thread_local = true,
+                        _ => {}
+                    }
+                }
+                if address.is_none() && !thread_local {
+                    continue;
+                }
+                // Out of line definitions, like C++ static members, name their declaration.
+                let named = match entry.attr_value(gimli::DW_AT_specification)? {
+                    Some(gimli::AttributeValue::UnitRef(offset)) => unit.entry(offset)?,
+                    _ => entry.clone(),
+                };
+                let Some(name) = named.attr_value(gimli::DW_AT_name)? else {
+                    continue;
+                };
+                let type_attr = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(attr) => Some(attr),
+                    None => named.attr_value(gimli::DW_AT_type)?,
+                };
+                let type_info = describe_type(&dwarf, &unit, type_attr)?;
+                globals.push((
+                    GlobalVariable {
+                        name: dwarf
+                            .attr_string(&unit, name)?
+                            .to_string_lossy()
+                            .into_owned(),
+                        type_name: type_info.name.clone(),
+                        address: address.filter(|_| !thread_local),
+                        size: type_info.size,
+                    },
+                    type_info,
+                ));
+            }
+        }
+        Ok(globals)
+    }
+
+    /// The line tables of every compilation unit, merged. Only rows that start a statement
+    /// are kept, as those are where a debugger can stop at a line.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed.
+    pub fn line_table(&self) -> Result<LineTable, DebuggerError> {
+        let dwarf = self.dwarf()?;
+        let mut table = LineTable::default();
+        let mut files = std::collections::HashMap::new();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                if !row.is_stmt() && !row.end_sequence() {
+                    continue;
+                }
+                let path = match row.file(header) {
+                    Some(file) => {
+                        let mut path = std::path::PathBuf::new();
+                        if let Some(directory) = file.directory(header) {
+                            path.push(
+                                dwarf
+                                    .attr_string(&unit, directory)?
+                                    .to_string_lossy()
+                                    .as_ref(),
+                            );
+                        }
+                        if path.is_relative()
+                            && let Some(comp_dir) = &unit.comp_dir
+                        {
+                            path = std::path::Path::new(comp_dir.to_string_lossy().as_ref())
+                                .join(path);
+                        }
+                        path.push(
+                            dwarf
+                                .attr_string(&unit, file.path_name())?
+                                .to_string_lossy()
+                                .as_ref(),
+                        );
+                        path
+                    }
+                    None => std::path::PathBuf::new(),
+                };
+                let file = *files.entry(path.clone()).or_insert_with(|| {
+                    table.files.push(path);
+                    table.files.len() - 1
+                });
+                table.rows.push(LineRow {
+                    address: row.address(),
+                    file,
+                    line: row.line().map_or(0, std::num::NonZeroU64::get),
+                    end_sequence: row.end_sequence(),
+                });
+            }
+        }
+        table
+            .rows
+            .sort_by_key(|row| (row.address, !row.end_sequence));
+        Ok(table)
+    }
+
+    /// The variables with static storage DWARF describes, at runtime addresses given the
+    /// file was loaded `load_bias` bytes away from where it asked.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed.
+    pub fn global_variables(&self, load_bias: u64) -> Result<Vec<GlobalVariable>, DebuggerError> {
+        Ok(self
+            .globals(load_bias)?
+            .into_iter()
+            .map(|(global, _)| global)
+            .collect())
+    }
+
+    /// Read the variable with static storage called `name`, if DWARF knows one.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed, or the variable is thread-local.
+    pub fn global_value(
+        &self,
+        name: &str,
+        load_bias: u64,
+        read_memory: &dyn Fn(u64, usize) -> Result<Vec<u8>, DebuggerError>,
+    ) -> Result<Option<Variable>, DebuggerError> {
+        let Some((global, type_info)) = self
+            .globals(load_bias)?
+            .into_iter()
+            .find(|(global, _)| global.name == name)
+        else {
+            return Ok(None);
+        };
+        let Some(address) = global.address else {
+            return Err(DebuggerError::ErrorMessage(format!(
+                "Cannot access thread-local variable \"{name}\": its address depends on the thread's fs_base, which is not resolved yet."
+            )));
+        };
+        Ok(Some(Variable {
+            name: global.name,
+            type_name: global.type_name,
+            value: read_memory(address, type_info.size)
+                .ok()
+                .map(|bytes| type_info.decode(&bytes)),
+        }))
+    }
+
+    /// Where the variable with static storage called `name` lives, to write it. Returns
+    /// `None` if DWARF knows no such variable.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed, or the variable is thread-local.
+    pub fn global_place(
+        &self,
+        name: &str,
+        load_bias: u64,
+    ) -> Result<Option<Assignable>, DebuggerError> {
+        let Some((global, type_info)) = self
+            .globals(load_bias)?
+            .into_iter()
+            .find(|(global, _)| global.name == name)
+        else {
+            return Ok(None);
+        };
+        let Some(address) = global.address else {
+            return Err(DebuggerError::ErrorMessage(format!(
+                "Cannot set thread-local variable \"{name}\": its address depends on the thread's fs_base, which is not resolved yet."
+            )));
+        };
+        Ok(Some(Assignable {
+            place: Place::Memory(address),
+            type_info,
+        }))
+    }
+}
+
+/// Where a variable can be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Place {
+    Memory(u64),
+    /// A DWARF register number, of the frame the variable was looked up in.
+    Register(u16),
+}
+
+/// A variable that can be assigned to: where it lives, and what its type can hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignable {
+    pub place: Place,
+    type_info: TypeInfo,
+}
+
+impl Assignable {
+    /// The bytes variable `name` holds `value` as, keeping the low bytes if its type is
+    /// narrower, like a C assignment does.
+    /// # Errors
+    ///
+    /// Will return `Err` if the type is not an integer or pointer of at most 8 bytes.
+    pub fn encode(&self, name: &str, value: u64) -> Result<Vec<u8>, DebuggerError> {
+        match self.type_info.kind {
+            TypeKind::Signed
+            | TypeKind::Unsigned
+            | TypeKind::Bool
+            | TypeKind::Char
+            | TypeKind::Pointer
+                if (1..=8).contains(&self.type_info.size) =>
+            {
+                Ok(value.to_le_bytes()[..self.type_info.size].to_vec())
+            }
+            _ => Err(DebuggerError::ErrorMessage(format!(
+                "Cannot set \"{name}\" of type {}: only integers and pointers can be set.",
+                self.type_info.name
+            ))),
+        }
+    }
+}
+
+/// The one place the pieces of variable `name` say it lives.
+fn place(name: &str, pieces: Option<&[gimli::Piece<Reader<'_>>]>) -> Result<Place, DebuggerError> {
+    let cannot = |why: &str| DebuggerError::ErrorMessage(format!("Cannot set \"{name}\": {why}."));
+    match pieces {
+        Some([piece]) => match piece.location {
+            gimli::Location::Address { address } => Ok(Place::Memory(address)),
+            gimli::Location::Register { register } => Ok(Place::Register(register.0)),
+            gimli::Location::Value { .. }
+            | gimli::Location::Bytes { .. }
+            | gimli::Location::ImplicitPointer { .. } => {
+                Err(cannot("it was optimized into a constant here"))
+            }
+            gimli::Location::Empty => Err(cannot("it is optimized out here")),
+        },
+        Some([_, _, ..]) => Err(cannot("it is split over several places here")),
+        None | Some([]) => Err(cannot("it is optimized out here")),
+    }
+}
+
+fn ranges_contain(
+    mut ranges: gimli::RangeIter<Reader<'_>>,
+    pc: u64,
+) -> Result<bool, DebuggerError> {
+    while let Some(range) = ranges.next()? {
+        if (range.begin..range.end).contains(&pc) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Find the unit and the subprogram entry whose code contains file address `pc`.
+fn find_function<'a>(
+    dwarf: &gimli::Dwarf<Reader<'a>>,
+    pc: u64,
+) -> Result<Option<(gimli::Unit<Reader<'a>>, gimli::UnitOffset)>, DebuggerError> {
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next()? {
+        let unit = dwarf.unit(header)?;
+        if !ranges_contain(dwarf.unit_ranges(&unit)?, pc)? {
+            continue;
+        }
+        let mut found = None;
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() == gimli::DW_TAG_subprogram
+                && ranges_contain(dwarf.die_ranges(&unit, entry)?, pc)?
+            {
+                found = Some(entry.offset());
+                break;
+            }
+        }
+        if let Some(offset) = found {
+            return Ok(Some((unit, offset)));
+        }
+    }
+    Ok(None)
+}
+
+fn describe_type(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    attr: Option<gimli::AttributeValue<Reader<'_>>>,
+) -> Result<TypeInfo, DebuggerError> {
+    let Some(gimli::AttributeValue::UnitRef(offset)) = attr else {
+        return Ok(TypeInfo {
+            name: String::from("void"),
+            kind: TypeKind::Other,
+            size: 0,
+        });
+    };
+    let entry = unit.entry(offset)?;
+    let name = match entry.attr_value(gimli::DW_AT_name)? {
+        Some(attr) => Some(
+            dwarf
+                .attr_string(unit, attr)?
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        None => None,
+    };
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|attr| attr.udata_value())
+        .and_then(|size| usize::try_from(size).ok())
+        .unwrap_or_default();
+    let inner = || {
+        describe_type(
+            dwarf,
+            unit,
+            entry.attr_value(gimli::DW_AT_type).ok().flatten(),
+        )
+    };
+    Ok(match entry.tag() {
+        gimli::DW_TAG_base_type => {
+            let kind = match entry
+                .attr_value(gimli::DW_AT_encoding)?
+                .map(|attr| match attr {
+                    gimli::AttributeValue::Encoding(encoding) => encoding,
+                    _ => gimli::DwAte(0),
+                }) {
+                Some(gimli::DW_ATE_signed) => TypeKind::Signed,
+                Some(gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF) => TypeKind::Unsigned,
+                Some(gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char) => TypeKind::Char,
+                Some(gimli::DW_ATE_boolean) => TypeKind::Bool,
+                Some(gimli::DW_ATE_float) => TypeKind::Float,
+                _ => TypeKind::Other,
+            };
+            TypeInfo {
+                name: name.unwrap_or_default(),
+                kind,
+                size,
+            }
+        }
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => TypeInfo {
+            name: {
+                let pointee = inner()?.name;
+                if pointee.ends_with('*') {
+                    format!("{pointee}*")
+                } else {
+                    format!("{pointee} *")
+                }
+            },
+            kind: TypeKind::Pointer,
+            size: 8,
+        },
+        gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => {
+            let qualifier = if entry.tag() == gimli::DW_TAG_const_type {
+                "const"
+            } else {
+                "volatile"
+            };
+            let inner = inner()?;
+            TypeInfo {
+                name: format!("{qualifier} {}", inner.name),
+                ..inner
+            }
+        }
+        gimli::DW_TAG_typedef => TypeInfo {
+            name: name.unwrap_or_default(),
+            ..inner()?
+        },
+        gimli::DW_TAG_enumeration_type => TypeInfo {
+            name: format!("enum {}", name.unwrap_or_default()),
+            kind: TypeKind::Signed,
+            size,
+        },
+        tag => {
+            let prefix = match tag {
+                gimli::DW_TAG_structure_type => "struct ",
+                gimli::DW_TAG_union_type => "union ",
+                gimli::DW_TAG_class_type => "class ",
+                _ => "",
+            };
+            TypeInfo {
+                name: format!("{prefix}{}", name.unwrap_or_else(|| String::from("?"))),
+                kind: TypeKind::Other,
+                size,
+            }
+        }
+    })
+}
+
+/// The location expression of a variable that applies at file address `pc`, looking it up in
+/// the location list if the variable moves around.
+fn location_expression<'a>(
+    dwarf: &gimli::Dwarf<Reader<'a>>,
+    unit: &gimli::Unit<Reader<'a>>,
+    attr: gimli::AttributeValue<Reader<'a>>,
+    pc: u64,
+) -> Result<Option<gimli::Expression<Reader<'a>>>, DebuggerError> {
+    if let gimli::AttributeValue::Exprloc(expression) = attr {
+        return Ok(Some(expression));
+    }
+    let Some(mut locations) = dwarf.attr_locations(unit, attr)? else {
+        return Ok(None);
+    };
+    while let Some(location) = locations.next()? {
+        if (location.range.begin..location.range.end).contains(&pc) {
+            return Ok(Some(location.data));
+        }
+    }
+    Ok(None)
+}
+
+fn evaluate<'a>(
+    expression: gimli::Expression<Reader<'a>>,
+    encoding: gimli::Encoding,
+    context: &EvaluationContext<'_>,
+) -> Result<Vec<gimli::Piece<Reader<'a>>>, DebuggerError> {
+    let unavailable = |what: &str| DebuggerError::ErrorMessage(format!("{what} is not available"));
+    let mut evaluation = expression.evaluation(encoding);
+    let mut result = evaluation.evaluate()?;
+    loop {
+        result = match result {
+            gimli::EvaluationResult::Complete => return Ok(evaluation.result()),
+            gimli::EvaluationResult::RequiresMemory { address, size, .. } => {
+                let bytes = (context.read_memory)(address, usize::from(size))?;
+                let mut word = [0u8; 8];
+                word[..bytes.len()].copy_from_slice(&bytes);
+                evaluation.resume_with_memory(gimli::Value::Generic(u64::from_le_bytes(word)))?
+            }
+            gimli::EvaluationResult::RequiresRegister { register, .. } => {
+                let value = context
+                    .registers
+                    .get(register)
+                    .ok_or_else(|| unavailable("Register"))?;
+                evaluation.resume_with_register(gimli::Value::Generic(value))?
+            }
+            gimli::EvaluationResult::RequiresFrameBase => evaluation.resume_with_frame_base(
+                context
+                    .frame_base
+                    .ok_or_else(|| unavailable("Frame base"))?,
+            )?,
+            gimli::EvaluationResult::RequiresCallFrameCfa => evaluation
+                .resume_with_call_frame_cfa(context.cfa.ok_or_else(|| unavailable("CFA"))?)?,
+            gimli::EvaluationResult::RequiresRelocatedAddress(address) => {
+                evaluation.resume_with_relocated_address(address + context.load_bias)?
+            }
+            other => {
+                return Err(DebuggerError::ErrorMessage(format!(
+                    "Unsupported DWARF expression requirement: {other:?}"
+                )));
+            }
+        };
+    }
+}
+
+fn frame_base(
+    expression: gimli::Expression<Reader<'_>>,
+    encoding: gimli::Encoding,
+    context: &EvaluationContext<'_>,
+) -> Option<u64> {
+    match evaluate(expression, encoding, context)
+        .ok()?
+        .first()?
+        .location
+    {
+        gimli::Location::Register { register } => context.registers.get(register),
+        gimli::Location::Address { address } => Some(address),
+        _ => None,
+    }
+}
+
+fn read_pieces(
+    pieces: &[gimli::Piece<Reader<'_>>],
+    size: usize,
+    context: &EvaluationContext<'_>,
+) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(size);
+    for piece in pieces {
+        let piece_size = piece
+            .size_in_bits
+            .and_then(|bits| usize::try_from(bits / 8).ok())
+            .unwrap_or(size);
+        match piece.location {
+            gimli::Location::Register { register } => {
+                let value = context.registers.get(register)?.to_le_bytes();
+                bytes.extend_from_slice(&value[..piece_size.min(value.len())]);
+            }
+            gimli::Location::Address { address } => {
+                bytes.extend((context.read_memory)(address, piece_size).ok()?);
+            }
+            gimli::Location::Value { value } => {
+                let value = value.to_u64(!0).ok()?.to_le_bytes();
+                bytes.extend_from_slice(&value[..piece_size.min(value.len())]);
+            }
+            gimli::Location::Bytes { value } => bytes.extend_from_slice(value.slice()),
+            _ => return None,
+        }
+    }
+    Some(bytes)
+}
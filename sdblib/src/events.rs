@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+/// Why a process stopped, as reported in [`Event::Stopped`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCause {
+    /// A freshly launched program stopped at its entry point.
+    Entry,
+    /// A process stopped because we attached to it.
+    Attach,
+    /// A signal we are configured to stop for arrived.
+    Signal,
+    /// A caught system call was entered or left.
+    Syscall,
+    /// A breakpoint was hit.
+    Breakpoint,
+    /// A watchpoint saw its memory change.
+    Watchpoint,
+    /// The debugger interrupted the thread.
+    Interrupt,
+    /// The thread executed the instruction it was single stepped through.
+    Step,
+}
+
+/// Something that happened to an inferior, in a form meant for tools rather than people.
+///
+/// Serialized internally tagged by `event`, e.g. `{"event":"exited","pid":42,"code":0}`. Fields
+/// are only ever added, so consumers can rely on the existing ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ProcessAdded {
+        pid: i32,
+    },
+    Stopped {
+        pid: i32,
+        reason: StopCause,
+        /// Absent when the registers could not be read.
+        pc: Option<u64>,
+        signal: Option<String>,
+    },
+    Exited {
+        pid: i32,
+        code: i32,
+    },
+    Killed {
+        pid: i32,
+        signal: String,
+    },
+}
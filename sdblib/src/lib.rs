@@ -11,23 +11,438 @@ use std::os::unix::process::CommandExt;
 use thiserror::Error;
 use tracing::{Level, event, instrument};
 
+mod auxv;
+mod backtrace;
+mod breakpoints;
+mod debug_info;
+mod events;
+mod maps;
+mod memory;
+mod registers;
+mod signals;
+mod symbol_cache;
+mod symbols;
+mod syscalls;
+mod user_area;
+mod watchpoints;
+
+pub use auxv::{AuxvEntry, AuxvFormat, read_auxv};
+pub use backtrace::{Backtrace, Frame};
+pub use breakpoints::{Breakpoint, BreakpointLocation, Rearmed};
+pub use debug_info::{GlobalVariable, SourceLocation, Value, Variable};
+pub use events::{Event, StopCause};
+pub use maps::{MemoryRegion, SharedObject};
+pub use memory::quote_bytes;
+pub use nix::sys::signal::Signal;
+pub use nix::unistd::Pid;
+pub use registers::{
+    Register, RegisterKind, canonical_register_name, decode_rflags, is_register_name,
+};
+pub use signals::{SignalDisposition, SignalReport, describe_si_code, fault_address, is_fault};
+pub use symbols::{FunctionSymbol, SymbolLocation, demangle};
+pub use syscalls::{
+    SyscallStop, decode_syscall_arguments, decode_syscall_return, syscall_name, syscall_number,
+};
+pub use user_area::{debug_register_offset, fs_base_offset, gs_base_offset};
+pub use watchpoints::{WatchBackend, WatchKind, Watchpoint};
+
 #[derive(Error, Debug)]
 pub enum DebuggerError {
     #[error("IO Error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Nix Error: {0}")]
     NixError(#[from] nix::errno::Errno),
+    #[error("DWARF Error: {0}")]
+    DwarfError(gimli::Error),
     #[error("Error: {0}")]
     ErrorMessage(String),
     #[error("Unknown Error")]
     Unknown,
 }
 
+/// What is needed to (re)launch a program under the debugger.
+#[derive(Debug, Clone)]
+struct LaunchSpec {
+    program: String,
+    args: Vec<std::ffi::OsString>,
+}
+
+/// What [`Debugger::load_program`] found in an executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramFile {
+    /// Where the program starts, as a file address: position-independent executables are
+    /// moved when launched.
+    pub entry_point: u64,
+    /// Whether it has DWARF, rather than only symbol tables.
+    pub has_debug_info: bool,
+}
+
+/// Why the inferior handed control back to the debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The process exited with the given status code.
+    Exited(nix::unistd::Pid, i32),
+    /// The process was terminated by a signal.
+    Killed(nix::unistd::Pid, Signal),
+    /// The thread stopped because it received a signal.
+    Signaled(nix::unistd::Pid, Signal),
+    /// The thread stopped entering or leaving a caught system call.
+    Syscall(nix::unistd::Pid, SyscallStop),
+    /// The thread hit the breakpoint with the given id, and sits right before executing it.
+    Breakpoint(nix::unistd::Pid, usize),
+    /// The thread changed the memory of the watchpoint with the given id.
+    Watchpoint(nix::unistd::Pid, usize),
+    /// The thread stopped because the debugger interrupted it.
+    Interrupted(nix::unistd::Pid),
+    /// The thread executed the one instruction it was stepped through, and nothing else
+    /// stopped it.
+    Stepped(nix::unistd::Pid),
+}
+
+/// Refuse the processes that attaching to would hang or make no sense.
+fn check_attachable(pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+    if pid.as_raw() <= 1 {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "Refusing to attach to process {pid}: it is the system's init process or invalid."
+        )));
+    }
+    // Any thread of ours: it would wait for itself to stop.
+    if std::path::Path::new(&format!("/proc/self/task/{pid}")).exists() {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "Refusing to attach to process {pid}: it is the debugger itself."
+        )));
+    }
+    // Kernel threads run no executable; a process that does not exist is left for `attach` to
+    // report.
+    if std::path::Path::new(&format!("/proc/{pid}")).exists()
+        && let Err(e) = std::fs::read_link(format!("/proc/{pid}/exe"))
+        && e.kind() == std::io::ErrorKind::NotFound
+    {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "Refusing to attach to process {pid}: it is a kernel thread."
+        )));
+    }
+    Ok(())
+}
+
+/// What a thread is doing, as the state letter of `/proc/<pid>/stat` says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    Sleeping,
+    /// Waiting for I/O, uninterruptibly.
+    DiskSleep,
+    /// In a job-control stop, as `SIGSTOP` or Ctrl-Z leave it.
+    Stopped,
+    /// Stopped by its tracer, so some debugger has it already.
+    TracingStop,
+    Zombie,
+    Dead,
+    Idle,
+    Other(char),
+}
+
+impl std::fmt::Display for ProcessState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Sleeping => write!(f, "sleeping"),
+            Self::DiskSleep => write!(f, "waiting for I/O"),
+            Self::Stopped => write!(f, "stopped"),
+            Self::TracingStop => write!(f, "stopped by a tracer"),
+            Self::Zombie => write!(f, "a zombie"),
+            Self::Dead => write!(f, "dead"),
+            Self::Idle => write!(f, "idle"),
+            Self::Other(state) => write!(f, "in state {state}"),
+        }
+    }
+}
+
+/// The state of process `pid`, that is of its main thread.
+/// # Errors
+///
+/// Will return `Err` if there is no such process.
+pub fn process_state(pid: nix::unistd::Pid) -> Result<ProcessState, DebuggerError> {
+    thread_state(pid, pid)
+}
+
+fn thread_state(
+    pid: nix::unistd::Pid,
+    tid: nix::unistd::Pid,
+) -> Result<ProcessState, DebuggerError> {
+    let fields = stat_fields(&format!("/proc/{pid}/task/{tid}/stat"))?;
+    let state = fields
+        .first()
+        .and_then(|state| state.chars().next())
+        .ok_or_else(|| DebuggerError::ErrorMessage(format!("Malformed stat of thread {tid}")))?;
+    Ok(match state {
+        'R' => ProcessState::Running,
+        'S' => ProcessState::Sleeping,
+        'D' => ProcessState::DiskSleep,
+        'T' => ProcessState::Stopped,
+        't' => ProcessState::TracingStop,
+        'Z' => ProcessState::Zombie,
+        'X' => ProcessState::Dead,
+        'I' => ProcessState::Idle,
+        other => ProcessState::Other(other),
+    })
+}
+
+/// The fields of a `stat` file in `/proc` from the state on, the third one.
+fn stat_fields(path: &str) -> Result<Vec<String>, DebuggerError> {
+    let stat = std::fs::read_to_string(path)?;
+    // The command name may contain spaces or parentheses, the state follows the last ')'.
+    let end = stat
+        .rfind(')')
+        .ok_or_else(|| DebuggerError::ErrorMessage(format!("Malformed {path}")))?;
+    Ok(stat[end + 1..]
+        .split_whitespace()
+        .map(str::to_string)
+        .collect())
+}
+
+/// The CPU time a process used, all its threads together, and the time since it started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessTimes {
+    pub user: std::time::Duration,
+    pub system: std::time::Duration,
+    pub elapsed: std::time::Duration,
+}
+
+impl ProcessTimes {
+    #[must_use]
+    pub fn cpu(&self) -> std::time::Duration {
+        self.user + self.system
+    }
+}
+
+/// A thread of a process being debugged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadInfo {
+    pub tid: nix::unistd::Pid,
+    /// What it was named with `pthread_setname_np`, or else the name of its process.
+    pub name: String,
+    /// Where it is, `None` if its registers cannot be read, as while it runs.
+    pub pc: Option<u64>,
+}
+
+/// The name of thread `tid` of process `pid` from `/proc`. Threads start with the name of
+/// their process, which is what one not renamed, or gone meanwhile, is called.
+/// # Errors
+///
+/// Will return `Err` if there is no such process.
+pub fn thread_name(pid: nix::unistd::Pid, tid: nix::unistd::Pid) -> Result<String, DebuggerError> {
+    let read = |path: String| {
+        std::fs::read_to_string(path).map(|name| name.trim_end_matches('\n').to_string())
+    };
+    match read(format!("/proc/{pid}/task/{tid}/comm")) {
+        Ok(name) if !name.is_empty() => Ok(name),
+        _ => Ok(read(format!("/proc/{pid}/comm"))?),
+    }
+}
+
+/// Read the CPU time process `pid` used and how long ago it started from `/proc`. Both are
+/// counted in clock ticks, usually hundredths of a second.
+/// # Errors
+///
+/// Will return `Err` if there is no such process.
+pub fn process_times(pid: nix::unistd::Pid) -> Result<ProcessTimes, DebuggerError> {
+    let fields = stat_fields(&format!("/proc/{pid}/stat"))?;
+    // utime, stime and starttime are fields 14, 15 and 22; the first we have is the third.
+    let ticks = |field: usize| {
+        fields
+            .get(field - 3)
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| DebuggerError::ErrorMessage(format!("Malformed stat of process {pid}")))
+    };
+    let ticks_per_second = u64::try_from(unsafe { nix::libc::sysconf(nix::libc::_SC_CLK_TCK) })
+        .ok()
+        .filter(|&ticks| ticks > 0)
+        .unwrap_or(100);
+    let duration = |ticks: u64| std::time::Duration::from_millis(ticks * 1000 / ticks_per_second);
+    let uptime = std::fs::read_to_string("/proc/uptime")?
+        .split_whitespace()
+        .next()
+        .and_then(|seconds| seconds.parse::<f64>().ok())
+        .ok_or_else(|| DebuggerError::ErrorMessage("Malformed /proc/uptime".to_string()))?;
+    Ok(ProcessTimes {
+        user: duration(ticks(14)?),
+        system: duration(ticks(15)?),
+        elapsed: std::time::Duration::from_secs_f64(uptime).saturating_sub(duration(ticks(22)?)),
+    })
+}
+
+/// The encoding of the x86-64 `syscall` instruction.
+const SYSCALL_INSTRUCTION: [u8; 2] = [0x0f, 0x05];
+
+/// The event of the stops `PTRACE_INTERRUPT` causes, group-stops of seized threads, and the
+/// first stop of the threads they clone.
+const PTRACE_EVENT_STOP: i32 = nix::sys::ptrace::Event::PTRACE_EVENT_STOP as i32;
+
+/// Attach thread `tid` with `PTRACE_SEIZE`, following the threads it clones from now on, and
+/// interrupt it for the stop attaching is expected to cause. A thread in a job-control stop
+/// reports that stop instead.
+fn seize(tid: nix::unistd::Pid) -> nix::Result<()> {
+    nix::sys::ptrace::seize(
+        tid,
+        nix::sys::ptrace::Options::PTRACE_O_TRACECLONE
+            | nix::sys::ptrace::Options::PTRACE_O_TRACESYSGOOD,
+    )?;
+    nix::sys::ptrace::interrupt(tid)
+}
+
+/// How the last process to end did, as a shell would see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Exited(i32),
+    Killed(Signal),
+}
+
+impl ExitStatus {
+    /// The status a shell reports: the exit code, or 128 plus the signal that killed it.
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::Exited(code) => code,
+            Self::Killed(signal) => 128 + signal as i32,
+        }
+    }
+}
+
+/// What [`Debugger::teardown`] does to the programs the debugger launched. Attached processes
+/// are always detached, as they were running before we came.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExitPolicy {
+    #[default]
+    Kill,
+    /// Let them run on untraced. Their output still goes to the debugger, so one that writes
+    /// after it is gone gets `SIGPIPE`.
+    Detach,
+    /// The front end asks which; they are killed if it did not.
+    Ask,
+}
+
+/// How many instructions [`Debugger::source_step`] executes looking for another line before
+/// giving up, as code without much line information could keep it going forever.
+pub const MAX_SOURCE_STEP_INSTRUCTIONS: usize = 100_000;
+
+/// Whether the instruction that took a thread from `before` to `after` was a call: one that
+/// pushed the address of the instruction following it.
+fn made_call(
+    tid: nix::unistd::Pid,
+    before: &nix::libc::user_regs_struct,
+    after: &nix::libc::user_regs_struct,
+) -> bool {
+    // x86 instructions are at most 15 bytes long.
+    after.rsp == before.rsp.wrapping_sub(8)
+        && memory::read_word(tid, after.rsp)
+            .is_ok_and(|pushed| pushed > before.rip && pushed <= before.rip + 15)
+}
+
+/// The length of the instruction `code` starts with if it is a `rep` or `repne` string
+/// instruction, which executes once per count and leaves the program counter on itself
+/// meanwhile. String instructions take no operands, so the prefixes and opcode are all of it.
+fn rep_string_length(code: &[u8]) -> Option<u64> {
+    let mut repeated = false;
+    for (index, byte) in code.iter().enumerate() {
+        match byte {
+            0xf2 | 0xf3 => repeated = true,
+            // Operand and address size, segment overrides, lock, and REX.
+            0x66 | 0x67 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 | 0xf0 | 0x40..=0x4f => {}
+            // ins, outs, movs, cmps, stos, lods, scas.
+            0x6c..=0x6f | 0xa4..=0xa7 | 0xaa..=0xaf if repeated => return Some(index as u64 + 1),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// How far [`Debugger::step_instructions`] or [`Debugger::source_step`] got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stepped {
+    /// How many instructions were executed.
+    pub steps: usize,
+    /// What ended the stepping early, if anything did.
+    pub reason: Option<StopReason>,
+}
+
+// gimli only implements `std::error::Error` with its `std` feature, so `#[from]` is unavailable.
+impl From<gimli::Error> for DebuggerError {
+    fn from(e: gimli::Error) -> Self {
+        Self::DwarfError(e)
+    }
+}
+
+fn no_debug_info() -> DebuggerError {
+    DebuggerError::ErrorMessage("No symbol table info available.".to_string())
+}
+
+fn invalid_register(name: &str) -> DebuggerError {
+    DebuggerError::ErrorMessage(format!("Invalid register `{name}'"))
+}
+
 #[derive(Debug)]
 pub struct Debugger {
     managed_processes: Vec<std::process::Child>,
 
+    launch_spec: Option<LaunchSpec>,
+    program_output: Option<std::process::ChildStdout>,
+
     attached_processes: Vec<nix::unistd::Pid>,
+
+    attached_threads: Vec<nix::unistd::Pid>,
+
+    signal_dispositions: std::collections::BTreeMap<Signal, SignalDisposition>,
+    pending_signals: std::collections::BTreeMap<nix::unistd::Pid, Signal>,
+    syscall_catch: syscalls::SyscallCatch,
+    /// Set while [`Debugger::step_syscall`] runs, to stop at the next syscall whatever it is.
+    stepping_syscall: bool,
+    /// Cloned threads whose initial stop has not been seen yet.
+    starting_threads: std::collections::BTreeSet<nix::unistd::Pid>,
+    /// Threads resumed or attached that have not reported a stop since: `ptrace` cannot touch
+    /// them until they do.
+    running_threads: std::collections::BTreeSet<nix::unistd::Pid>,
+    /// Threads attached with `PTRACE_SEIZE`, and the ones they clone: they are stopped with
+    /// `PTRACE_INTERRUPT` rather than a signal. Programs we launch trace themselves instead.
+    seized_threads: std::collections::BTreeSet<nix::unistd::Pid>,
+    signal_reports: Vec<SignalReport>,
+    /// Threads stopped by a signal whose disposition says not to pass it, dropped when they
+    /// are resumed.
+    dropped_signals: std::collections::BTreeMap<nix::unistd::Pid, Signal>,
+    resumed_signals: Vec<SignalReport>,
+
+    backtrace_limit: Option<usize>,
+    selected_frame: usize,
+    /// The thread the last stop the user heard about happened in.
+    last_stopped_thread: Option<nix::unistd::Pid>,
+
+    /// The `int3` patches, per process.
+    breakpoints: Vec<Breakpoint>,
+    /// The breakpoints the user set, by id and whether enabled, patched again into every
+    /// relaunched process. Those with no patch in the current process are pending, at a
+    /// function no object loaded so far defines.
+    breakpoint_locations: Vec<(usize, BreakpointLocation, bool)>,
+    /// Enabled pending breakpoints that were set since the last call of
+    /// [`Debugger::take_activated_breakpoints`].
+    activated_breakpoints: Vec<Breakpoint>,
+    /// Pending breakpoints deleted since the last call of
+    /// [`Debugger::take_failed_breakpoints`], as they could not be set where a library loaded
+    /// their function.
+    failed_breakpoints: Vec<(usize, BreakpointLocation, DebuggerError)>,
+    /// The shared objects mapped when pending breakpoints were last looked up.
+    loaded_objects: Vec<String>,
+    watchpoints: Vec<Watchpoint>,
+    next_breakpoint_id: usize,
+    print_demangle: bool,
+    /// The executables and libraries read so far, kept parsed.
+    symbol_cache: symbol_cache::SymbolCache,
+
+    /// `None` until [`Debugger::record_events`] is called, so nobody pays for an unread log.
+    events: Option<Vec<Event>>,
+    exit_status: Option<ExitStatus>,
+    exit_policy: ExitPolicy,
+    /// Whether programs are launched with address space layout randomization off.
+    disable_randomization: bool,
 }
 
 impl Default for Debugger {
@@ -38,10 +453,7 @@ impl Default for Debugger {
 
 impl Drop for Debugger {
     fn drop(&mut self) {
-        for child in &mut self.managed_processes {
-            let _ = child.kill();
-            let _ = child.wait();
-        }
+        self.teardown();
     }
 }
 
@@ -50,28 +462,147 @@ impl Debugger {
     pub const fn new() -> Self {
         Self {
             managed_processes: Vec::new(),
+            launch_spec: None,
+            program_output: None,
             attached_processes: Vec::new(),
+            attached_threads: Vec::new(),
+            signal_dispositions: std::collections::BTreeMap::new(),
+            pending_signals: std::collections::BTreeMap::new(),
+            starting_threads: std::collections::BTreeSet::new(),
+            seized_threads: std::collections::BTreeSet::new(),
+            running_threads: std::collections::BTreeSet::new(),
+            syscall_catch: syscalls::SyscallCatch::Nothing,
+            stepping_syscall: false,
+            signal_reports: Vec::new(),
+            dropped_signals: std::collections::BTreeMap::new(),
+            resumed_signals: Vec::new(),
+            backtrace_limit: None,
+            selected_frame: 0,
+            last_stopped_thread: None,
+            breakpoints: Vec::new(),
+            breakpoint_locations: Vec::new(),
+            activated_breakpoints: Vec::new(),
+            failed_breakpoints: Vec::new(),
+            loaded_objects: Vec::new(),
+            watchpoints: Vec::new(),
+            next_breakpoint_id: 1,
+            print_demangle: true,
+            symbol_cache: symbol_cache::SymbolCache::new(),
+            events: None,
+            exit_status: None,
+            exit_policy: ExitPolicy::Kill,
+            disable_randomization: true,
         }
     }
 
+    /// A debugger for just `program`, launched with `args` and left stopped at its entry point,
+    /// along with its output. See [`Self::add_program`].
+    /// # Errors
+    ///
+    /// Will return `Err` if the program fails to start, or we fail to attach.
+    pub fn launch<I, S>(
+        program: &str,
+        args: I,
+    ) -> Result<(Self, std::process::ChildStdout), DebuggerError>
+    where
+        I: IntoIterator<Item = S> + std::fmt::Debug,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let mut debugger = Self::new();
+        let stdout = debugger.add_program(program, args)?;
+        Ok((debugger, stdout))
+    }
+
+    /// A debugger for just process `pid`, attached and stopped. See [`Self::add_proc`].
+    /// # Errors
+    ///
+    /// Will return `Err` if the process cannot be attached to.
+    pub fn attach(pid: u64) -> Result<Self, DebuggerError> {
+        let mut debugger = Self::new();
+        debugger.add_proc(pid)?;
+        debugger.wait()?;
+        Ok(debugger)
+    }
+
     #[instrument]
     /// Attach a process into the debugger by its PID.
     /// # Errors
     ///
-    /// Will return `Err` if `PID` does not exist or is invalid.
+    /// Will return `Err` if `PID` does not exist or is invalid, or is one that cannot be
+    /// debugged: our own process, `init`, or a kernel thread.
     pub fn add_proc(&mut self, pid: u64) -> Result<(), DebuggerError> {
         let pid = nix::unistd::Pid::from_raw(
             pid.try_into()
                 .map_err(|e| DebuggerError::ErrorMessage(format!("PID conversion error: {e}")))?,
         );
         event!(Level::INFO, "Adding process with PID: {}", pid);
-        nix::sys::ptrace::attach(pid)?;
+        self.check_not_attached(pid)?;
+        check_attachable(pid)?;
+        seize(pid)?;
+        self.running_threads.insert(pid);
+        self.seized_threads.insert(pid);
         self.attached_processes.push(pid);
+        self.attached_threads.push(pid);
+        self.record(Event::ProcessAdded { pid: pid.as_raw() });
+        self.attach_sibling_threads(pid)?;
+        Ok(())
+    }
+
+    /// Refuse to track `pid` twice: attaching again would fail, and launching cannot give a PID
+    /// still tracked unless its old process was never reaped.
+    fn check_not_attached(&self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        if self.attached_processes.contains(&pid) || self.attached_threads.contains(&pid) {
+            return Err(DebuggerError::ErrorMessage(format!(
+                "Already attached to process {pid}."
+            )));
+        }
         Ok(())
     }
 
+    /// Attach every LWP of `pid` that is not traced yet.
+    ///
+    /// Threads may be spawned by not-yet-attached siblings while we scan, so the task list is
+    /// re-read until a full pass finds nothing new. Threads that exit between listing and
+    /// attaching are skipped.
+    fn attach_sibling_threads(&mut self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        loop {
+            let mut attached_any = false;
+            for entry in std::fs::read_dir(format!("/proc/{pid}/task"))? {
+                let Some(tid) = entry?
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.parse::<i32>().ok())
+                    .map(nix::unistd::Pid::from_raw)
+                else {
+                    continue;
+                };
+                if self.attached_threads.contains(&tid) {
+                    continue;
+                }
+                match seize(tid) {
+                    Ok(()) => {
+                        event!(Level::INFO, "Attached thread {} of process {}", tid, pid);
+                        self.running_threads.insert(tid);
+                        self.seized_threads.insert(tid);
+                        self.attached_threads.push(tid);
+                        attached_any = true;
+                    }
+                    Err(nix::errno::Errno::ESRCH) => {
+                        event!(Level::DEBUG, "Thread {} exited before we could attach", tid);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if !attached_any {
+                return Ok(());
+            }
+        }
+    }
+
     #[instrument]
     /// Run a program under the debugger with given arguments.
+    ///
+    /// The program is left stopped at its entry point, before any of its code has run.
     /// # Errors
     ///
     /// Will return `Err` if the program fails to start, or we fail to attach.
@@ -85,46 +616,2435 @@ impl Debugger {
         S: AsRef<std::ffi::OsStr>,
     {
         event!(Level::INFO, "Adding program: {}", program);
+        let args = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect::<Vec<_>>();
+        let disable_randomization = self.disable_randomization;
         let mut child = unsafe {
             std::process::Command::new(program)
-                .args(args)
-                .pre_exec(|| -> std::io::Result<()> {
+                .args(&args)
+                .pre_exec(move || -> std::io::Result<()> {
+                    if disable_randomization {
+                        let persona = nix::sys::personality::get()?;
+                        nix::sys::personality::set(
+                            persona | nix::sys::personality::Persona::ADDR_NO_RANDOMIZE,
+                        )?;
+                    }
                     nix::sys::ptrace::traceme()?;
                     Ok(())
                 })
                 .stdout(std::process::Stdio::piped())
-                .spawn()?
+                .spawn()
+                .map_err(|e| {
+                    DebuggerError::ErrorMessage(format!("failed to launch {program}: {e}"))
+                })?
         };
-        self.attached_processes
-            .push(nix::unistd::Pid::from_raw(child.id().cast_signed()));
+        let pid = nix::unistd::Pid::from_raw(child.id().cast_signed());
+        // Only a child that actually stopped under ptrace counts as attached; anything else is
+        // reaped here so a failed launch leaves no trace behind.
+        if let Err(e) = Self::wait_exec_stop(pid).and_then(|()| self.check_not_attached(pid)) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        self.attached_processes.push(pid);
+        self.attached_threads.push(pid);
         let stdout = child.stdout.take().ok_or_else(|| {
             DebuggerError::ErrorMessage("Failed to take stdout of the child process".to_string())
         });
         self.managed_processes.push(child);
+        self.launch_spec = Some(LaunchSpec {
+            program: program.to_string(),
+            args,
+        });
+        self.record(Event::ProcessAdded { pid: pid.as_raw() });
+        self.record_stop(pid, StopCause::Entry, None);
         stdout
     }
 
     #[instrument]
-    /// Waits for all attached processes to change state.
+    /// Kill the most recently launched program, if it is still around, and launch it again
+    /// with the same arguments. The new process is left stopped at its entry point, and its
+    /// output can be collected with [`Self::take_program_output`].
+    ///
+    /// Breakpoints are set again in the new process, looking their functions up anew; those
+    /// at functions no object loaded yet defines stay pending, and those that cannot be set
+    /// are deleted and reported back.
     /// # Errors
     ///
-    /// Will return `Err` if the program no longer exists.
-    pub fn wait(&self) -> Result<(), DebuggerError> {
-        for pid in &self.attached_processes {
-            nix::sys::wait::waitpid(*pid, None)?;
+    /// Will return `Err` if no program was launched before, or it fails to start again.
+    pub fn relaunch(&mut self) -> Result<Rearmed, DebuggerError> {
+        let spec = self.launch_spec.clone().ok_or_else(|| {
+            DebuggerError::ErrorMessage("No program has been launched".to_string())
+        })?;
+        self.selected_frame = 0;
+        if let Some(mut child) = self.managed_processes.pop() {
+            let pid = nix::unistd::Pid::from_raw(child.id().cast_signed());
+            let threads = std::fs::read_dir(format!("/proc/{pid}/task"))
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+                        .map(nix::unistd::Pid::from_raw)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let _ = child.kill();
+            let _ = child.wait();
+            for tid in threads {
+                // Traced threads linger as zombies until we reap them ourselves.
+                if tid != pid {
+                    let _ = self.wait_thread(Some(tid));
+                }
+                self.forget(tid);
+            }
+            self.forget(pid);
         }
-        Ok(())
+        self.program_output = Some(self.add_program(&spec.program, spec.args)?);
+        // The patches died with the old process, but the breakpoints the user set did not.
+        let mut rearmed = Rearmed::default();
+        for (id, location, enabled) in std::mem::take(&mut self.breakpoint_locations) {
+            match self.insert_breakpoint(id, location.clone()) {
+                Ok(true) => {
+                    rearmed.count += 1;
+                    self.breakpoint_locations.push((id, location, true));
+                    if !enabled {
+                        self.set_breakpoints_enabled(Some(id), false)?;
+                    }
+                }
+                // The libraries are not loaded yet at the entry point.
+                Ok(false) => self.breakpoint_locations.push((id, location, enabled)),
+                Err(e) => rearmed.failures.push((id, location, e)),
+            }
+        }
+        self.watch_for_libraries();
+        Ok(rearmed)
     }
 
     #[instrument]
-    /// Continues the execution of all attached processes.
+    /// Read the executable at `program` and make it the one [`Self::relaunch`] launches,
+    /// keeping the arguments already set. Nothing is launched: until it is, breakpoints are
+    /// checked against its symbols and set once it starts.
     /// # Errors
     ///
-    /// Will return `Err` if the program was already running or has exited.
-    pub fn continue_execution(&self) -> Result<(), DebuggerError> {
-        for pid in &self.attached_processes {
-            nix::sys::ptrace::cont(*pid, None)?;
+    /// Will return `Err` if the file cannot be read or is not a 64-bit ELF file.
+    pub fn load_program(&mut self, program: &str) -> Result<ProgramFile, DebuggerError> {
+        if self
+            .launched_program()
+            .is_some_and(|launched| launched != program)
+        {
+            // The files of the old target stay mapped otherwise, even once deleted.
+            self.symbol_cache.clear();
+        }
+        let object = self.symbol_cache.object(std::path::Path::new(program))?;
+        let args = self
+            .launch_spec
+            .take()
+            .map(|spec| spec.args)
+            .unwrap_or_default();
+        self.launch_spec = Some(LaunchSpec {
+            program: program.to_string(),
+            args,
+        });
+        Ok(ProgramFile {
+            entry_point: object.info.entry_point(),
+            has_debug_info: object.info.has_dwarf(),
+        })
+    }
+
+    /// Take the output of a program launched by [`Self::relaunch`] since the last call.
+    pub const fn take_program_output(&mut self) -> Option<std::process::ChildStdout> {
+        self.program_output.take()
+    }
+
+    /// The program [`Self::relaunch`] launches again, `None` if no program was launched.
+    #[must_use]
+    pub fn launched_program(&self) -> Option<&str> {
+        self.launch_spec.as_ref().map(|spec| spec.program.as_str())
+    }
+
+    /// The architecture the current process' executable was built for, like `x86_64`.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its executable cannot be read.
+    pub fn architecture(&self) -> Result<String, DebuggerError> {
+        let pid = self.current_process()?;
+        let executable = std::fs::read_link(format!("/proc/{pid}/exe"))?;
+        Ok(self.symbol_cache.object(&executable)?.info.architecture())
+    }
+
+    /// The arguments the next [`Self::relaunch`] passes to the program, `None` if no program
+    /// was launched.
+    #[must_use]
+    pub fn launch_args(&self) -> Option<&[std::ffi::OsString]> {
+        self.launch_spec.as_ref().map(|spec| spec.args.as_slice())
+    }
+
+    /// Replace the arguments the next [`Self::relaunch`] passes to the program. The running
+    /// process is not affected.
+    /// # Errors
+    ///
+    /// Will return `Err` if no program was launched before.
+    pub fn set_launch_args<I, S>(&mut self, args: I) -> Result<(), DebuggerError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let spec = self.launch_spec.as_mut().ok_or_else(|| {
+            DebuggerError::ErrorMessage("No program has been launched".to_string())
+        })?;
+        spec.args = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        Ok(())
+    }
+
+    /// Consume the `SIGTRAP` a `PTRACE_TRACEME` child raises when it `execve`s, and configure
+    /// its ptrace options while it sits at the entry point.
+    fn wait_exec_stop(pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        match nix::sys::wait::waitpid(pid, None)? {
+            nix::sys::wait::WaitStatus::Stopped(_, nix::sys::signal::Signal::SIGTRAP) => {}
+            status => {
+                return Err(DebuggerError::ErrorMessage(format!(
+                    "Unexpected initial stop of process {pid}: {status:?}"
+                )));
+            }
+        }
+        nix::sys::ptrace::setoptions(
+            pid,
+            nix::sys::ptrace::Options::PTRACE_O_EXITKILL
+                | nix::sys::ptrace::Options::PTRACE_O_TRACECLONE
+                | nix::sys::ptrace::Options::PTRACE_O_TRACESYSGOOD,
+        )?;
+        event!(Level::INFO, "Process {} stopped at entry point", pid);
+        Ok(())
+    }
+
+    /// The runtime address of function `name`, found in DWARF if there is any, in the ELF
+    /// symbol tables otherwise. The executable is searched before the shared libraries.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or no object defines the function.
+    pub fn resolve_function(&self, name: &str) -> Result<u64, DebuggerError> {
+        symbols::resolve_function(&self.symbol_cache, self.current_process()?, name)
+    }
+
+    /// Every function the most recently added process has loaded, sorted by runtime address.
+    /// Names are left as the symbols spell them; see [`demangle`].
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its memory map cannot be read.
+    pub fn functions(&self) -> Result<Vec<FunctionSymbol>, DebuggerError> {
+        symbols::functions(&self.symbol_cache, self.current_process()?)
+    }
+
+    /// The function containing `address` in the most recently added process, if known. Its
+    /// name is demangled unless [`Debugger::set_print_demangle`] turned that off.
+    #[must_use]
+    pub fn symbolize(&self, address: u64) -> Option<SymbolLocation> {
+        let mut location =
+            symbols::symbolize(&self.symbol_cache, self.current_process().ok()?, address)?;
+        if self.print_demangle {
+            location.function = demangle(&location.function);
+        }
+        Some(location)
+    }
+
+    /// Whether symbol names are shown demangled.
+    #[must_use]
+    pub const fn print_demangle(&self) -> bool {
+        self.print_demangle
+    }
+
+    pub const fn set_print_demangle(&mut self, demangle: bool) {
+        self.print_demangle = demangle;
+    }
+
+    #[must_use]
+    pub const fn exit_policy(&self) -> ExitPolicy {
+        self.exit_policy
+    }
+
+    pub const fn set_exit_policy(&mut self, policy: ExitPolicy) {
+        self.exit_policy = policy;
+    }
+
+    /// Whether programs are launched with their addresses the same on every run, as gdb does
+    /// by default. Attached processes keep the layout they started with.
+    #[must_use]
+    pub const fn disable_randomization(&self) -> bool {
+        self.disable_randomization
+    }
+
+    pub const fn set_disable_randomization(&mut self, disable: bool) {
+        self.disable_randomization = disable;
+    }
+
+    /// Whether a program the debugger launched is still alive, which
+    /// [`Self::teardown`] would kill unless the [`ExitPolicy`] says to detach it.
+    #[must_use]
+    pub const fn has_launched_process(&self) -> bool {
+        !self.managed_processes.is_empty()
+    }
+
+    /// Let go of every process: attached ones are detached, launched ones killed unless the
+    /// [`ExitPolicy`] says to detach them too. Errors are ignored, as there is nobody left to
+    /// tell; a process that cannot be detached is at worst killed with us.
+    pub fn teardown(&mut self) {
+        for pid in self.attached_processes.clone() {
+            let launched = self
+                .managed_processes
+                .iter()
+                .any(|child| child.id().cast_signed() == pid.as_raw());
+            if !launched || self.exit_policy == ExitPolicy::Detach {
+                let _ = self.detach(pid);
+            }
+        }
+        for mut child in std::mem::take(&mut self.managed_processes) {
+            let pid = nix::unistd::Pid::from_raw(child.id().cast_signed());
+            let threads = self.threads_of(pid);
+            let _ = child.kill();
+            // A process is only reaped once its other threads are, which is up to us while we
+            // trace them.
+            for tid in threads.into_iter().filter(|tid| *tid != pid) {
+                while let Ok(status) = self.wait_thread(Some(tid)) {
+                    if matches!(
+                        status,
+                        nix::sys::wait::WaitStatus::Exited(..)
+                            | nix::sys::wait::WaitStatus::Signaled(..)
+                    ) {
+                        break;
+                    }
+                }
+            }
+            let _ = child.wait();
+        }
+    }
+
+    /// Lift the breakpoints and watchpoints of process `pid` and stop tracing it, handing its
+    /// threads the signals they were about to get.
+    fn detach(&mut self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        for breakpoint in &self.breakpoints {
+            if breakpoint.pid == pid && breakpoint.enabled {
+                breakpoint.remove()?;
+            }
+        }
+        let threads = self.threads_of(pid);
+        for watchpoint in &self.watchpoints {
+            if let (true, WatchBackend::Hardware { slot }) =
+                (watchpoint.pid == pid, watchpoint.backend)
+            {
+                for &tid in &threads {
+                    watchpoints::disarm(tid, slot)?;
+                }
+            }
+        }
+        for tid in threads {
+            let signal = self.pending_signals.get(&tid).copied();
+            if nix::sys::ptrace::detach(tid, signal) == Err(nix::errno::Errno::ESRCH) {
+                // Just attached, it has not reached the stop attaching causes yet.
+                self.wait_thread(Some(tid))?;
+                nix::sys::ptrace::detach(tid, signal)?;
+            }
+            self.forget(tid);
+        }
+        // Forgetting it drops the `Child` too, so a detached program is not killed after all.
+        self.forget(pid);
+        Ok(())
+    }
+
+    /// Set a breakpoint at `address` in the most recently added process, and return its id.
+    /// With no process, it is set in the program [`Self::relaunch`] launches, once it does.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process nor program to launch, or its code cannot be
+    /// patched there.
+    pub fn set_breakpoint(&mut self, address: u64) -> Result<usize, DebuggerError> {
+        self.set_breakpoint_at(BreakpointLocation::Address(address))
+    }
+
+    /// Set a breakpoint `offset` bytes into function `name` in the most recently added process,
+    /// and return its id. Unlike one set by address, it follows the function to wherever it is
+    /// loaded after a relaunch. With no process, it is set once [`Self::relaunch`] starts one.
+    ///
+    /// A function no object loaded so far defines leaves the breakpoint pending: it is looked
+    /// up again whenever the libraries change, and set once one defines it, see
+    /// [`Self::take_activated_breakpoints`].
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process nor program to launch, or the function's code
+    /// cannot be patched.
+    pub fn set_function_breakpoint(
+        &mut self,
+        name: &str,
+        offset: u64,
+    ) -> Result<usize, DebuggerError> {
+        self.set_breakpoint_at(BreakpointLocation::Function {
+            name: name.to_string(),
+            offset,
+        })
+    }
+
+    fn set_breakpoint_at(&mut self, location: BreakpointLocation) -> Result<usize, DebuggerError> {
+        let id = self.next_breakpoint_id;
+        if self.has_process() {
+            self.insert_breakpoint(id, location.clone())?;
+        } else if self.launch_spec.is_none() {
+            return Err(self.not_running());
+        }
+        self.breakpoint_locations.push((id, location, true));
+        self.next_breakpoint_id += 1;
+        if self.has_process() {
+            self.watch_for_libraries();
+        }
+        Ok(id)
+    }
+
+    /// Patch breakpoint `id` into the most recently added process, resolving its location
+    /// there. Returns `false`, patching nothing, if it is at a function no object loaded so
+    /// far defines.
+    fn insert_breakpoint(
+        &mut self,
+        id: usize,
+        location: BreakpointLocation,
+    ) -> Result<bool, DebuggerError> {
+        let pid = self.stopped_process()?;
+        let address = match &location {
+            BreakpointLocation::Address(address) => *address,
+            BreakpointLocation::Function { name, offset } => match self.resolve_function(name) {
+                Ok(address) => address.checked_add(*offset).ok_or_else(|| {
+                    DebuggerError::ErrorMessage(format!(
+                        "Cannot insert breakpoint at {location}: the address is out of range."
+                    ))
+                })?,
+                Err(_) => return Ok(false),
+            },
+        };
+        let already_patched = self
+            .armed_breakpoint(address)
+            .filter(|breakpoint| breakpoint.pid == pid)
+            .map(Breakpoint::original_byte);
+        let breakpoint = Breakpoint::insert(id, location, pid, address, already_patched)?;
+        self.breakpoints.push(breakpoint);
+        Ok(true)
+    }
+
+    /// The breakpoints the user set that are not patched into the current process, with
+    /// whether they are enabled: pending ones, or all of them until a process is started.
+    #[must_use]
+    pub fn pending_breakpoints(&self) -> Vec<(usize, &BreakpointLocation, bool)> {
+        self.breakpoint_locations
+            .iter()
+            .filter(|(id, ..)| {
+                !self
+                    .breakpoints
+                    .iter()
+                    .any(|breakpoint| breakpoint.id == *id)
+            })
+            .map(|(id, location, enabled)| (*id, location, *enabled))
+            .collect()
+    }
+
+    /// Take the enabled pending breakpoints that were set since the last call, once a library
+    /// defining their function was loaded.
+    pub fn take_activated_breakpoints(&mut self) -> Vec<Breakpoint> {
+        std::mem::take(&mut self.activated_breakpoints)
+    }
+
+    /// Take the pending breakpoints deleted since the last call, by id and with why they could
+    /// not be set once a library defining their function was loaded.
+    pub fn take_failed_breakpoints(&mut self) -> Vec<(usize, BreakpointLocation, DebuggerError)> {
+        std::mem::take(&mut self.failed_breakpoints)
+    }
+
+    /// Break on [`breakpoints::LOADER_HOOK`] while there are pending breakpoints, to look them
+    /// up again as soon as a library is loaded. Without it, as in a static executable, they are
+    /// only looked up when the program stops.
+    fn watch_for_libraries(&mut self) {
+        if self
+            .breakpoints
+            .iter()
+            .any(|breakpoint| breakpoint.id == breakpoints::LOADER_BREAKPOINT_ID)
+            || self.pending_breakpoints().is_empty()
+        {
+            return;
+        }
+        if let Ok(pid) = self.current_process()
+            && let Ok(objects) = maps::shared_objects(pid)
+        {
+            self.loaded_objects = objects.into_iter().map(|object| object.path).collect();
+        }
+        let hook = BreakpointLocation::Function {
+            name: breakpoints::LOADER_HOOK.to_string(),
+            offset: 0,
+        };
+        match self.insert_breakpoint(breakpoints::LOADER_BREAKPOINT_ID, hook) {
+            Ok(true) => {}
+            Ok(false) => event!(
+                Level::DEBUG,
+                "No {}, pending breakpoints are looked up at stops",
+                breakpoints::LOADER_HOOK
+            ),
+            Err(e) => event!(
+                Level::WARN,
+                "Could not break on {}: {}",
+                breakpoints::LOADER_HOOK,
+                e
+            ),
+        }
+    }
+
+    /// Set the pending breakpoints the objects loaded now define, and stop watching for
+    /// libraries once none is left pending.
+    fn set_pending_breakpoints(&mut self) -> Result<(), DebuggerError> {
+        let pid = self.stopped_process()?;
+        self.loaded_objects = maps::shared_objects(pid)?
+            .into_iter()
+            .map(|object| object.path)
+            .collect();
+        let pending = self
+            .pending_breakpoints()
+            .into_iter()
+            .map(|(id, location, enabled)| (id, location.clone(), enabled))
+            .collect::<Vec<_>>();
+        for (id, location, enabled) in pending {
+            match self.insert_breakpoint(id, location.clone()) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    self.breakpoint_locations
+                        .retain(|(location_id, ..)| *location_id != id);
+                    self.failed_breakpoints.push((id, location, e));
+                    continue;
+                }
+            }
+            if !enabled {
+                // Set, but not worth announcing until it is enabled.
+                self.set_breakpoints_enabled(Some(id), false)?;
+            } else if let Some(breakpoint) = self
+                .breakpoints
+                .iter()
+                .find(|breakpoint| breakpoint.id == id)
+            {
+                self.activated_breakpoints.push(breakpoint.clone());
+            }
+        }
+        if self.pending_breakpoints().is_empty()
+            && let Some(index) = self
+                .breakpoints
+                .iter()
+                .position(|breakpoint| breakpoint.id == breakpoints::LOADER_BREAKPOINT_ID)
+        {
+            let hook = self.breakpoints.remove(index);
+            if !self.breakpoints.iter().any(|other| {
+                other.enabled && other.pid == hook.pid && other.address == hook.address
+            }) {
+                hook.remove()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Look the pending breakpoints up again if the program stopped with other shared objects
+    /// mapped than when they last were, as the loader is not always seen loading them.
+    fn check_pending_breakpoints(&mut self) {
+        if self.pending_breakpoints().is_empty() || !self.has_process() {
+            return;
+        }
+        let Ok(pid) = self.stopped_process() else {
+            return;
+        };
+        let changed = maps::shared_objects(pid).is_ok_and(|objects| {
+            !objects
+                .iter()
+                .map(|object| &object.path)
+                .eq(self.loaded_objects.iter())
+        });
+        if changed && let Err(e) = self.set_pending_breakpoints() {
+            event!(Level::WARN, "Could not set the pending breakpoints: {}", e);
+        }
+    }
+
+    /// The enabled breakpoint at `address`, which has its `int3` in the code.
+    fn armed_breakpoint(&self, address: u64) -> Option<&Breakpoint> {
+        // The user's breakpoint is the one to report where the loader hook is too.
+        self.breakpoints
+            .iter()
+            .filter(|breakpoint| breakpoint.enabled && breakpoint.address == address)
+            .min_by_key(|breakpoint| breakpoint.id == breakpoints::LOADER_BREAKPOINT_ID)
+    }
+
+    /// The breakpoint at `address` as a stop to report: any but the loader hook, which the
+    /// user never set.
+    fn reported_breakpoint(&self, address: u64) -> Option<usize> {
+        self.armed_breakpoint(address)
+            .map(|breakpoint| breakpoint.id)
+            .filter(|id| *id != breakpoints::LOADER_BREAKPOINT_ID)
+    }
+
+    #[must_use]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Make breakpoint `id`, or every breakpoint if `None`, stop the program again.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no such breakpoint, or the code cannot be patched.
+    pub fn enable_breakpoints(&mut self, id: Option<usize>) -> Result<(), DebuggerError> {
+        self.set_breakpoints_enabled(id, true)
+    }
+
+    /// Let the program run through breakpoint `id`, or every breakpoint if `None`, keeping it to
+    /// enable later.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no such breakpoint, or the code cannot be restored.
+    pub fn disable_breakpoints(&mut self, id: Option<usize>) -> Result<(), DebuggerError> {
+        self.set_breakpoints_enabled(id, false)
+    }
+
+    fn set_breakpoints_enabled(
+        &mut self,
+        id: Option<usize>,
+        enabled: bool,
+    ) -> Result<(), DebuggerError> {
+        if let Some(id) = id
+            && !self
+                .breakpoint_locations
+                .iter()
+                .any(|(location_id, ..)| *location_id == id)
+        {
+            return Err(DebuggerError::ErrorMessage(format!(
+                "No breakpoint number {id}."
+            )));
+        }
+        let selected = |breakpoint: &Breakpoint| {
+            breakpoint.enabled != enabled
+                && id.map_or_else(|| breakpoint.is_user_breakpoint(), |id| breakpoint.id == id)
+        };
+        for index in 0..self.breakpoints.len() {
+            let breakpoint = &self.breakpoints[index];
+            if !selected(breakpoint) {
+                continue;
+            }
+            // The code stays patched while another enabled breakpoint is at the same address.
+            let shared = self.breakpoints.iter().any(|other| {
+                other.enabled
+                    && other.id != breakpoint.id
+                    && other.pid == breakpoint.pid
+                    && other.address == breakpoint.address
+            });
+            if !shared {
+                if enabled {
+                    breakpoint.reinsert()?;
+                } else {
+                    breakpoint.remove()?;
+                }
+            }
+            self.breakpoints[index].enabled = enabled;
+        }
+        // Pending breakpoints too, which are set as they were left.
+        for (location_id, _, location_enabled) in &mut self.breakpoint_locations {
+            if id.is_none_or(|id| id == *location_id) {
+                *location_enabled = enabled;
+            }
         }
         Ok(())
     }
+
+    /// Watch `length` bytes at `address` in the most recently added process for `kind`
+    /// accesses, and return the watchpoint's id. `expression` is only kept for display.
+    ///
+    /// A debug register is used if one is free and can watch that much. Otherwise write
+    /// watchpoints fall back to [`Debugger::set_software_watchpoint`]; reads cannot be seen
+    /// by single stepping, so the others fail.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, the memory cannot be read, or a read or
+    /// access watchpoint cannot get a debug register.
+    pub fn set_watchpoint(
+        &mut self,
+        expression: &str,
+        address: u64,
+        length: usize,
+        kind: WatchKind,
+    ) -> Result<usize, DebuggerError> {
+        let pid = self.stopped_process()?;
+        let free_slot = (0..watchpoints::HARDWARE_SLOTS).find(|slot| {
+            !self.watchpoints.iter().any(|watchpoint| {
+                watchpoint.pid == pid
+                    && watchpoint.backend == WatchBackend::Hardware { slot: *slot }
+            })
+        });
+        let Some(slot) = free_slot.filter(|_| watchpoints::hardware_can_watch(address, length))
+        else {
+            if kind != WatchKind::Write {
+                return Err(DebuggerError::ErrorMessage(
+                    "Expression cannot be implemented with read/access watchpoint.".to_string(),
+                ));
+            }
+            return self.set_software_watchpoint(expression, address, length);
+        };
+        let watchpoint = Watchpoint::new(
+            self.next_breakpoint_id,
+            pid,
+            expression.to_string(),
+            address,
+            length,
+            kind,
+            WatchBackend::Hardware { slot },
+        )?;
+        for tid in self.threads_of(pid) {
+            watchpoints::arm(tid, slot, &watchpoint)?;
+        }
+        Ok(self.add_watchpoint(watchpoint))
+    }
+
+    /// Watch `length` bytes at `address` in the most recently added process by single
+    /// stepping it and comparing them after every instruction. This works for any length and
+    /// any number of watchpoints, but makes resuming very slow, and syscalls are not caught
+    /// while it is in effect.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or the memory cannot be read.
+    pub fn set_software_watchpoint(
+        &mut self,
+        expression: &str,
+        address: u64,
+        length: usize,
+    ) -> Result<usize, DebuggerError> {
+        let watchpoint = Watchpoint::new(
+            self.next_breakpoint_id,
+            self.stopped_process()?,
+            expression.to_string(),
+            address,
+            length,
+            WatchKind::Write,
+            WatchBackend::Software,
+        )?;
+        Ok(self.add_watchpoint(watchpoint))
+    }
+
+    fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.next_breakpoint_id += 1;
+        let id = watchpoint.id;
+        self.watchpoints.push(watchpoint);
+        id
+    }
+
+    #[must_use]
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Delete watchpoint `id`, freeing its debug register for the next one.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no such watchpoint, or its debug register cannot be
+    /// cleared.
+    pub fn delete_watchpoint(&mut self, id: usize) -> Result<(), DebuggerError> {
+        let index = self
+            .watchpoints
+            .iter()
+            .position(|watchpoint| watchpoint.id == id)
+            .ok_or_else(|| DebuggerError::ErrorMessage(format!("No watchpoint number {id}.")))?;
+        let watchpoint = self.watchpoints.remove(index);
+        if let WatchBackend::Hardware { slot } = watchpoint.backend {
+            for tid in self.threads_of(watchpoint.pid) {
+                watchpoints::disarm(tid, slot)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The traced threads that belong to process `pid`.
+    fn threads_of(&self, pid: nix::unistd::Pid) -> Vec<nix::unistd::Pid> {
+        self.attached_threads
+            .iter()
+            .copied()
+            .filter(|tid| std::path::Path::new(&format!("/proc/{pid}/task/{tid}")).exists())
+            .collect()
+    }
+
+    /// Whether any process is still being debugged.
+    #[must_use]
+    pub const fn has_process(&self) -> bool {
+        !self.attached_processes.is_empty()
+    }
+
+    /// The most recently added process, which is the one commands act on.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process.
+    pub fn current_process(&self) -> Result<nix::unistd::Pid, DebuggerError> {
+        let pid = self
+            .attached_processes
+            .last()
+            .copied()
+            .ok_or_else(|| self.not_running())?;
+        self.require_live(pid)?;
+        Ok(pid)
+    }
+
+    /// The thread of the current process that last stopped for a reason the user was told
+    /// about, such as a breakpoint; the process itself, its main thread, until another does.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process.
+    pub fn current_thread(&self) -> Result<nix::unistd::Pid, DebuggerError> {
+        let pid = self.current_process()?;
+        Ok(self
+            .last_stopped_thread
+            .filter(|tid| {
+                self.attached_threads.contains(tid)
+                    && std::path::Path::new(&format!("/proc/{pid}/task/{tid}")).exists()
+            })
+            .unwrap_or(pid))
+    }
+
+    /// The threads of the current process we trace, by thread id.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process.
+    pub fn threads(&self) -> Result<Vec<ThreadInfo>, DebuggerError> {
+        let pid = self.current_process()?;
+        let mut threads = self.threads_of(pid);
+        threads.sort_unstable();
+        threads
+            .into_iter()
+            .map(|tid| {
+                Ok(ThreadInfo {
+                    tid,
+                    name: thread_name(pid, tid)?,
+                    pc: nix::sys::ptrace::getregs(tid).ok().map(|regs| regs.rip),
+                })
+            })
+            .collect()
+    }
+
+    /// Read the program counter of the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or it is not stopped.
+    pub fn program_counter(&self) -> Result<u64, DebuggerError> {
+        Ok(nix::sys::ptrace::getregs(self.stopped_process()?)?.rip)
+    }
+
+    /// Read the general purpose registers of the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or it is not stopped.
+    pub fn registers(&self) -> Result<Vec<Register>, DebuggerError> {
+        let regs = nix::sys::ptrace::getregs(self.stopped_process()?)?;
+        Ok(registers::general_registers(&regs))
+    }
+
+    /// Read register `name` of the most recently added process, see
+    /// [`canonical_register_name`] for the names understood.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no such register, no process, or it is not stopped.
+    pub fn register(&self, name: &str) -> Result<Register, DebuggerError> {
+        let wanted = canonical_register_name(name);
+        self.registers()?
+            .into_iter()
+            .find(|register| register.name == wanted)
+            .ok_or_else(|| invalid_register(name))
+    }
+
+    /// Write register `name` of the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no such register, no process, or it is not stopped.
+    pub fn set_register(&mut self, name: &str, value: u64) -> Result<(), DebuggerError> {
+        let pid = self.stopped_process()?;
+        let mut regs = nix::sys::ptrace::getregs(pid)?;
+        *registers::register_mut(&mut regs, name).ok_or_else(|| invalid_register(name))? = value;
+        nix::sys::ptrace::setregs(pid, regs)?;
+        Ok(())
+    }
+
+    /// Read the word at `offset` in the user area of thread `pid`, see
+    /// [`debug_register_offset`] and friends for where things are.
+    /// # Errors
+    ///
+    /// Will return `Err` if `offset` is not a word of the user area, or the thread is not
+    /// stopped.
+    pub fn read_user(&self, pid: nix::unistd::Pid, offset: u64) -> Result<u64, DebuggerError> {
+        self.require_stopped(pid)?;
+        user_area::read_user(pid, offset)
+    }
+
+    /// Write the word at `offset` in the user area of thread `pid`. The kernel validates what
+    /// goes into the debug registers and fails with `EINVAL` on bad values.
+    /// # Errors
+    ///
+    /// Will return `Err` if `offset` is not a word of the user area, the thread is not
+    /// stopped, or the kernel rejects the value.
+    pub fn write_user(
+        &self,
+        pid: nix::unistd::Pid,
+        offset: u64,
+        value: u64,
+    ) -> Result<(), DebuggerError> {
+        self.require_stopped(pid)?;
+        user_area::write_user(pid, offset, value)
+    }
+
+    /// Read `len` bytes at `address` in the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or the memory is not mapped.
+    pub fn read_memory(&self, address: u64, len: usize) -> Result<Vec<u8>, DebuggerError> {
+        memory::read_memory(self.stopped_process()?, address, len)
+    }
+
+    /// How many reads of inferior memory could not be made in a single system call, and were
+    /// finished one word at a time, since the debugger library was loaded.
+    #[must_use]
+    pub fn word_by_word_reads(&self) -> usize {
+        memory::word_by_word_reads()
+    }
+
+    /// Read the NUL terminated string at `address` in the most recently added process, at
+    /// most `max_len` bytes of it.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or the memory is not mapped.
+    pub fn read_c_string(&self, address: u64, max_len: usize) -> Result<Vec<u8>, DebuggerError> {
+        memory::read_c_string(self.stopped_process()?, address, max_len)
+    }
+
+    /// The addresses in `range` of the memory of process `pid` where `pattern` starts, at
+    /// most `max_matches` of them, lowest first. Unreadable memory in the range is skipped.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not a live traced process.
+    pub fn search_memory(
+        &self,
+        pid: nix::unistd::Pid,
+        range: std::ops::Range<u64>,
+        pattern: &[u8],
+        max_matches: usize,
+    ) -> Result<Vec<u64>, DebuggerError> {
+        self.require_stopped(pid)?;
+        Ok(memory::search_memory(pid, range, pattern, max_matches))
+    }
+
+    /// The auxiliary vector of the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its auxiliary vector cannot be read.
+    pub fn auxv(&self) -> Result<Vec<AuxvEntry>, DebuggerError> {
+        read_auxv(self.current_process()?)
+    }
+
+    /// The memory regions of the most recently added process, as `/proc/<pid>/maps` lists them.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its memory map cannot be read.
+    pub fn memory_map(&self) -> Result<Vec<MemoryRegion>, DebuggerError> {
+        maps::read_memory_map(self.current_process()?)
+    }
+
+    /// List the shared objects mapped into the most recently added process.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its memory map cannot be read.
+    pub fn shared_libraries(&self) -> Result<Vec<SharedObject>, DebuggerError> {
+        maps::shared_objects(self.current_process()?)
+    }
+
+    /// Walk the call stack of the most recently added process, following frame pointers, and
+    /// say where each frame is in the symbols and the source.
+    ///
+    /// At most `max_frames` frames are returned, and never more than the backtrace limit.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its registers cannot be read.
+    pub fn backtrace(&self, max_frames: usize) -> Result<Backtrace, DebuggerError> {
+        let mut backtrace = self.walk_stack(max_frames)?;
+        for frame in &mut backtrace.frames {
+            let lookup = frame.lookup_pc();
+            frame.symbol = self.symbolize(lookup).map(|mut location| {
+                location.offset += frame.pc - lookup;
+                location
+            });
+            frame.source = self.source_location(lookup);
+        }
+        Ok(backtrace)
+    }
+
+    /// [`Self::backtrace`] without looking anything up, for when only the registers matter.
+    fn walk_stack(&self, max_frames: usize) -> Result<Backtrace, DebuggerError> {
+        let max_frames = self
+            .backtrace_limit
+            .map_or(max_frames, |limit| max_frames.min(limit));
+        backtrace::frame_pointer_backtrace(self.stopped_process()?, max_frames)
+    }
+
+    /// The maximum number of frames a backtrace walks, `None` meaning no limit.
+    #[must_use]
+    pub const fn backtrace_limit(&self) -> Option<usize> {
+        self.backtrace_limit
+    }
+
+    pub const fn set_backtrace_limit(&mut self, limit: Option<usize>) {
+        self.backtrace_limit = limit;
+    }
+
+    /// The index of the frame commands inspect, 0 being the innermost one.
+    #[must_use]
+    pub const fn selected_frame_index(&self) -> usize {
+        self.selected_frame
+    }
+
+    /// Select the frame at `index` for inspection, and return it.
+    ///
+    /// The selection goes back to the innermost frame whenever the process resumes.
+    /// # Errors
+    ///
+    /// Will return `Err` if the stack has no frame at `index`.
+    pub fn select_frame(&mut self, index: usize) -> Result<Frame, DebuggerError> {
+        let mut backtrace = self.backtrace(index.saturating_add(1))?;
+        let frame_count = backtrace.frames.len();
+        let frame = backtrace
+            .frames
+            .pop()
+            .filter(|frame| frame.index == index)
+            .ok_or_else(|| {
+                DebuggerError::ErrorMessage(format!(
+                    "No frame at level {index}, the stack has {frame_count} frames"
+                ))
+            })?;
+        self.selected_frame = index;
+        Ok(frame)
+    }
+
+    /// Pop the innermost frame without running the rest of its function, as if it returned
+    /// `value` in rax, or whatever rax holds: the stack and frame pointers go back to what its
+    /// caller had, and execution resumes at the return address. Nothing the function had
+    /// left to do, like releasing what it allocated, is done.
+    ///
+    /// The call frame information of the executable says where the caller's registers were
+    /// saved; elsewhere, and in executables without it, the frame pointer chain does.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, it is not stopped, or the innermost frame has
+    /// no caller. On success, returns the caller's frame, now the innermost one.
+    pub fn return_from_frame(&mut self, value: Option<u64>) -> Result<Frame, DebuggerError> {
+        let pid = self.stopped_process()?;
+        let mut regs = nix::sys::ptrace::getregs(pid)?;
+        let unwound = self
+            .executable_debug_info()
+            .ok()
+            .and_then(|(_, object, load_bias)| {
+                object.info.caller_frame(
+                    regs.rip.wrapping_sub(load_bias),
+                    &debug_info::FrameRegisters::from_user_regs(&regs),
+                    &|address, len| memory::read_memory(pid, address, len),
+                )
+            });
+        let caller = match unwound {
+            Some(caller) => caller,
+            None => self
+                .walk_stack(2)?
+                .frames
+                .into_iter()
+                .nth(1)
+                .ok_or_else(|| {
+                    DebuggerError::ErrorMessage("The innermost frame has no caller.".to_string())
+                })?,
+        };
+        if caller.pc == 0 {
+            return Err(DebuggerError::ErrorMessage(
+                "The innermost frame has no caller.".to_string(),
+            ));
+        }
+        regs.rip = caller.pc;
+        regs.rsp = caller.stack_pointer;
+        regs.rbp = caller.frame_pointer;
+        if let Some(value) = value {
+            regs.rax = value;
+        }
+        nix::sys::ptrace::setregs(pid, regs)?;
+        self.select_frame(0)
+    }
+
+    /// Evaluate the parameters of the function the selected frame is executing, using the
+    /// DWARF of the current process' executable.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or no debug information for where it is.
+    pub fn arguments(&self) -> Result<Vec<Variable>, DebuggerError> {
+        let (pid, object, load_bias) = self.executable_debug_info()?;
+        let (frame, registers) = self.selected_frame_registers(pid)?;
+        object
+            .info
+            .arguments(
+                frame.lookup_pc() - load_bias,
+                &registers,
+                load_bias,
+                &|address, len| memory::read_memory(pid, address, len),
+            )?
+            .ok_or_else(no_debug_info)
+    }
+
+    /// Store `value` in the variable `name` print would show: a parameter of the function the
+    /// selected frame is executing, or else a variable with static storage. Only integers
+    /// and pointers can be set; a value too wide for the variable keeps its low bytes.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, it is not stopped, there is no such
+    /// variable, or it has no place it can be written to: optimized out, a constant, or
+    /// split in pieces.
+    pub fn set_variable(&mut self, name: &str, value: u64) -> Result<(), DebuggerError> {
+        let (pid, object, load_bias) = self.executable_debug_info()?;
+        self.require_stopped(pid)?;
+        let (frame, registers) = self.selected_frame_registers(pid)?;
+        let read_memory = |address, len| memory::read_memory(pid, address, len);
+        let parameter = object.info.parameter_place(
+            frame.lookup_pc() - load_bias,
+            &registers,
+            load_bias,
+            &read_memory,
+            name,
+        )?;
+        let variable = match parameter {
+            Some(parameter) => parameter,
+            None => object.info.global_place(name, load_bias)?.ok_or_else(|| {
+                DebuggerError::ErrorMessage(format!("No symbol \"{name}\" in current context."))
+            })?,
+        };
+        let bytes = variable.encode(name, value)?;
+        match variable.place {
+            debug_info::Place::Memory(address) => memory::write_memory(pid, address, &bytes),
+            // Outer frames only know where their stack and frame pointers are.
+            debug_info::Place::Register(_) if frame.index != 0 => {
+                Err(DebuggerError::ErrorMessage(format!(
+                    "Cannot set \"{name}\": it lives in a register of an outer frame, which is not recovered."
+                )))
+            }
+            debug_info::Place::Register(number) => {
+                let mut regs = nix::sys::ptrace::getregs(pid)?;
+                let register = gimli::X86_64::register_name(gimli::Register(number))
+                    .and_then(|register| registers::register_mut(&mut regs, register))
+                    .ok_or_else(|| {
+                        DebuggerError::ErrorMessage(format!(
+                            "Cannot set \"{name}\": it lives in DWARF register {number}, which cannot be written."
+                        ))
+                    })?;
+                let mut word = register.to_le_bytes();
+                word[..bytes.len()].copy_from_slice(&bytes);
+                *register = u64::from_le_bytes(word);
+                nix::sys::ptrace::setregs(pid, regs)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// The selected frame of process `pid`, with the registers known in it: all of them in
+    /// the innermost frame, and only what the frame pointer chain recovers in the others.
+    fn selected_frame_registers(
+        &self,
+        pid: nix::unistd::Pid,
+    ) -> Result<(Frame, debug_info::FrameRegisters), DebuggerError> {
+        let frame = self
+            .walk_stack(self.selected_frame.saturating_add(1))?
+            .frames
+            .into_iter()
+            .nth(self.selected_frame)
+            .ok_or_else(|| DebuggerError::ErrorMessage("No frame selected.".to_string()))?;
+        let registers = if frame.index == 0 {
+            debug_info::FrameRegisters::from_user_regs(&nix::sys::ptrace::getregs(pid)?)
+        } else {
+            debug_info::FrameRegisters::from_frame(&frame)
+        };
+        Ok((frame, registers))
+    }
+
+    /// The source line the code at runtime address `address` of the current process'
+    /// executable belongs to, if its DWARF says.
+    #[must_use]
+    pub fn source_location(&self, address: u64) -> Option<SourceLocation> {
+        let (_, object, load_bias) = self.executable_debug_info().ok()?;
+        object
+            .line_table()
+            .ok()?
+            .location(address.wrapping_sub(load_bias))
+    }
+
+    /// The runtime address where the code of line `line` of `file` starts, in the current
+    /// process' executable.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, its executable has no line table, or no code
+    /// comes from that line or any after it in the file.
+    pub fn line_address(&self, file: &std::path::Path, line: u64) -> Result<u64, DebuggerError> {
+        let (_, object, load_bias) = self.executable_debug_info()?;
+        object
+            .line_table()?
+            .line_address(file, line)
+            .map(|address| address.wrapping_add(load_bias))
+            .ok_or_else(|| {
+                DebuggerError::ErrorMessage(format!(
+                    "No line {line} in file \"{}\".",
+                    file.display()
+                ))
+            })
+    }
+
+    /// The variables with static storage the DWARF of the current process' executable
+    /// describes, at runtime addresses.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or its executable has no debug information.
+    pub fn global_variables(&self) -> Result<Vec<GlobalVariable>, DebuggerError> {
+        let (_, object, load_bias) = self.executable_debug_info()?;
+        object.info.global_variables(load_bias)
+    }
+
+    /// Read the variable with static storage called `name`. No frame is needed, so this works
+    /// as soon as the process is stopped.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, no such variable, or it is thread-local.
+    pub fn global_value(&self, name: &str) -> Result<Variable, DebuggerError> {
+        let (pid, object, load_bias) = self.executable_debug_info()?;
+        self.require_stopped(pid)?;
+        object
+            .info
+            .global_value(name, load_bias, &|address, len| {
+                memory::read_memory(pid, address, len)
+            })?
+            .ok_or_else(|| {
+                DebuggerError::ErrorMessage(format!("No symbol \"{name}\" in current context."))
+            })
+    }
+
+    /// The DWARF of the current process' executable, and how far from its preferred address
+    /// it was loaded.
+    fn executable_debug_info(
+        &self,
+    ) -> Result<
+        (
+            nix::unistd::Pid,
+            std::sync::Arc<symbol_cache::ParsedObject>,
+            u64,
+        ),
+        DebuggerError,
+    > {
+        let pid = self.current_process()?;
+        let executable = std::fs::read_link(format!("/proc/{pid}/exe"))?;
+        let object = self.symbol_cache.object(&executable)?;
+        if !object.info.has_dwarf() {
+            return Err(no_debug_info());
+        }
+        let load_bias = maps::load_address(pid, &executable)? - object.info.first_load_address();
+        Ok((pid, object, load_bias))
+    }
+
+    /// How many times an executable or library was read for its symbols or debug information
+    /// since the debugger was created. Each is read once, and again only once it changes.
+    #[must_use]
+    pub fn symbol_file_loads(&self) -> usize {
+        self.symbol_cache.loads()
+    }
+
+    #[instrument]
+    /// Waits for all attached threads to change state.
+    /// # Errors
+    ///
+    /// Will return `Err` if the program no longer exists.
+    pub fn wait(&mut self) -> Result<(), DebuggerError> {
+        for tid in self.attached_threads.clone() {
+            let status = match self.wait_thread(Some(tid)) {
+                // Already reaped, there is nothing left to wait for.
+                Err(nix::errno::Errno::ECHILD) => {
+                    self.forget(tid);
+                    continue;
+                }
+                status => status?,
+            };
+            // This is how the stop caused by attaching gets consumed. A thread that was in a
+            // job-control stop reports that one instead, and stays there until resumed.
+            if !matches!(
+                status,
+                nix::sys::wait::WaitStatus::PtraceEvent(_, _, PTRACE_EVENT_STOP)
+            ) {
+                continue;
+            }
+            if self.attached_processes.contains(&tid) {
+                self.record_stop(tid, StopCause::Attach, None);
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument]
+    /// Resume all attached threads, of every process, and wait for the next stop worth
+    /// reporting: a signal whose disposition says to stop, or a process exiting.
+    ///
+    /// This is the one primitive everything that runs the inferior builds on, so embedders can
+    /// drive it in a loop without any front end:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), sdblib::DebuggerError> {
+    /// let (mut debugger, _stdout) = sdblib::Debugger::launch("/bin/true", std::iter::empty::<&str>())?;
+    /// while let sdblib::StopReason::Signaled(pid, signal) = debugger.continue_all()? {
+    ///     println!("{pid} stopped by {signal}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Signals that we do not stop for are delivered or dropped as their disposition says,
+    /// and the ones that should be printed are queued for [`Self::take_signal_reports`].
+    /// # Errors
+    ///
+    /// Will return `Err` if the program was already running or has exited.
+    pub fn continue_all(&mut self) -> Result<StopReason, DebuggerError> {
+        self.selected_frame = 0;
+        let reason = self.resume()?;
+        self.record_stop_reason(reason);
+        Ok(reason)
+    }
+
+    #[instrument]
+    /// Like [`Self::continue_all`], but resume only the threads of the current process, which
+    /// is all there is with a single one. Other processes stay stopped where they are.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or it is already running.
+    pub fn continue_current(&mut self) -> Result<StopReason, DebuggerError> {
+        self.selected_frame = 0;
+        let pid = self.current_process()?;
+        let threads = self.threads_of(pid);
+        let reason = self.resume_threads(threads)?;
+        self.record_stop_reason(reason);
+        Ok(reason)
+    }
+
+    /// [`Self::continue_all`], without telling the event log.
+    fn resume(&mut self) -> Result<StopReason, DebuggerError> {
+        self.resume_threads(self.attached_threads.clone())
+    }
+
+    /// Step `threads` off their breakpoints, resume them and wait for the next stop worth
+    /// reporting.
+    fn resume_threads(
+        &mut self,
+        threads: Vec<nix::unistd::Pid>,
+    ) -> Result<StopReason, DebuggerError> {
+        self.require_threads()?;
+        for &tid in &threads {
+            if let Some(reason) = self.step_over_breakpoint(tid, true)? {
+                return Ok(reason);
+            }
+        }
+        if self
+            .watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.backend == WatchBackend::Software)
+        {
+            self.step_until_watchpoint_changes()
+        } else {
+            for tid in threads {
+                let signal = self.take_pending_signal(tid);
+                self.resume_thread(tid, signal)?;
+            }
+            self.wait_for_stop()
+        }
+    }
+
+    #[instrument]
+    /// Resume only thread `pid` and wait until it enters or leaves a system call, whether it is
+    /// caught or not. Other threads stay stopped, unless something else stops first.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not a stopped thread we trace.
+    pub fn step_syscall(&mut self, pid: nix::unistd::Pid) -> Result<StopReason, DebuggerError> {
+        self.selected_frame = 0;
+        self.require_stopped(pid)?;
+        // Set first, so a breakpoint on a `syscall` instruction is stepped off into the call.
+        self.stepping_syscall = true;
+        let reason = match self.step_over_breakpoint(pid, true) {
+            Ok(Some(reason)) => Ok(reason),
+            Ok(None) => {
+                let signal = self.take_pending_signal(pid);
+                self.resume_thread(pid, signal)
+                    .and_then(|()| self.wait_for_stop())
+            }
+            Err(e) => Err(e),
+        };
+        self.stepping_syscall = false;
+        let reason = reason?;
+        self.record_stop_reason(reason);
+        Ok(reason)
+    }
+
+    #[instrument]
+    /// Execute one instruction of thread `pid`, while the other threads stay stopped, and tell
+    /// how that went: [`StopReason::Stepped`] unless it hit a watchpoint, landed on a
+    /// breakpoint, or anything else that would have stopped [`Self::continue_all`] happened.
+    /// The breakpoint it sits on is stepped off rather than hit again.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not a stopped thread we trace.
+    pub fn single_step(&mut self, pid: nix::unistd::Pid) -> Result<StopReason, DebuggerError> {
+        self.selected_frame = 0;
+        self.require_stopped(pid)?;
+        let reason = self
+            .step_instruction(pid)?
+            .unwrap_or(StopReason::Stepped(pid));
+        self.record_stop_reason(reason);
+        Ok(reason)
+    }
+
+    #[instrument]
+    /// Execute `count` instructions of the most recently added process, one at a time, while
+    /// its other threads stay stopped. Stepping ends early on anything that would have stopped
+    /// [`Self::continue_all`], or on landing on a breakpoint.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or it is not stopped.
+    pub fn step_instructions(&mut self, count: usize) -> Result<Stepped, DebuggerError> {
+        self.selected_frame = 0;
+        self.require_threads()?;
+        let tid = self.stopped_process()?;
+        let mut stepped = Stepped {
+            steps: 0,
+            reason: None,
+        };
+        while stepped.steps < count {
+            stepped.reason = self.step_instruction(tid)?;
+            if let Some(reason) = stepped.reason {
+                self.record_stop_reason(reason);
+                break;
+            }
+            stepped.steps += 1;
+        }
+        Ok(stepped)
+    }
+
+    #[instrument]
+    /// Execute thread `pid` until it reaches the start of another source line, according to
+    /// the DWARF of the executable, and return how many instructions that took.
+    ///
+    /// Calls into functions with line information stop at their first line past the
+    /// prologue; calls into anything else, like the PLT or a library without debug
+    /// information, run until they return. Stepping ends early on anything that would have
+    /// stopped [`Self::continue_all`], or on landing on a breakpoint.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not stopped, there is no line information for where it
+    /// is, or no other line is reached within [`MAX_SOURCE_STEP_INSTRUCTIONS`] instructions.
+    pub fn source_step(&mut self, pid: nix::unistd::Pid) -> Result<Stepped, DebuggerError> {
+        self.selected_frame = 0;
+        self.require_stopped(pid)?;
+        let (_, object, load_bias) = self.executable_debug_info()?;
+        let lines = object.line_table()?;
+        let mut regs = nix::sys::ptrace::getregs(pid)?;
+        let mut line = lines
+            .location(regs.rip.wrapping_sub(load_bias))
+            .ok_or_else(|| {
+                DebuggerError::ErrorMessage(
+                    "Cannot step: no line number information for the current location.".to_string(),
+                )
+            })?;
+        let mut stepped = Stepped {
+            steps: 0,
+            reason: None,
+        };
+        while stepped.steps < MAX_SOURCE_STEP_INSTRUCTIONS {
+            let before = regs;
+            stepped.reason = self.step_instruction(pid)?;
+            if let Some(reason) = stepped.reason {
+                self.record_stop_reason(reason);
+                return Ok(stepped);
+            }
+            stepped.steps += 1;
+            regs = nix::sys::ptrace::getregs(pid)?;
+            let mut called = made_call(pid, &before, &regs);
+            if called && lines.location(regs.rip.wrapping_sub(load_bias)).is_none() {
+                stepped.reason = self.run_until_return(pid, regs.rsp)?;
+                if let Some(reason) = stepped.reason {
+                    self.record_stop_reason(reason);
+                    return Ok(stepped);
+                }
+                regs = nix::sys::ptrace::getregs(pid)?;
+                called = false;
+            }
+            let pc = regs.rip.wrapping_sub(load_bias);
+            match lines.location(pc) {
+                Some(location) if called => {
+                    // The prologue belongs to the opening line, so stop at the one after.
+                    line = location;
+                }
+                Some(location) => {
+                    if location != line && lines.is_line_start(pc) {
+                        return Ok(stepped);
+                    }
+                }
+                // Returned into code without line information.
+                None => return Ok(stepped),
+            }
+        }
+        Err(DebuggerError::ErrorMessage(format!(
+            "Stepped {MAX_SOURCE_STEP_INSTRUCTIONS} instructions without reaching another line."
+        )))
+    }
+
+    #[instrument]
+    /// Execute `count` instructions of the most recently added process like
+    /// [`Self::step_instructions`], but a call counts as one instruction with everything it
+    /// runs, and so does a `rep` string instruction with all its repetitions. Those run at full
+    /// speed, with a temporary breakpoint where they end, so other threads run meanwhile.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, or it is not stopped.
+    pub fn next_instructions(&mut self, count: usize) -> Result<Stepped, DebuggerError> {
+        self.selected_frame = 0;
+        self.require_threads()?;
+        let tid = self.stopped_process()?;
+        let mut stepped = Stepped {
+            steps: 0,
+            reason: None,
+        };
+        while stepped.steps < count {
+            stepped.reason = self.next_instruction(tid)?;
+            if let Some(reason) = stepped.reason {
+                self.record_stop_reason(reason);
+                break;
+            }
+            stepped.steps += 1;
+        }
+        Ok(stepped)
+    }
+
+    /// One instruction of [`Self::next_instructions`].
+    fn next_instruction(
+        &mut self,
+        tid: nix::unistd::Pid,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        let before = nix::sys::ptrace::getregs(tid)?;
+        // The instruction may end a mapping, so fewer bytes than its longest do.
+        let code = (1..=15)
+            .rev()
+            .find_map(|len| memory::read_memory(tid, before.rip, len).ok())
+            .unwrap_or_default();
+        // The breakpoint the instruction may sit on must not count: it is the one stepped off.
+        let code: Vec<u8> = match self.armed_breakpoint(before.rip) {
+            Some(breakpoint) => std::iter::once(breakpoint.original_byte())
+                .chain(code.into_iter().skip(1))
+                .collect(),
+            None => code,
+        };
+        if let Some(length) = rep_string_length(&code) {
+            if self.armed_breakpoint(before.rip).is_none() {
+                return self.run_to_temporary_breakpoint(before.rip + length, |stopped| {
+                    Ok(stopped == tid)
+                });
+            }
+            // Resumed, it would hit the breakpoint it sits on again at every repetition.
+            loop {
+                if let Some(reason) = self.step_over_breakpoint(tid, false)? {
+                    return Ok(Some(reason));
+                }
+                let pc = nix::sys::ptrace::getregs(tid)?.rip;
+                if pc != before.rip {
+                    return Ok(self
+                        .reported_breakpoint(pc)
+                        .map(|id| StopReason::Breakpoint(tid, id)));
+                }
+            }
+        }
+        if let Some(reason) = self.step_instruction(tid)? {
+            return Ok(Some(reason));
+        }
+        let after = nix::sys::ptrace::getregs(tid)?;
+        if made_call(tid, &before, &after) {
+            return self.run_until_return(tid, after.rsp);
+        }
+        Ok(None)
+    }
+
+    /// Execute one instruction of `tid`, stepping over the breakpoint it sits on, if any, and
+    /// return why it stopped if that is worth reporting. Landing on a breakpoint is.
+    fn step_instruction(
+        &mut self,
+        tid: nix::unistd::Pid,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        let pc = nix::sys::ptrace::getregs(tid)?.rip;
+        let reason = if self.armed_breakpoint(pc).is_some() {
+            self.step_over_breakpoint(tid, false)?
+        } else {
+            self.step_thread(tid)?
+        };
+        if reason.is_some() {
+            return Ok(reason);
+        }
+        if let Some(reason) = self.software_watchpoint_hit(tid)? {
+            return Ok(Some(reason));
+        }
+        let pc = nix::sys::ptrace::getregs(tid)?.rip;
+        Ok(self
+            .reported_breakpoint(pc)
+            .map(|id| StopReason::Breakpoint(tid, id)))
+    }
+
+    /// Let the inferior run until thread `pid` returns from the call it just made, its stack
+    /// pointer right after the call being `call_sp`. Returns the reason if something else
+    /// stopped it first.
+    fn run_until_return(
+        &mut self,
+        pid: nix::unistd::Pid,
+        call_sp: u64,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        let return_address = memory::read_word(pid, call_sp)?;
+        // A recursive call, or another thread, may get there first.
+        self.run_to_temporary_breakpoint(return_address, |tid| {
+            Ok(tid == pid && nix::sys::ptrace::getregs(tid)?.rsp > call_sp)
+        })
+    }
+
+    /// Let the inferior run until it reaches function `name`, as `start` does with `main`. No
+    /// breakpoint of the user's is left behind. Returns the reason if something else stopped
+    /// it first.
+    ///
+    /// The dynamic loader runs on the way without stopping: it only would for a signal, a
+    /// caught system call or a breakpoint in its code.
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no process, no such function, or it cannot be resumed.
+    pub fn run_to_function(&mut self, name: &str) -> Result<Option<StopReason>, DebuggerError> {
+        let address = self.resolve_function(name)?;
+        self.run_to_temporary_breakpoint(address, |_| Ok(true))
+    }
+
+    /// Resume the inferior with a breakpoint at `address` until a thread hitting it is one
+    /// `arrived` accepts, then remove the breakpoint. Returns the reason if something else
+    /// stopped the inferior first.
+    fn run_to_temporary_breakpoint(
+        &mut self,
+        address: u64,
+        mut arrived: impl FnMut(nix::unistd::Pid) -> Result<bool, DebuggerError>,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        let process = self.stopped_process()?;
+        let already_patched = self
+            .armed_breakpoint(address)
+            .map(Breakpoint::original_byte);
+        // Id 0 is never given to the user's breakpoints.
+        self.breakpoints.push(Breakpoint::insert(
+            0,
+            BreakpointLocation::Address(address),
+            process,
+            address,
+            already_patched,
+        )?);
+        let reason = loop {
+            match self.resume() {
+                Ok(StopReason::Breakpoint(tid, 0)) => match arrived(tid) {
+                    Ok(true) => break Ok(None),
+                    Ok(false) => {}
+                    Err(e) => break Err(e),
+                },
+                other => break other.map(Some),
+            }
+        };
+        if let Some(index) = self.breakpoints.iter().position(|b| b.id == 0) {
+            let temporary = self.breakpoints.remove(index);
+            if already_patched.is_none() {
+                temporary.remove()?;
+            }
+        }
+        reason
+    }
+
+    /// Stop at system call `number`, or every system call if `None`, from now on.
+    pub fn catch_syscall(&mut self, number: Option<u64>) {
+        match (number, &mut self.syscall_catch) {
+            (_, syscalls::SyscallCatch::Any) => {}
+            (None, catch) => *catch = syscalls::SyscallCatch::Any,
+            (Some(number), syscalls::SyscallCatch::Only(numbers)) => {
+                numbers.insert(number);
+            }
+            (Some(number), catch @ syscalls::SyscallCatch::Nothing) => {
+                *catch = syscalls::SyscallCatch::Only(std::iter::once(number).collect());
+            }
+        }
+    }
+
+    /// Let `tid` run, stopping at syscalls too when they may have to be reported.
+    fn resume_thread(
+        &mut self,
+        tid: nix::unistd::Pid,
+        signal: Option<Signal>,
+    ) -> Result<(), DebuggerError> {
+        let resumed = if self.stops_at_syscalls() {
+            nix::sys::ptrace::syscall(tid, signal)
+        } else {
+            nix::sys::ptrace::cont(tid, signal)
+        };
+        self.resume_ptrace(tid, resumed)
+    }
+
+    /// Whether threads resume with `PTRACE_SYSCALL` rather than `PTRACE_CONT`: the two are
+    /// exclusive, and only the first stops at system calls, which we need to catch any.
+    fn stops_at_syscalls(&self) -> bool {
+        self.stepping_syscall || self.syscall_catch != syscalls::SyscallCatch::Nothing
+    }
+
+    fn record_stop_reason(&mut self, reason: StopReason) {
+        if let StopReason::Signaled(tid, _)
+        | StopReason::Syscall(tid, _)
+        | StopReason::Breakpoint(tid, _)
+        | StopReason::Watchpoint(tid, _)
+        | StopReason::Interrupted(tid)
+        | StopReason::Stepped(tid) = reason
+        {
+            self.last_stopped_thread = Some(tid);
+        }
+        match reason {
+            StopReason::Exited(pid, code) => {
+                self.exit_status = Some(ExitStatus::Exited(code));
+                self.record(Event::Exited {
+                    pid: pid.as_raw(),
+                    code,
+                });
+            }
+            StopReason::Killed(pid, signal) => {
+                self.exit_status = Some(ExitStatus::Killed(signal));
+                self.record(Event::Killed {
+                    pid: pid.as_raw(),
+                    signal: signal.to_string(),
+                });
+            }
+            StopReason::Signaled(pid, signal) => {
+                self.record_stop(pid, StopCause::Signal, Some(signal));
+            }
+            StopReason::Syscall(pid, _) => self.record_stop(pid, StopCause::Syscall, None),
+            StopReason::Breakpoint(pid, _) => {
+                self.record_stop(pid, StopCause::Breakpoint, Some(Signal::SIGTRAP));
+            }
+            StopReason::Watchpoint(pid, _) => {
+                self.record_stop(pid, StopCause::Watchpoint, Some(Signal::SIGTRAP));
+            }
+            StopReason::Interrupted(pid) => self.record_stop(pid, StopCause::Interrupt, None),
+            StopReason::Stepped(pid) => {
+                self.record_stop(pid, StopCause::Step, Some(Signal::SIGTRAP));
+            }
+        }
+        self.check_pending_breakpoints();
+    }
+
+    /// Wait until some thread stops in a way the user has to hear about, resuming the inferior
+    /// past everything else. Whatever is reported, every thread is stopped when this returns.
+    fn wait_for_stop(&mut self) -> Result<StopReason, DebuggerError> {
+        loop {
+            let status = match self.wait_thread(None) {
+                Err(nix::errno::Errno::ECHILD) => {
+                    // Everything we traced was reaped behind our back.
+                    for tid in self.attached_threads.clone() {
+                        self.forget(tid);
+                    }
+                    return Err(self.not_running());
+                }
+                status => status?,
+            };
+            match status {
+                // Unlike a group-stop, which comes with the stop signal, this is `interrupt`.
+                nix::sys::wait::WaitStatus::PtraceEvent(
+                    tid,
+                    Signal::SIGTRAP,
+                    PTRACE_EVENT_STOP,
+                ) if !self.starting_threads.contains(&tid)
+                    && self.attached_threads.contains(&tid) =>
+                {
+                    self.stop_all_threads(tid)?;
+                    return Ok(StopReason::Interrupted(tid));
+                }
+                nix::sys::wait::WaitStatus::PtraceEvent(tid, _, event) => {
+                    self.handle_ptrace_event(tid, event);
+                    self.resume_thread(tid, None)?;
+                }
+                nix::sys::wait::WaitStatus::PtraceSyscall(tid) => {
+                    let stop = syscalls::syscall_stop(tid)?;
+                    if self.stepping_syscall || self.syscall_catch.catches(stop.number()) {
+                        self.stop_all_threads(tid)?;
+                        return Ok(StopReason::Syscall(tid, stop));
+                    }
+                    self.resume_thread(tid, None)?;
+                }
+                nix::sys::wait::WaitStatus::Stopped(tid, signal) => {
+                    if self.is_thread_start_or_group_stop(tid, signal) {
+                        self.resume_thread(tid, None)?;
+                        continue;
+                    }
+                    if signal == Signal::SIGTRAP {
+                        match self.hardware_watchpoint_hit(tid)? {
+                            watchpoints::Trap::Hit(id) => {
+                                self.stop_all_threads(tid)?;
+                                return Ok(StopReason::Watchpoint(tid, id));
+                            }
+                            watchpoints::Trap::Ignored => {
+                                self.resume_thread(tid, None)?;
+                                continue;
+                            }
+                            watchpoints::Trap::Unrelated => {}
+                        }
+                        if let Some(id) = self.breakpoint_hit(tid)? {
+                            if id == breakpoints::LOADER_BREAKPOINT_ID {
+                                return self.libraries_changed(tid);
+                            }
+                            self.stop_all_threads(tid)?;
+                            return Ok(StopReason::Breakpoint(tid, id));
+                        }
+                    }
+                    let disposition = self.signal_disposition(signal);
+                    if disposition.stop {
+                        if disposition.pass {
+                            self.pending_signals.insert(tid, signal);
+                        } else {
+                            self.dropped_signals.insert(tid, signal);
+                        }
+                        self.stop_all_threads(tid)?;
+                        return Ok(StopReason::Signaled(tid, signal));
+                    }
+                    if disposition.print {
+                        self.signal_reports.push(SignalReport {
+                            pid: tid,
+                            signal,
+                            passed: disposition.pass,
+                        });
+                    }
+                    self.resume_thread(tid, disposition.pass.then_some(signal))?;
+                }
+                nix::sys::wait::WaitStatus::Exited(pid, code) => {
+                    if self.forget(pid) {
+                        self.stop_all_threads(pid)?;
+                        return Ok(StopReason::Exited(pid, code));
+                    }
+                }
+                nix::sys::wait::WaitStatus::Signaled(pid, signal, _) => {
+                    if self.forget(pid) {
+                        self.stop_all_threads(pid)?;
+                        return Ok(StopReason::Killed(pid, signal));
+                    }
+                }
+                status => {
+                    event!(Level::DEBUG, "Ignoring wait status {:?}", status);
+                }
+            }
+        }
+    }
+
+    /// The loader stopped `tid` at [`breakpoints::LOADER_HOOK`], having loaded or unloaded
+    /// libraries: stop the inferior to set the pending breakpoints they define, then resume
+    /// the threads that were running and wait on.
+    fn libraries_changed(&mut self, tid: nix::unistd::Pid) -> Result<StopReason, DebuggerError> {
+        let known = self.attached_threads.clone();
+        let mut running = self.running_threads.clone();
+        running.insert(tid);
+        self.stop_all_threads(tid)?;
+        if let Err(e) = self.set_pending_breakpoints() {
+            event!(Level::WARN, "Could not set the pending breakpoints: {}", e);
+        }
+        let threads = self
+            .attached_threads
+            .iter()
+            .copied()
+            .filter(|tid| running.contains(tid) || !known.contains(tid))
+            .collect();
+        self.resume_threads(threads)
+    }
+
+    /// Start tracing the thread a `PTRACE_EVENT_CLONE` announces, and arm the one a
+    /// `PTRACE_EVENT_STOP` says has started: a thread cloned by a seized one starts out seized,
+    /// and stopped by that event rather than by a `SIGSTOP`.
+    fn handle_ptrace_event(&mut self, tid: nix::unistd::Pid, event: i32) {
+        if event == PTRACE_EVENT_STOP {
+            // The new thread may report in before its parent's clone event does.
+            if !self.attached_threads.contains(&tid) {
+                self.attached_threads.push(tid);
+                self.seized_threads.insert(tid);
+                self.arm_new_thread(tid);
+            } else if self.starting_threads.remove(&tid) {
+                self.arm_new_thread(tid);
+            }
+            return;
+        }
+        if event != nix::sys::ptrace::Event::PTRACE_EVENT_CLONE as i32 {
+            return;
+        }
+        let Ok(new_tid) = nix::sys::ptrace::getevent(tid) else {
+            return;
+        };
+        let new_tid = nix::unistd::Pid::from_raw(i32::try_from(new_tid).unwrap_or_default());
+        if !self.attached_threads.contains(&new_tid) {
+            event!(Level::INFO, "Thread {} spawned thread {}", tid, new_tid);
+            self.attached_threads.push(new_tid);
+            self.starting_threads.insert(new_tid);
+            self.running_threads.insert(new_tid);
+            if self.seized_threads.contains(&tid) {
+                self.seized_threads.insert(new_tid);
+            }
+        }
+    }
+
+    /// Whether a stop is bookkeeping rather than a signal for the inferior: the `SIGSTOP` a
+    /// cloned thread starts with, or a group-stop, which reports the signal that caused it
+    /// even though that signal was already delivered.
+    fn is_thread_start_or_group_stop(&mut self, tid: nix::unistd::Pid, signal: Signal) -> bool {
+        if signal == Signal::SIGSTOP {
+            // The new thread may report in before its parent's clone event does.
+            if self.starting_threads.remove(&tid) {
+                self.arm_new_thread(tid);
+                return true;
+            }
+            if !self.attached_threads.contains(&tid) {
+                self.attached_threads.push(tid);
+                self.arm_new_thread(tid);
+                return true;
+            }
+        }
+        // Only a signal-delivery-stop has siginfo to read.
+        matches!(
+            signal,
+            Signal::SIGSTOP | Signal::SIGTSTP | Signal::SIGTTIN | Signal::SIGTTOU
+        ) && nix::sys::ptrace::getsiginfo(tid) == Err(nix::errno::Errno::EINVAL)
+    }
+
+    /// Stop thread `pid` wherever it is, or right after it is resumed if it is stopped; the stop
+    /// shows up at the next wait, as [`StopReason::Interrupted`]. A thread of an attached process is stopped with
+    /// `PTRACE_INTERRUPT`, which sends it no signal at all and so cannot be mistaken for one the
+    /// program sent itself. One of a program we launched is not seized, and gets a `SIGSTOP`
+    /// instead, which shows up as [`StopReason::Signaled`].
+    ///
+    /// As with any `ptrace` request, only the thread that attached or launched it can do this.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not a thread we trace, or it is gone.
+    pub fn interrupt(&self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        if !self.attached_threads.contains(&pid) {
+            return Err(self.not_running());
+        }
+        if self.seized_threads.contains(&pid) {
+            nix::sys::ptrace::interrupt(pid)?;
+            return Ok(());
+        }
+        // SAFETY: tkill only sends a signal.
+        let result = unsafe {
+            nix::libc::syscall(
+                nix::libc::SYS_tkill,
+                pid.as_raw(),
+                Signal::SIGSTOP as nix::libc::c_int,
+            )
+        };
+        nix::errno::Errno::result(result)?;
+        Ok(())
+    }
+
+    /// Bring every running thread but `stopped` (which is already stopped or gone) to a halt,
+    /// so the inferior sits still while the user looks at it. Threads that were left stopped,
+    /// such as those of the other processes when only one was continued, are left alone.
+    ///
+    /// Each thread is interrupted, and the stop that causes swallowed once it arrives. Signals
+    /// that arrive first are kept to be delivered on the next resume.
+    fn stop_all_threads(&mut self, stopped: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        let mut running = self
+            .running_threads
+            .iter()
+            .copied()
+            .filter(|tid| *tid != stopped)
+            .collect::<std::collections::BTreeSet<_>>();
+        // Threads that are still starting stop by themselves.
+        for &tid in running.difference(&self.starting_threads) {
+            // A thread that is already gone is reaped below.
+            if let Err(e) = self.interrupt(tid) {
+                event!(Level::DEBUG, "Could not interrupt thread {}: {}", tid, e);
+            }
+        }
+        while !running.is_empty() {
+            match self.wait_thread(None)? {
+                nix::sys::wait::WaitStatus::Stopped(tid, Signal::SIGSTOP)
+                    if !self.seized_threads.contains(&tid) =>
+                {
+                    self.starting_threads.remove(&tid);
+                    if !self.attached_threads.contains(&tid) {
+                        self.attached_threads.push(tid);
+                    }
+                    running.remove(&tid);
+                }
+                nix::sys::wait::WaitStatus::Stopped(tid, signal) => {
+                    // Our interruption is still pending behind this one, let the thread get to it.
+                    if self.signal_disposition(signal).pass {
+                        self.pending_signals.insert(tid, signal);
+                    }
+                    self.resume_ptrace(tid, nix::sys::ptrace::cont(tid, None))?;
+                }
+                nix::sys::wait::WaitStatus::PtraceSyscall(tid) => {
+                    // Not worth reporting now that we are stopping, but our interruption is
+                    // pending.
+                    self.resume_ptrace(tid, nix::sys::ptrace::cont(tid, None))?;
+                }
+                nix::sys::wait::WaitStatus::PtraceEvent(tid, _, PTRACE_EVENT_STOP) => {
+                    self.handle_ptrace_event(tid, PTRACE_EVENT_STOP);
+                    running.remove(&tid);
+                }
+                nix::sys::wait::WaitStatus::PtraceEvent(tid, _, event) => {
+                    self.handle_ptrace_event(tid, event);
+                    // The new thread starts out stopped by itself.
+                    running.extend(self.starting_threads.iter().copied());
+                    self.resume_ptrace(tid, nix::sys::ptrace::cont(tid, None))?;
+                }
+                nix::sys::wait::WaitStatus::Exited(tid, _)
+                | nix::sys::wait::WaitStatus::Signaled(tid, _, _) => {
+                    self.forget(tid);
+                    running.remove(&tid);
+                }
+                status => {
+                    event!(Level::DEBUG, "Ignoring wait status {:?}", status);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail with gdb's wording once there has been a program, and say there is none before.
+    fn not_running(&self) -> DebuggerError {
+        if self.launch_spec.is_none() && self.attached_processes.is_empty() {
+            return DebuggerError::ErrorMessage("No program is being debugged.".to_string());
+        }
+        DebuggerError::ErrorMessage("The program is not being run.".to_string())
+    }
+
+    /// Fail with gdb's wording unless `pid` is a thread we trace that still exists. A thread
+    /// can be gone before we reaped it, so asking the kernel catches what `ptrace` would only
+    /// report as `ESRCH`.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` exited, or was never traced.
+    pub fn require_live(&self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        let traced = self.attached_threads.contains(&pid) || self.attached_processes.contains(&pid);
+        if !traced || nix::sys::signal::kill(pid, None) == Err(nix::errno::Errno::ESRCH) {
+            return Err(self.not_running());
+        }
+        Ok(())
+    }
+
+    /// Fail with gdb's wording when there is nothing to resume, or some of it already runs.
+    fn require_threads(&self) -> Result<(), DebuggerError> {
+        if self.attached_threads.is_empty() {
+            return Err(self.not_running());
+        }
+        if !self.running_threads.is_empty() {
+            return Err(Self::running());
+        }
+        Ok(())
+    }
+
+    fn running() -> DebuggerError {
+        DebuggerError::ErrorMessage("Cannot do this while the process is running.".to_string())
+    }
+
+    /// Whether `pid`, a thread we trace or the main thread of a process, is stopped, so its
+    /// registers and memory can be looked at. Under the debugger it is, save between attaching
+    /// and [`Self::wait`], or when a resume failed halfway.
+    #[must_use]
+    pub fn is_stopped(&self, pid: nix::unistd::Pid) -> bool {
+        self.require_stopped(pid).is_ok()
+    }
+
+    /// [`Self::require_live`], and fail rather than get `ESRCH` from `ptrace` if `pid` is
+    /// running.
+    fn require_stopped(&self, pid: nix::unistd::Pid) -> Result<(), DebuggerError> {
+        self.require_live(pid)?;
+        if self.running_threads.contains(&pid) {
+            return Err(Self::running());
+        }
+        Ok(())
+    }
+
+    /// The most recently added process, as long as it is stopped.
+    fn stopped_process(&self) -> Result<nix::unistd::Pid, DebuggerError> {
+        let pid = self.current_process()?;
+        self.require_stopped(pid)?;
+        Ok(pid)
+    }
+
+    /// Keep track of `tid` running if `resumed`, the result of a `ptrace` request resuming it,
+    /// says it does.
+    fn resume_ptrace(
+        &mut self,
+        tid: nix::unistd::Pid,
+        resumed: nix::Result<()>,
+    ) -> Result<(), DebuggerError> {
+        resumed?;
+        self.running_threads.insert(tid);
+        Ok(())
+    }
+
+    /// `waitpid` for `pid`, or any thread, keeping track of the threads that stopped.
+    fn wait_thread(
+        &mut self,
+        pid: Option<nix::unistd::Pid>,
+    ) -> nix::Result<nix::sys::wait::WaitStatus> {
+        let status = nix::sys::wait::waitpid(pid, Some(nix::sys::wait::WaitPidFlag::__WALL))?;
+        if let Some(tid) = status.pid() {
+            self.running_threads.remove(&tid);
+        }
+        Ok(status)
+    }
+
+    /// Stop tracking a thread or process that no longer exists. Returns whether it was a
+    /// whole process rather than one of its threads.
+    fn forget(&mut self, pid: nix::unistd::Pid) -> bool {
+        self.attached_threads.retain(|t| *t != pid);
+        self.pending_signals.remove(&pid);
+        self.dropped_signals.remove(&pid);
+        self.starting_threads.remove(&pid);
+        self.seized_threads.remove(&pid);
+        self.running_threads.remove(&pid);
+        if self.last_stopped_thread == Some(pid) {
+            self.last_stopped_thread = None;
+        }
+        if !self.attached_processes.contains(&pid) {
+            return false;
+        }
+        self.attached_processes.retain(|p| *p != pid);
+        self.managed_processes
+            .retain(|child| child.id().cast_signed() != pid.as_raw());
+        self.breakpoints.retain(|breakpoint| breakpoint.pid != pid);
+        self.watchpoints.retain(|watchpoint| watchpoint.pid != pid);
+        true
+    }
+
+    /// If `tid` stopped because it executed a breakpoint's `int3`, rewind it onto the patched
+    /// instruction and tell which breakpoint it was.
+    fn breakpoint_hit(&self, tid: nix::unistd::Pid) -> Result<Option<usize>, DebuggerError> {
+        if !breakpoints::stopped_by_int3(tid) {
+            return Ok(None);
+        }
+        let mut regs = nix::sys::ptrace::getregs(tid)?;
+        let address = regs.rip - 1;
+        let Some(breakpoint) = self.armed_breakpoint(address) else {
+            return Ok(None);
+        };
+        regs.rip = address;
+        nix::sys::ptrace::setregs(tid, regs)?;
+        Ok(Some(breakpoint.id))
+    }
+
+    /// Debug registers are not inherited across `clone`, so give a new thread the hardware
+    /// watchpoints of its process.
+    fn arm_new_thread(&self, tid: nix::unistd::Pid) {
+        for watchpoint in &self.watchpoints {
+            if let WatchBackend::Hardware { slot } = watchpoint.backend
+                && self.threads_of(watchpoint.pid).contains(&tid)
+                && let Err(e) = watchpoints::arm(tid, slot, watchpoint)
+            {
+                event!(Level::WARN, "Could not watch from thread {}: {}", tid, e);
+            }
+        }
+    }
+
+    /// Whether a debug register made `tid` trap, and if so whether its watchpoint stops for
+    /// that access.
+    fn hardware_watchpoint_hit(
+        &mut self,
+        tid: nix::unistd::Pid,
+    ) -> Result<watchpoints::Trap, DebuggerError> {
+        let slots = watchpoints::triggered_slots(tid)?;
+        if slots.is_empty() {
+            return Ok(watchpoints::Trap::Unrelated);
+        }
+        let owned = self
+            .watchpoints
+            .iter()
+            .map(|watchpoint| self.threads_of(watchpoint.pid).contains(&tid))
+            .collect::<Vec<_>>();
+        for (watchpoint, owned) in self.watchpoints.iter_mut().zip(owned) {
+            if let WatchBackend::Hardware { slot } = watchpoint.backend
+                && owned
+                && slots.contains(&slot)
+            {
+                let changed = watchpoint.refresh()?;
+                if watchpoint.stops_for(changed) {
+                    watchpoint.hit_count += 1;
+                    return Ok(watchpoints::Trap::Hit(watchpoint.id));
+                }
+            }
+        }
+        Ok(watchpoints::Trap::Ignored)
+    }
+
+    /// Single step every thread in turn until a software watchpoint sees its memory change, or
+    /// something else stops the inferior. Only the stepped thread runs at any time, so there
+    /// is no one to stop afterwards.
+    fn step_until_watchpoint_changes(&mut self) -> Result<StopReason, DebuggerError> {
+        loop {
+            for tid in self.attached_threads.clone() {
+                if let Some(reason) = self.step_thread(tid)? {
+                    return Ok(reason);
+                }
+                if let Some(reason) = self.software_watchpoint_hit(tid)? {
+                    return Ok(reason);
+                }
+            }
+            self.require_threads()?;
+        }
+    }
+
+    /// Whether the instruction `tid` just executed changed the memory a software watchpoint
+    /// watches.
+    fn software_watchpoint_hit(
+        &mut self,
+        tid: nix::unistd::Pid,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        for watchpoint in &mut self.watchpoints {
+            if watchpoint.backend == WatchBackend::Software && watchpoint.refresh()? {
+                watchpoint.hit_count += 1;
+                return Ok(Some(StopReason::Watchpoint(tid, watchpoint.id)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Execute one instruction of `tid`, and return why it stopped if that is worth reporting.
+    fn step_thread(&mut self, tid: nix::unistd::Pid) -> Result<Option<StopReason>, DebuggerError> {
+        let signal = self.take_pending_signal(tid);
+        self.resume_ptrace(tid, nix::sys::ptrace::step(tid, signal))?;
+        match self.wait_thread(Some(tid))? {
+            nix::sys::wait::WaitStatus::Stopped(tid, signal) => {
+                if signal == Signal::SIGTRAP {
+                    if let watchpoints::Trap::Hit(id) = self.hardware_watchpoint_hit(tid)? {
+                        return Ok(Some(StopReason::Watchpoint(tid, id)));
+                    }
+                    if let Some(id) = self.breakpoint_hit(tid)? {
+                        if id != breakpoints::LOADER_BREAKPOINT_ID {
+                            return Ok(Some(StopReason::Breakpoint(tid, id)));
+                        }
+                        // Every other thread is stopped already.
+                        if let Err(e) = self.set_pending_breakpoints() {
+                            event!(Level::WARN, "Could not set the pending breakpoints: {}", e);
+                        }
+                        return Ok(None);
+                    }
+                    // An `int3` of the program's own, or a `SIGTRAP` sent to it, is a signal
+                    // like any other.
+                    if breakpoints::stopped_by_step(tid) {
+                        return Ok(None);
+                    }
+                }
+                if self.is_thread_start_or_group_stop(tid, signal) {
+                    return Ok(None);
+                }
+                let disposition = self.signal_disposition(signal);
+                if disposition.pass {
+                    self.pending_signals.insert(tid, signal);
+                }
+                if disposition.stop {
+                    if !disposition.pass {
+                        self.dropped_signals.insert(tid, signal);
+                    }
+                    return Ok(Some(StopReason::Signaled(tid, signal)));
+                }
+                if disposition.print {
+                    self.signal_reports.push(SignalReport {
+                        pid: tid,
+                        signal,
+                        passed: disposition.pass,
+                    });
+                }
+                Ok(None)
+            }
+            nix::sys::wait::WaitStatus::PtraceEvent(tid, _, event) => {
+                self.handle_ptrace_event(tid, event);
+                // Collect the new thread's initial stop, so it can be stepped in turn.
+                for new_tid in self.starting_threads.clone() {
+                    self.wait_thread(Some(new_tid))?;
+                    self.starting_threads.remove(&new_tid);
+                    self.arm_new_thread(new_tid);
+                }
+                Ok(None)
+            }
+            nix::sys::wait::WaitStatus::Exited(pid, code) => {
+                if self.forget(pid) {
+                    self.stop_all_threads(pid)?;
+                    return Ok(Some(StopReason::Exited(pid, code)));
+                }
+                Ok(None)
+            }
+            nix::sys::wait::WaitStatus::Signaled(pid, signal, _) => {
+                if self.forget(pid) {
+                    self.stop_all_threads(pid)?;
+                    return Ok(Some(StopReason::Killed(pid, signal)));
+                }
+                Ok(None)
+            }
+            status => {
+                event!(Level::DEBUG, "Ignoring wait status {:?}", status);
+                Ok(None)
+            }
+        }
+    }
+
+    /// If `tid` sits on a breakpoint, execute the original instruction with the breakpoint
+    /// lifted, then put it back. Other threads stay stopped meanwhile so none can run past it.
+    ///
+    /// Returns the reason if the thread stopped for something else on the way. When the
+    /// thread is `resuming` and system calls are being stopped at, a `syscall` instruction is
+    /// not single stepped, which would run the whole call unseen: the thread goes on to the
+    /// entry of the call instead, which is a stop of its own if the call is caught.
+    ///
+    /// This runs for every thread on every resume, so it is kept cheap. Without an armed
+    /// breakpoint nothing is asked of the thread, and one not sitting on one costs a single
+    /// `getregs`. Stepping off then takes five more system calls: the `int3` is lifted and
+    /// put back with one read and two writes, around the step and its wait.
+    fn step_over_breakpoint(
+        &mut self,
+        tid: nix::unistd::Pid,
+        resuming: bool,
+    ) -> Result<Option<StopReason>, DebuggerError> {
+        if !self.breakpoints.iter().any(|breakpoint| breakpoint.enabled) {
+            return Ok(None);
+        }
+        let pc = nix::sys::ptrace::getregs(tid)?.rip;
+        let Some(breakpoint) = self.armed_breakpoint(pc).cloned() else {
+            return Ok(None);
+        };
+        let patched = breakpoint.lift()?;
+        let enters_syscall = resuming
+            && self.stops_at_syscalls()
+            && memory::read_memory(tid, pc, 2).is_ok_and(|code| code == SYSCALL_INSTRUCTION);
+        let resumed = if enters_syscall {
+            nix::sys::ptrace::syscall(tid, None)
+        } else {
+            nix::sys::ptrace::step(tid, None)
+        };
+        self.resume_ptrace(tid, resumed)?;
+        let status = self.wait_thread(Some(tid))?;
+        match status {
+            nix::sys::wait::WaitStatus::PtraceSyscall(tid) => {
+                breakpoint.restore(patched)?;
+                let stop = syscalls::syscall_stop(tid)?;
+                if self.stepping_syscall || self.syscall_catch.catches(stop.number()) {
+                    return Ok(Some(StopReason::Syscall(tid, stop)));
+                }
+                return Ok(None);
+            }
+            nix::sys::wait::WaitStatus::Exited(pid, code) => {
+                return Ok(self.forget(pid).then_some(StopReason::Exited(pid, code)));
+            }
+            nix::sys::wait::WaitStatus::Signaled(pid, signal, _) => {
+                return Ok(self.forget(pid).then_some(StopReason::Killed(pid, signal)));
+            }
+            // A signal arriving before the step keeps the instruction from running, so the
+            // breakpoint is hit again once the signal is dealt with.
+            nix::sys::wait::WaitStatus::Stopped(_, signal)
+                if signal != Signal::SIGTRAP && self.signal_disposition(signal).pass =>
+            {
+                self.pending_signals.insert(tid, signal);
+            }
+            _ => {}
+        }
+        breakpoint.restore(patched)?;
+        Ok(None)
+    }
+
+    /// How the debugger currently reacts to `signal`.
+    #[must_use]
+    pub fn signal_disposition(&self, signal: Signal) -> SignalDisposition {
+        self.signal_dispositions
+            .get(&signal)
+            .copied()
+            .unwrap_or_else(|| SignalDisposition::default_for(signal))
+    }
+
+    /// Change how the debugger reacts to `signal` from now on.
+    pub fn set_signal_disposition(&mut self, signal: Signal, disposition: SignalDisposition) {
+        self.signal_dispositions.insert(signal, disposition);
+    }
+
+    /// Start keeping an [`Event`] log, to be drained with [`Self::take_events`].
+    pub fn record_events(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+
+    /// How the last process that ended under the debugger did, `None` if none has.
+    #[must_use]
+    pub const fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status
+    }
+
+    /// Take the events recorded since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record(&mut self, event: Event) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+
+    fn record_stop(&mut self, pid: nix::unistd::Pid, reason: StopCause, signal: Option<Signal>) {
+        if self.events.is_none() {
+            return;
+        }
+        let pc = nix::sys::ptrace::getregs(pid).ok().map(|regs| regs.rip);
+        self.record(Event::Stopped {
+            pid: pid.as_raw(),
+            reason,
+            pc,
+            signal: signal.map(|signal| signal.to_string()),
+        });
+    }
+
+    /// What the kernel says about the signal thread `pid` stopped with, such as where a
+    /// `SIGSEGV` faulted.
+    /// # Errors
+    ///
+    /// Will return `Err` if `pid` is not stopped by a signal.
+    pub fn signal_info(
+        &self,
+        pid: nix::unistd::Pid,
+    ) -> Result<nix::libc::siginfo_t, DebuggerError> {
+        self.require_stopped(pid)?;
+        Ok(nix::sys::ptrace::getsiginfo(pid)?)
+    }
+
+    /// Take the signals that were printed but not stopped for since the last call.
+    pub fn take_signal_reports(&mut self) -> Vec<SignalReport> {
+        std::mem::take(&mut self.signal_reports)
+    }
+
+    /// Take the signals threads had stopped for and were delivered, or dropped, as they were
+    /// resumed since the last call.
+    pub fn take_resumed_signals(&mut self) -> Vec<SignalReport> {
+        std::mem::take(&mut self.resumed_signals)
+    }
+
+    /// The signal `tid` is to be resumed with, noting it, or the one it drops, for
+    /// [`Self::take_resumed_signals`].
+    fn take_pending_signal(&mut self, tid: nix::unistd::Pid) -> Option<Signal> {
+        let signal = self.pending_signals.remove(&tid);
+        let report = match (signal, self.dropped_signals.remove(&tid)) {
+            (Some(signal), _) => Some((signal, true)),
+            (None, Some(dropped)) => Some((dropped, false)),
+            (None, None) => None,
+        };
+        if let Some((reported, passed)) = report {
+            self.resumed_signals.push(SignalReport {
+                pid: tid,
+                signal: reported,
+                passed,
+            });
+        }
+        signal
+    }
 }
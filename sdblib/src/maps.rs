@@ -0,0 +1,133 @@
+use crate::DebuggerError;
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    /// Permissions as the kernel prints them, e.g. `r-xp`.
+    pub permissions: String,
+    pub offset: u64,
+    /// The backing file, or a pseudo-name like `[stack]`. `None` for anonymous memory.
+    pub path: Option<String>,
+}
+
+impl MemoryRegion {
+    #[must_use]
+    pub fn is_executable(&self) -> bool {
+        self.permissions.contains('x')
+    }
+
+    #[must_use]
+    pub fn is_file_backed(&self) -> bool {
+        self.path.as_ref().is_some_and(|path| path.starts_with('/'))
+    }
+}
+
+/// A loaded ELF object and where it was mapped in the inferior.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedObject {
+    pub path: String,
+    pub load_address: u64,
+    /// Whether the file on disk still has its `.symtab`.
+    pub has_symbol_table: bool,
+}
+
+fn parse_hex(field: &str, line: &str) -> Result<u64, DebuggerError> {
+    u64::from_str_radix(field, 16)
+        .map_err(|e| DebuggerError::ErrorMessage(format!("Malformed maps line '{line}': {e}")))
+}
+
+fn parse_region(line: &str) -> Result<MemoryRegion, DebuggerError> {
+    let malformed = || DebuggerError::ErrorMessage(format!("Malformed maps line '{line}'"));
+    // address perms offset dev inode [path]; the path itself may contain spaces.
+    let mut fields = line.splitn(6, ' ');
+    let (start, end) = fields
+        .next()
+        .and_then(|range| range.split_once('-'))
+        .ok_or_else(malformed)?;
+    let permissions = fields.next().ok_or_else(malformed)?.to_string();
+    let offset = parse_hex(fields.next().ok_or_else(malformed)?, line)?;
+    let path = fields
+        .nth(2)
+        .map(str::trim_start)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string);
+    Ok(MemoryRegion {
+        start: parse_hex(start, line)?,
+        end: parse_hex(end, line)?,
+        permissions,
+        offset,
+        path,
+    })
+}
+
+/// Read the memory map of `pid`.
+pub fn read_memory_map(pid: nix::unistd::Pid) -> Result<Vec<MemoryRegion>, DebuggerError> {
+    std::fs::read_to_string(format!("/proc/{pid}/maps"))?
+        .lines()
+        .map(parse_region)
+        .collect()
+}
+
+/// The lowest address at which `path` is mapped in `pid`.
+pub fn load_address(pid: nix::unistd::Pid, path: &std::path::Path) -> Result<u64, DebuggerError> {
+    read_memory_map(pid)?
+        .iter()
+        .filter(|region| region.path.as_deref().map(std::path::Path::new) == Some(path))
+        .map(|region| region.start)
+        .min()
+        .ok_or_else(|| {
+            DebuggerError::ErrorMessage(format!(
+                "{} is not mapped in process {pid}",
+                path.display()
+            ))
+        })
+}
+
+fn has_symbol_table(path: &str) -> bool {
+    use object::read::elf::{ElfFile64, FileHeader};
+    let Ok(data) = std::fs::read(path) else {
+        return false;
+    };
+    let Ok(elf) = ElfFile64::<object::Endianness>::parse(data.as_slice()) else {
+        return false;
+    };
+    let endian = elf.endian();
+    elf.elf_header()
+        .sections(endian, data.as_slice())
+        .and_then(|sections| sections.symbols(endian, data.as_slice(), object::elf::SHT_SYMTAB))
+        .is_ok_and(|symbols| !symbols.is_empty())
+}
+
+/// Group the file-backed executable regions of `pid` into the objects they belong to, sorted
+/// by load address. The main executable is left out.
+pub fn shared_objects(pid: nix::unistd::Pid) -> Result<Vec<SharedObject>, DebuggerError> {
+    let executable = std::fs::read_link(format!("/proc/{pid}/exe")).ok();
+    let regions = read_memory_map(pid)?;
+    let mut objects: Vec<SharedObject> = Vec::new();
+    for region in regions.iter().filter(|region| region.is_executable()) {
+        let Some(path) = region.path.as_ref().filter(|_| region.is_file_backed()) else {
+            continue;
+        };
+        if executable.as_deref() == Some(std::path::Path::new(path))
+            || objects.iter().any(|object| object.path == *path)
+        {
+            continue;
+        }
+        // The object starts at its lowest mapping, which is not necessarily the executable one.
+        let load_address = regions
+            .iter()
+            .filter(|other| other.path.as_ref() == Some(path))
+            .map(|other| other.start)
+            .min()
+            .unwrap_or(region.start);
+        objects.push(SharedObject {
+            path: path.clone(),
+            load_address,
+            has_symbol_table: has_symbol_table(path),
+        });
+    }
+    objects.sort_by_key(|object| object.load_address);
+    Ok(objects)
+}
@@ -0,0 +1,195 @@
+use std::fmt::Write;
+
+use crate::DebuggerError;
+
+const WORD_SIZE: u64 = std::mem::size_of::<nix::libc::c_long>() as u64;
+
+pub fn read_word(pid: nix::unistd::Pid, address: u64) -> Result<u64, DebuggerError> {
+    let word = nix::sys::ptrace::read(pid, address as nix::sys::ptrace::AddressType)?;
+    Ok(word.cast_unsigned())
+}
+
+/// How many reads [`read_memory`] finished word by word, over every process.
+static WORD_BY_WORD_READS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+pub fn word_by_word_reads() -> usize {
+    WORD_BY_WORD_READS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Read `len` bytes of the inferior's memory starting at `address`.
+///
+/// The whole range is read in one `process_vm_readv`. That stops at the first page it may not
+/// read, like one mapped without read permission, which ptrace can still read: the rest is
+/// read one word at a time from there.
+pub fn read_memory(
+    pid: nix::unistd::Pid,
+    address: u64,
+    len: usize,
+) -> Result<Vec<u8>, DebuggerError> {
+    let mut bytes = vec![0; len];
+    let read = usize::try_from(address)
+        .ok()
+        .and_then(|base| {
+            nix::sys::uio::process_vm_readv(
+                pid,
+                &mut [std::io::IoSliceMut::new(&mut bytes)],
+                &[nix::sys::uio::RemoteIoVec { base, len }],
+            )
+            .ok()
+        })
+        .unwrap_or_default();
+    if read == len {
+        return Ok(bytes);
+    }
+    bytes.truncate(read);
+    WORD_BY_WORD_READS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    bytes.extend(read_words(pid, address + read as u64, len - read)?);
+    Ok(bytes)
+}
+
+/// Read `len` bytes at `address` one word at a time.
+fn read_words(pid: nix::unistd::Pid, address: u64, len: usize) -> Result<Vec<u8>, DebuggerError> {
+    let mut bytes = Vec::with_capacity(len);
+    let mut word_address = address - address % WORD_SIZE;
+    let mut skip = usize::try_from(address % WORD_SIZE).unwrap_or_default();
+    while bytes.len() < len {
+        let word = read_word(pid, word_address)?.to_le_bytes();
+        let take = (word.len() - skip).min(len - bytes.len());
+        bytes.extend_from_slice(&word[skip..skip + take]);
+        skip = 0;
+        word_address += WORD_SIZE;
+    }
+    Ok(bytes)
+}
+
+pub fn write_word(pid: nix::unistd::Pid, address: u64, word: u64) -> Result<(), DebuggerError> {
+    nix::sys::ptrace::write(
+        pid,
+        address as nix::sys::ptrace::AddressType,
+        word.cast_signed(),
+    )?;
+    Ok(())
+}
+
+/// Write `bytes` at `address` one word at a time, keeping the bytes of the first and last
+/// words that are not written.
+pub fn write_memory(
+    pid: nix::unistd::Pid,
+    address: u64,
+    bytes: &[u8],
+) -> Result<(), DebuggerError> {
+    let mut word_address = address - address % WORD_SIZE;
+    let mut skip = usize::try_from(address % WORD_SIZE).unwrap_or_default();
+    let mut bytes = bytes;
+    while !bytes.is_empty() {
+        let mut word = read_word(pid, word_address)?.to_le_bytes();
+        let take = (word.len() - skip).min(bytes.len());
+        word[skip..skip + take].copy_from_slice(&bytes[..take]);
+        write_word(pid, word_address, u64::from_le_bytes(word))?;
+        bytes = &bytes[take..];
+        skip = 0;
+        word_address += WORD_SIZE;
+    }
+    Ok(())
+}
+
+/// Replace the byte at `address`, returning the one that was there.
+pub fn replace_byte(pid: nix::unistd::Pid, address: u64, byte: u8) -> Result<u8, DebuggerError> {
+    let word = read_word(pid, address)?;
+    write_word(pid, address, (word & !0xff) | u64::from(byte))?;
+    Ok(word.to_le_bytes()[0])
+}
+
+/// Read the NUL terminated string at `address`, giving up after `max_len` bytes. Whole aligned
+/// words are read, so no word past the NUL is touched: it may not be mapped.
+pub fn read_c_string(
+    pid: nix::unistd::Pid,
+    address: u64,
+    max_len: usize,
+) -> Result<Vec<u8>, DebuggerError> {
+    let mut bytes = Vec::new();
+    let mut word_address = address - address % WORD_SIZE;
+    let mut skip = usize::try_from(address % WORD_SIZE).unwrap_or_default();
+    while bytes.len() < max_len {
+        let word = read_word(pid, word_address)?.to_le_bytes();
+        let word = &word[skip..];
+        if let Some(end) = word.iter().position(|byte| *byte == 0) {
+            bytes.extend_from_slice(&word[..end]);
+            break;
+        }
+        bytes.extend_from_slice(word);
+        skip = 0;
+        word_address += WORD_SIZE;
+    }
+    bytes.truncate(max_len);
+    Ok(bytes)
+}
+
+/// Escape `bytes` the way C would write them in a string literal, quotes included, and mark
+/// it `truncated` with a trailing `...`.
+#[must_use]
+pub fn quote_bytes(bytes: &[u8], truncated: bool) -> String {
+    let mut quoted = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\r' => quoted.push_str("\\r"),
+            0x20..0x7f => quoted.push(char::from(byte)),
+            _ => {
+                let _ = write!(quoted, "\\x{byte:02x}");
+            }
+        }
+    }
+    quoted.push('"');
+    if truncated {
+        quoted.push_str("...");
+    }
+    quoted
+}
+
+/// How much memory [`search_memory`] reads at a time.
+const SEARCH_CHUNK_SIZE: u64 = 4096;
+
+/// The addresses in `range` where `pattern` starts, at most `max_matches` of them. Memory that
+/// cannot be read is skipped, so a range may cover gaps between mappings.
+pub fn search_memory(
+    pid: nix::unistd::Pid,
+    range: std::ops::Range<u64>,
+    pattern: &[u8],
+    max_matches: usize,
+) -> Vec<u64> {
+    let mut matches = Vec::new();
+    if pattern.is_empty() {
+        return matches;
+    }
+    // The tail of the previous chunk, so matches that straddle two chunks are found, and the
+    // address its first byte was read from.
+    let mut window = Vec::new();
+    let mut window_start = range.start;
+    let mut address = range.start;
+    while address < range.end && matches.len() < max_matches {
+        // Chunks end on chunk-size boundaries, so one unmapped page only costs its own chunk.
+        let chunk_end = (address - address % SEARCH_CHUNK_SIZE + SEARCH_CHUNK_SIZE).min(range.end);
+        let len = usize::try_from(chunk_end - address).unwrap_or_default();
+        if let Ok(chunk) = read_memory(pid, address, len) {
+            window.extend_from_slice(&chunk);
+            let found = window
+                .windows(pattern.len())
+                .enumerate()
+                .filter(|(_, candidate)| *candidate == pattern)
+                .map(|(offset, _)| window_start + offset as u64);
+            matches.extend(found.take(max_matches - matches.len()));
+            let keep = window.len().min(pattern.len() - 1);
+            window.drain(..window.len() - keep);
+            window_start = chunk_end - keep as u64;
+        } else {
+            window.clear();
+            window_start = chunk_end;
+        }
+        address = chunk_end;
+    }
+    matches
+}
@@ -0,0 +1,136 @@
+use nix::libc::user_regs_struct;
+
+/// The flags of `rflags` worth naming, by bit.
+const RFLAGS: [(u32, &str); 9] = [
+    (0, "CF"),
+    (2, "PF"),
+    (4, "AF"),
+    (6, "ZF"),
+    (7, "SF"),
+    (8, "TF"),
+    (9, "IF"),
+    (10, "DF"),
+    (11, "OF"),
+];
+
+/// The general purpose registers, as [`general_registers`] lists them.
+const REGISTER_NAMES: [&str; 26] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "rflags", "cs", "ss", "ds", "es", "fs", "gs", "fs_base", "gs_base",
+];
+
+/// How a register's value reads best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// A number, shown in decimal besides hex.
+    Integer,
+    /// An address, shown in hex only.
+    Pointer,
+    /// An address in the code, shown with the function it is in.
+    CodePointer,
+    /// `rflags`, shown as the names of the flags set.
+    Flags,
+}
+
+/// A general purpose register of a stopped thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub name: &'static str,
+    pub value: u64,
+    pub kind: RegisterKind,
+}
+
+/// The names of the flags set in `rflags`, lowest bit first.
+#[must_use]
+pub fn decode_rflags(rflags: u64) -> Vec<&'static str> {
+    RFLAGS
+        .iter()
+        .filter(|(bit, _)| rflags & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// The name `name` refers to: the `$` gdb writes registers with is optional, and `pc`, `sp`,
+/// `fp` and gdb's `eflags` stand for `rip`, `rsp`, `rbp` and `rflags`.
+#[must_use]
+pub fn canonical_register_name(name: &str) -> &str {
+    match name.strip_prefix('$').unwrap_or(name) {
+        "pc" => "rip",
+        "sp" => "rsp",
+        "fp" => "rbp",
+        "eflags" => "rflags",
+        other => other,
+    }
+}
+
+/// Whether `name` is a general purpose register, aliases and `$` included.
+#[must_use]
+pub fn is_register_name(name: &str) -> bool {
+    REGISTER_NAMES.contains(&canonical_register_name(name))
+}
+
+/// The field of `regs` holding register `name`, aliases included.
+pub fn register_mut<'a>(regs: &'a mut user_regs_struct, name: &str) -> Option<&'a mut u64> {
+    Some(match canonical_register_name(name) {
+        "rax" => &mut regs.rax,
+        "rbx" => &mut regs.rbx,
+        "rcx" => &mut regs.rcx,
+        "rdx" => &mut regs.rdx,
+        "rsi" => &mut regs.rsi,
+        "rdi" => &mut regs.rdi,
+        "rbp" => &mut regs.rbp,
+        "rsp" => &mut regs.rsp,
+        "r8" => &mut regs.r8,
+        "r9" => &mut regs.r9,
+        "r10" => &mut regs.r10,
+        "r11" => &mut regs.r11,
+        "r12" => &mut regs.r12,
+        "r13" => &mut regs.r13,
+        "r14" => &mut regs.r14,
+        "r15" => &mut regs.r15,
+        "rip" => &mut regs.rip,
+        "rflags" => &mut regs.eflags,
+        "cs" => &mut regs.cs,
+        "ss" => &mut regs.ss,
+        "ds" => &mut regs.ds,
+        "es" => &mut regs.es,
+        "fs" => &mut regs.fs,
+        "gs" => &mut regs.gs,
+        "fs_base" => &mut regs.fs_base,
+        "gs_base" => &mut regs.gs_base,
+        _ => return None,
+    })
+}
+
+/// The general purpose registers in `regs`, in gdb's order.
+pub fn general_registers(regs: &user_regs_struct) -> Vec<Register> {
+    let register = |name, value, kind| Register { name, value, kind };
+    vec![
+        register("rax", regs.rax, RegisterKind::Integer),
+        register("rbx", regs.rbx, RegisterKind::Integer),
+        register("rcx", regs.rcx, RegisterKind::Integer),
+        register("rdx", regs.rdx, RegisterKind::Integer),
+        register("rsi", regs.rsi, RegisterKind::Integer),
+        register("rdi", regs.rdi, RegisterKind::Integer),
+        register("rbp", regs.rbp, RegisterKind::Pointer),
+        register("rsp", regs.rsp, RegisterKind::Pointer),
+        register("r8", regs.r8, RegisterKind::Integer),
+        register("r9", regs.r9, RegisterKind::Integer),
+        register("r10", regs.r10, RegisterKind::Integer),
+        register("r11", regs.r11, RegisterKind::Integer),
+        register("r12", regs.r12, RegisterKind::Integer),
+        register("r13", regs.r13, RegisterKind::Integer),
+        register("r14", regs.r14, RegisterKind::Integer),
+        register("r15", regs.r15, RegisterKind::Integer),
+        register("rip", regs.rip, RegisterKind::CodePointer),
+        register("rflags", regs.eflags, RegisterKind::Flags),
+        register("cs", regs.cs, RegisterKind::Integer),
+        register("ss", regs.ss, RegisterKind::Integer),
+        register("ds", regs.ds, RegisterKind::Integer),
+        register("es", regs.es, RegisterKind::Integer),
+        register("fs", regs.fs, RegisterKind::Integer),
+        register("gs", regs.gs, RegisterKind::Integer),
+        register("fs_base", regs.fs_base, RegisterKind::Pointer),
+        register("gs_base", regs.gs_base, RegisterKind::Pointer),
+    ]
+}
@@ -0,0 +1,113 @@
+use nix::sys::signal::Signal;
+
+/// How the debugger reacts when an inferior receives a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalDisposition {
+    /// Hand control back to the user when the signal arrives.
+    pub stop: bool,
+    /// Report the signal even when we do not stop for it.
+    pub print: bool,
+    /// Deliver the signal to the inferior when it is resumed.
+    pub pass: bool,
+}
+
+impl SignalDisposition {
+    /// The disposition a signal has until the user changes it.
+    #[must_use]
+    pub const fn default_for(signal: Signal) -> Self {
+        match signal {
+            // We use SIGTRAP internally, the inferior never gets to see it.
+            Signal::SIGTRAP => Self {
+                stop: false,
+                print: false,
+                pass: false,
+            },
+            Signal::SIGINT => Self {
+                stop: true,
+                print: true,
+                pass: false,
+            },
+            Signal::SIGALRM
+            | Signal::SIGURG
+            | Signal::SIGCHLD
+            | Signal::SIGWINCH
+            | Signal::SIGIO
+            | Signal::SIGVTALRM
+            | Signal::SIGPROF => Self {
+                stop: false,
+                print: false,
+                pass: true,
+            },
+            _ => Self {
+                stop: true,
+                print: true,
+                pass: true,
+            },
+        }
+    }
+}
+
+/// A signal that was received while continuing, but that we did not stop for, or one a thread
+/// had stopped for and was resumed with or without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalReport {
+    pub pid: nix::unistd::Pid,
+    pub signal: Signal,
+    /// Whether the signal was delivered to the inferior.
+    pub passed: bool,
+}
+
+/// What `si_code` says about how `signal` came about, for the codes worth a word: the
+/// fault kinds of the synchronous signals, and who sent the others.
+#[must_use]
+pub const fn describe_si_code(signal: Signal, code: i32) -> Option<&'static str> {
+    Some(match (signal, code) {
+        (Signal::SIGSEGV, 1) => "Address not mapped to object",
+        (Signal::SIGSEGV, 2) => "Invalid permissions for mapped object",
+        (Signal::SIGSEGV, 3) => "Failed address bound checks",
+        (Signal::SIGSEGV, 4) => "Failed protection key checks",
+        (Signal::SIGBUS, 1) => "Invalid address alignment",
+        (Signal::SIGBUS, 2) => "Nonexistent physical address",
+        (Signal::SIGBUS, 3) => "Object-specific hardware error",
+        (Signal::SIGILL, 1) => "Illegal opcode",
+        (Signal::SIGILL, 2) => "Illegal operand",
+        (Signal::SIGILL, 3) => "Illegal addressing mode",
+        (Signal::SIGILL, 4) => "Illegal trap",
+        (Signal::SIGILL, 5) => "Privileged opcode",
+        (Signal::SIGILL, 6) => "Privileged register",
+        (Signal::SIGILL, 7) => "Coprocessor error",
+        (Signal::SIGILL, 8) => "Internal stack error",
+        (Signal::SIGFPE, 1) => "Integer divide by zero",
+        (Signal::SIGFPE, 2) => "Integer overflow",
+        (Signal::SIGFPE, 3) => "Floating point divide by zero",
+        (Signal::SIGFPE, 4) => "Floating point overflow",
+        (Signal::SIGFPE, 5) => "Floating point underflow",
+        (Signal::SIGFPE, 6) => "Floating point inexact result",
+        (Signal::SIGFPE, 7) => "Invalid floating point operation",
+        (Signal::SIGFPE, 8) => "Subscript out of range",
+        (_, 0) => "Sent by kill",
+        (_, 0x80) => "Sent by the kernel",
+        (_, -1) => "Sent by sigqueue",
+        (_, -6) => "Sent by tkill",
+        _ => return None,
+    })
+}
+
+/// Whether `signal` reports a fault of the instruction at hand, so its siginfo carries the
+/// faulting address.
+#[must_use]
+pub const fn is_fault(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGILL | Signal::SIGFPE
+    )
+}
+
+/// The address a fault is about, as `si_addr` has it; `None` unless the kernel raised a fault
+/// signal.
+#[must_use]
+pub fn fault_address(info: &nix::libc::siginfo_t) -> Option<u64> {
+    let is_kernel_fault = Signal::try_from(info.si_signo).is_ok_and(is_fault) && info.si_code > 0;
+    // SAFETY: the kernel fills `si_addr` in for faults it raises, which is all we read.
+    is_kernel_fault.then(|| unsafe { info.si_addr() } as u64)
+}
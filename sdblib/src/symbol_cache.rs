@@ -0,0 +1,135 @@
+use crate::DebuggerError;
+use crate::debug_info::{DebugInfo, LineTable};
+use crate::symbols::FunctionSymbol;
+use std::os::unix::fs::MetadataExt;
+
+/// What tells one version of a file from another without reading it: a rebuilt binary, or a
+/// different one exec'd under the same path, changes at least one of these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    device: u64,
+    inode: u64,
+    size: u64,
+    modified: i64,
+    modified_nanoseconds: i64,
+}
+
+impl FileIdentity {
+    fn of(path: &std::path::Path) -> Result<Self, DebuggerError> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            size: metadata.size(),
+            modified: metadata.mtime(),
+            modified_nanoseconds: metadata.mtime_nsec(),
+        })
+    }
+}
+
+/// An object file read once, with what is looked up in it worked out the first time it is
+/// needed.
+pub struct ParsedObject {
+    identity: FileIdentity,
+    pub info: DebugInfo,
+    functions: std::sync::OnceLock<Vec<FunctionSymbol>>,
+    line_table: std::sync::OnceLock<LineTable>,
+}
+
+impl ParsedObject {
+    /// The functions it defines at their file addresses: DWARF first, as it is the most
+    /// precise, then whatever the symbol tables have.
+    pub fn functions(&self) -> &[FunctionSymbol] {
+        self.functions.get_or_init(|| {
+            let mut functions = self.info.dwarf_functions().unwrap_or_default();
+            functions.extend(self.info.elf_functions());
+            functions
+        })
+    }
+
+    /// Its line table, at file addresses.
+    /// # Errors
+    ///
+    /// Will return `Err` if the DWARF is malformed; that is found out again on every call.
+    pub fn line_table(&self) -> Result<&LineTable, DebuggerError> {
+        if let Some(table) = self.line_table.get() {
+            return Ok(table);
+        }
+        let table = self.info.line_table()?;
+        Ok(self.line_table.get_or_init(|| table))
+    }
+}
+
+type Objects = std::collections::BTreeMap<std::path::PathBuf, std::sync::Arc<ParsedObject>>;
+
+/// The object files symbols and lines were looked up in, by path, so commands do not read
+/// and parse them again every time.
+///
+/// An entry is only used while the file is the one that was read: after an exec or a rebuild
+/// the path names another file, which is read afresh.
+pub struct SymbolCache {
+    objects: std::sync::Mutex<Objects>,
+    loads: std::sync::atomic::AtomicUsize,
+}
+
+impl SymbolCache {
+    pub const fn new() -> Self {
+        Self {
+            objects: std::sync::Mutex::new(std::collections::BTreeMap::new()),
+            loads: std::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+
+    /// The object file at `path`, read now unless it already was.
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or is not a 64-bit ELF file.
+    pub fn object(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<std::sync::Arc<ParsedObject>, DebuggerError> {
+        let identity = FileIdentity::of(path)?;
+        if let Some(object) = self.objects().get(path)
+            && object.identity == identity
+        {
+            return Ok(object.clone());
+        }
+        let object = std::sync::Arc::new(ParsedObject {
+            identity,
+            info: DebugInfo::load(path)?,
+            functions: std::sync::OnceLock::new(),
+            line_table: std::sync::OnceLock::new(),
+        });
+        self.loads
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.objects().insert(path.to_path_buf(), object.clone());
+        Ok(object)
+    }
+
+    fn objects(&self) -> std::sync::MutexGuard<'_, Objects> {
+        // Nothing panics while holding the lock, and the map is never left half updated.
+        self.objects
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Forget every file, unmapping those no command still uses.
+    pub fn clear(&self) {
+        self.objects().clear();
+    }
+
+    /// How many times a file was read.
+    pub fn loads(&self) -> usize {
+        self.loads.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// The file contents would drown the logs that print the debugger.
+impl std::fmt::Debug for SymbolCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SymbolCache")
+            .field("objects", &self.objects().keys().collect::<Vec<_>>())
+            .field("loads", &self.loads())
+            .finish()
+    }
+}
@@ -0,0 +1,165 @@
+use crate::DebuggerError;
+use crate::maps;
+use crate::symbol_cache::{ParsedObject, SymbolCache};
+
+/// A function and where its code lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSymbol {
+    pub name: String,
+    pub address: u64,
+    /// 0 when the symbol does not say.
+    pub size: u64,
+}
+
+impl FunctionSymbol {
+    fn contains(&self, address: u64) -> bool {
+        (self.address..self.address.saturating_add(self.size)).contains(&address)
+    }
+}
+
+/// Where an address falls relative to the function containing it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SymbolLocation {
+    pub function: String,
+    pub offset: u64,
+}
+
+impl std::fmt::Display for SymbolLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.offset == 0 {
+            write!(f, "{}", self.function)
+        } else {
+            write!(f, "{}+{}", self.function, self.offset)
+        }
+    }
+}
+
+/// The human readable form of symbol `name`, or `name` itself if it is not mangled.
+///
+/// Rust symbols in both manglings are understood, and so are C++ names made only of nested
+/// identifiers, which share the legacy Rust scheme. Anything else passes through as is.
+#[must_use]
+pub fn demangle(name: &str) -> String {
+    // The alternate form leaves out the hash legacy Rust symbols end with.
+    rustc_demangle::try_demangle(name).map_or_else(|_| name.to_string(), |name| format!("{name:#}"))
+}
+
+/// An ELF object mapped in the inferior, and the difference between its file addresses and
+/// the runtime ones.
+struct LoadedObject {
+    object: std::sync::Arc<ParsedObject>,
+    load_bias: u64,
+}
+
+impl LoadedObject {
+    fn load(
+        cache: &SymbolCache,
+        pid: nix::unistd::Pid,
+        path: &std::path::Path,
+    ) -> Result<Self, DebuggerError> {
+        let object = cache.object(path)?;
+        let load_bias =
+            maps::load_address(pid, path)?.wrapping_sub(object.info.first_load_address());
+        Ok(Self { object, load_bias })
+    }
+
+    /// Find `name` by its symbol name or its demangled one.
+    fn find_function(&self, name: &str) -> Option<u64> {
+        find_function(&self.object, name).map(|address| address.wrapping_add(self.load_bias))
+    }
+
+    fn symbolize(&self, address: u64) -> Option<SymbolLocation> {
+        let file_address = address.wrapping_sub(self.load_bias);
+        let functions = self.object.functions();
+        // Symbols without a size still cover whatever follows them, as a last resort.
+        let function = functions
+            .iter()
+            .find(|function| function.contains(file_address))
+            .or_else(|| {
+                functions
+                    .iter()
+                    .filter(|function| function.size == 0 && function.address <= file_address)
+                    .max_by_key(|function| function.address)
+            })?;
+        Some(SymbolLocation {
+            function: function.name.clone(),
+            offset: file_address - function.address,
+        })
+    }
+}
+
+/// The file address of function `name` in `object`, by its symbol name or its demangled one.
+fn find_function(object: &ParsedObject, name: &str) -> Option<u64> {
+    object
+        .functions()
+        .iter()
+        .find(|function| function.name == name || demangle(&function.name) == name)
+        .map(|function| function.address)
+}
+
+/// The runtime address of function `name`, looking in the executable before the shared
+/// libraries.
+pub fn resolve_function(
+    cache: &SymbolCache,
+    pid: nix::unistd::Pid,
+    name: &str,
+) -> Result<u64, DebuggerError> {
+    let executable = std::fs::read_link(format!("/proc/{pid}/exe"))?;
+    let libraries = maps::shared_objects(pid)?
+        .into_iter()
+        .map(|object| std::path::PathBuf::from(object.path));
+    std::iter::once(executable)
+        .chain(libraries)
+        .find_map(|path| {
+            LoadedObject::load(cache, pid, &path)
+                .ok()?
+                .find_function(name)
+        })
+        .ok_or_else(|| DebuggerError::ErrorMessage(format!("Function \"{name}\" not defined.")))
+}
+
+/// Every function the executable and its shared libraries define, at runtime addresses and
+/// sorted by them. Functions both DWARF and a symbol table know are listed once.
+pub fn functions(
+    cache: &SymbolCache,
+    pid: nix::unistd::Pid,
+) -> Result<Vec<FunctionSymbol>, DebuggerError> {
+    let executable = std::fs::read_link(format!("/proc/{pid}/exe"))?;
+    let libraries = maps::shared_objects(pid)?
+        .into_iter()
+        .map(|object| std::path::PathBuf::from(object.path));
+    let mut functions = Vec::new();
+    for path in std::iter::once(executable).chain(libraries) {
+        let Ok(object) = LoadedObject::load(cache, pid, &path) else {
+            continue;
+        };
+        functions.extend(
+            object
+                .object
+                .functions()
+                .iter()
+                .map(|function| FunctionSymbol {
+                    address: function.address.wrapping_add(object.load_bias),
+                    ..function.clone()
+                }),
+        );
+    }
+    functions.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+    functions.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+    Ok(functions)
+}
+
+/// The function containing runtime address `address`, if any object mapped there knows it.
+pub fn symbolize(
+    cache: &SymbolCache,
+    pid: nix::unistd::Pid,
+    address: u64,
+) -> Option<SymbolLocation> {
+    let region = maps::read_memory_map(pid)
+        .ok()?
+        .into_iter()
+        .find(|region| (region.start..region.end).contains(&address) && region.is_file_backed())?;
+    LoadedObject::load(cache, pid, std::path::Path::new(region.path.as_deref()?))
+        .ok()?
+        .symbolize(address)
+}
@@ -0,0 +1,714 @@
+use crate::DebuggerError;
+use crate::memory::quote_bytes;
+
+/// x86-64 Linux system call numbers and names, sorted by number.
+const SYSCALLS: &[(u64, &str)] = &[
+    (0, "read"),
+    (1, "write"),
+    (2, "open"),
+    (3, "close"),
+    (4, "stat"),
+    (5, "fstat"),
+    (6, "lstat"),
+    (7, "poll"),
+    (8, "lseek"),
+    (9, "mmap"),
+    (10, "mprotect"),
+    (11, "munmap"),
+    (12, "brk"),
+    (13, "rt_sigaction"),
+    (14, "rt_sigprocmask"),
+    (15, "rt_sigreturn"),
+    (16, "ioctl"),
+    (17, "pread64"),
+    (18, "pwrite64"),
+    (19, "readv"),
+    (20, "writev"),
+    (21, "access"),
+    (22, "pipe"),
+    (23, "select"),
+    (24, "sched_yield"),
+    (25, "mremap"),
+    (26, "msync"),
+    (27, "mincore"),
+    (28, "madvise"),
+    (29, "shmget"),
+    (30, "shmat"),
+    (31, "shmctl"),
+    (32, "dup"),
+    (33, "dup2"),
+    (34, "pause"),
+    (35, "nanosleep"),
+    (36, "getitimer"),
+    (37, "alarm"),
+    (38, "setitimer"),
+    (39, "getpid"),
+    (40, "sendfile"),
+    (41, "socket"),
+    (42, "connect"),
+    (43, "accept"),
+    (44, "sendto"),
+    (45, "recvfrom"),
+    (46, "sendmsg"),
+    (47, "recvmsg"),
+    (48, "shutdown"),
+    (49, "bind"),
+    (50, "listen"),
+    (51, "getsockname"),
+    (52, "getpeername"),
+    (53, "socketpair"),
+    (54, "setsockopt"),
+    (55, "getsockopt"),
+    (56, "clone"),
+    (57, "fork"),
+    (58, "vfork"),
+    (59, "execve"),
+    (60, "exit"),
+    (61, "wait4"),
+    (62, "kill"),
+    (63, "uname"),
+    (64, "semget"),
+    (65, "semop"),
+    (66, "semctl"),
+    (67, "shmdt"),
+    (68, "msgget"),
+    (69, "msgsnd"),
+    (70, "msgrcv"),
+    (71, "msgctl"),
+    (72, "fcntl"),
+    (73, "flock"),
+    (74, "fsync"),
+    (75, "fdatasync"),
+    (76, "truncate"),
+    (77, "ftruncate"),
+    (78, "getdents"),
+    (79, "getcwd"),
+    (80, "chdir"),
+    (81, "fchdir"),
+    (82, "rename"),
+    (83, "mkdir"),
+    (84, "rmdir"),
+    (85, "creat"),
+    (86, "link"),
+    (87, "unlink"),
+    (88, "symlink"),
+    (89, "readlink"),
+    (90, "chmod"),
+    (91, "fchmod"),
+    (92, "chown"),
+    (93, "fchown"),
+    (94, "lchown"),
+    (95, "umask"),
+    (96, "gettimeofday"),
+    (97, "getrlimit"),
+    (98, "getrusage"),
+    (99, "sysinfo"),
+    (100, "times"),
+    (101, "ptrace"),
+    (102, "getuid"),
+    (103, "syslog"),
+    (104, "getgid"),
+    (105, "setuid"),
+    (106, "setgid"),
+    (107, "geteuid"),
+    (108, "getegid"),
+    (109, "setpgid"),
+    (110, "getppid"),
+    (111, "getpgrp"),
+    (112, "setsid"),
+    (113, "setreuid"),
+    (114, "setregid"),
+    (115, "getgroups"),
+    (116, "setgroups"),
+    (117, "setresuid"),
+    (118, "getresuid"),
+    (119, "setresgid"),
+    (120, "getresgid"),
+    (121, "getpgid"),
+    (122, "setfsuid"),
+    (123, "setfsgid"),
+    (124, "getsid"),
+    (125, "capget"),
+    (126, "capset"),
+    (127, "rt_sigpending"),
+    (128, "rt_sigtimedwait"),
+    (129, "rt_sigqueueinfo"),
+    (130, "rt_sigsuspend"),
+    (131, "sigaltstack"),
+    (132, "utime"),
+    (133, "mknod"),
+    (134, "uselib"),
+    (135, "personality"),
+    (136, "ustat"),
+    (137, "statfs"),
+    (138, "fstatfs"),
+    (139, "sysfs"),
+    (140, "getpriority"),
+    (141, "setpriority"),
+    (142, "sched_setparam"),
+    (143, "sched_getparam"),
+    (144, "sched_setscheduler"),
+    (145, "sched_getscheduler"),
+    (146, "sched_get_priority_max"),
+    (147, "sched_get_priority_min"),
+    (148, "sched_rr_get_interval"),
+    (149, "mlock"),
+    (150, "munlock"),
+    (151, "mlockall"),
+    (152, "munlockall"),
+    (153, "vhangup"),
+    (154, "modify_ldt"),
+    (155, "pivot_root"),
+    (156, "_sysctl"),
+    (157, "prctl"),
+    (158, "arch_prctl"),
+    (159, "adjtimex"),
+    (160, "setrlimit"),
+    (161, "chroot"),
+    (162, "sync"),
+    (163, "acct"),
+    (164, "settimeofday"),
+    (165, "mount"),
+    (166, "umount2"),
+    (167, "swapon"),
+    (168, "swapoff"),
+    (169, "reboot"),
+    (170, "sethostname"),
+    (171, "setdomainname"),
+    (172, "iopl"),
+    (173, "ioperm"),
+    (174, "create_module"),
+    (175, "init_module"),
+    (176, "delete_module"),
+    (177, "get_kernel_syms"),
+    (178, "query_module"),
+    (179, "quotactl"),
+    (180, "nfsservctl"),
+    (181, "getpmsg"),
+    (182, "putpmsg"),
+    (183, "afs_syscall"),
+    (184, "tuxcall"),
+    (185, "security"),
+    (186, "gettid"),
+    (187, "readahead"),
+    (188, "setxattr"),
+    (189, "lsetxattr"),
+    (190, "fsetxattr"),
+    (191, "getxattr"),
+    (192, "lgetxattr"),
+    (193, "fgetxattr"),
+    (194, "listxattr"),
+    (195, "llistxattr"),
+    (196, "flistxattr"),
+    (197, "removexattr"),
+    (198, "lremovexattr"),
+    (199, "fremovexattr"),
+    (200, "tkill"),
+    (201, "time"),
+    (202, "futex"),
+    (203, "sched_setaffinity"),
+    (204, "sched_getaffinity"),
+    (205, "set_thread_area"),
+    (206, "io_setup"),
+    (207, "io_destroy"),
+    (208, "io_getevents"),
+    (209, "io_submit"),
+    (210, "io_cancel"),
+    (211, "get_thread_area"),
+    (212, "lookup_dcookie"),
+    (213, "epoll_create"),
+    (214, "epoll_ctl_old"),
+    (215, "epoll_wait_old"),
+    (216, "remap_file_pages"),
+    (217, "getdents64"),
+    (218, "set_tid_address"),
+    (219, "restart_syscall"),
+    (220, "semtimedop"),
+    (221, "fadvise64"),
+    (222, "timer_create"),
+    (223, "timer_settime"),
+    (224, "timer_gettime"),
+    (225, "timer_getoverrun"),
+    (226, "timer_delete"),
+    (227, "clock_settime"),
+    (228, "clock_gettime"),
+    (229, "clock_getres"),
+    (230, "clock_nanosleep"),
+    (231, "exit_group"),
+    (232, "epoll_wait"),
+    (233, "epoll_ctl"),
+    (234, "tgkill"),
+    (235, "utimes"),
+    (236, "vserver"),
+    (237, "mbind"),
+    (238, "set_mempolicy"),
+    (239, "get_mempolicy"),
+    (240, "mq_open"),
+    (241, "mq_unlink"),
+    (242, "mq_timedsend"),
+    (243, "mq_timedreceive"),
+    (244, "mq_notify"),
+    (245, "mq_getsetattr"),
+    (246, "kexec_load"),
+    (247, "waitid"),
+    (248, "add_key"),
+    (249, "request_key"),
+    (250, "keyctl"),
+    (251, "ioprio_set"),
+    (252, "ioprio_get"),
+    (253, "inotify_init"),
+    (254, "inotify_add_watch"),
+    (255, "inotify_rm_watch"),
+    (256, "migrate_pages"),
+    (257, "openat"),
+    (258, "mkdirat"),
+    (259, "mknodat"),
+    (260, "fchownat"),
+    (261, "futimesat"),
+    (262, "newfstatat"),
+    (263, "unlinkat"),
+    (264, "renameat"),
+    (265, "linkat"),
+    (266, "symlinkat"),
+    (267, "readlinkat"),
+    (268, "fchmodat"),
+    (269, "faccessat"),
+    (270, "pselect6"),
+    (271, "ppoll"),
+    (272, "unshare"),
+    (273, "set_robust_list"),
+    (274, "get_robust_list"),
+    (275, "splice"),
+    (276, "tee"),
+    (277, "sync_file_range"),
+    (278, "vmsplice"),
+    (279, "move_pages"),
+    (280, "utimensat"),
+    (281, "epoll_pwait"),
+    (282, "signalfd"),
+    (283, "timerfd_create"),
+    (284, "eventfd"),
+    (285, "fallocate"),
+    (286, "timerfd_settime"),
+    (287, "timerfd_gettime"),
+    (288, "accept4"),
+    (289, "signalfd4"),
+    (290, "eventfd2"),
+    (291, "epoll_create1"),
+    (292, "dup3"),
+    (293, "pipe2"),
+    (294, "inotify_init1"),
+    (295, "preadv"),
+    (296, "pwritev"),
+    (297, "rt_tgsigqueueinfo"),
+    (298, "perf_event_open"),
+    (299, "recvmmsg"),
+    (300, "fanotify_init"),
+    (301, "fanotify_mark"),
+    (302, "prlimit64"),
+    (303, "name_to_handle_at"),
+    (304, "open_by_handle_at"),
+    (305, "clock_adjtime"),
+    (306, "syncfs"),
+    (307, "sendmmsg"),
+    (308, "setns"),
+    (309, "getcpu"),
+    (310, "process_vm_readv"),
+    (311, "process_vm_writev"),
+    (312, "kcmp"),
+    (313, "finit_module"),
+    (314, "sched_setattr"),
+    (315, "sched_getattr"),
+    (316, "renameat2"),
+    (317, "seccomp"),
+    (318, "getrandom"),
+    (319, "memfd_create"),
+    (320, "kexec_file_load"),
+    (321, "bpf"),
+    (322, "execveat"),
+    (323, "userfaultfd"),
+    (324, "membarrier"),
+    (325, "mlock2"),
+    (326, "copy_file_range"),
+    (327, "preadv2"),
+    (328, "pwritev2"),
+    (329, "pkey_mprotect"),
+    (330, "pkey_alloc"),
+    (331, "pkey_free"),
+    (332, "statx"),
+    (333, "io_pgetevents"),
+    (334, "rseq"),
+    (424, "pidfd_send_signal"),
+    (425, "io_uring_setup"),
+    (426, "io_uring_enter"),
+    (427, "io_uring_register"),
+    (428, "open_tree"),
+    (429, "move_mount"),
+    (430, "fsopen"),
+    (431, "fsconfig"),
+    (432, "fsmount"),
+    (433, "fspick"),
+    (434, "pidfd_open"),
+    (435, "clone3"),
+    (436, "close_range"),
+    (437, "openat2"),
+    (438, "pidfd_getfd"),
+    (439, "faccessat2"),
+    (440, "process_madvise"),
+    (441, "epoll_pwait2"),
+    (442, "mount_setattr"),
+    (443, "quotactl_fd"),
+    (444, "landlock_create_ruleset"),
+    (445, "landlock_add_rule"),
+    (446, "landlock_restrict_self"),
+    (447, "memfd_secret"),
+    (448, "process_mrelease"),
+    (449, "futex_waitv"),
+    (450, "set_mempolicy_home_node"),
+];
+
+/// The name of x86-64 system call `number`.
+#[must_use]
+pub fn syscall_name(number: u64) -> Option<&'static str> {
+    SYSCALLS
+        .binary_search_by_key(&number, |(number, _)| *number)
+        .ok()
+        .map(|index| SYSCALLS[index].1)
+}
+
+/// The number of the x86-64 system call called `name`.
+#[must_use]
+pub fn syscall_number(name: &str) -> Option<u64> {
+    SYSCALLS
+        .iter()
+        .find(|(_, syscall)| *syscall == name)
+        .map(|(number, _)| *number)
+}
+
+/// Which system calls stop the inferior.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SyscallCatch {
+    #[default]
+    Nothing,
+    Any,
+    Only(std::collections::BTreeSet<u64>),
+}
+
+impl SyscallCatch {
+    #[must_use]
+    pub fn catches(&self, number: u64) -> bool {
+        match self {
+            Self::Nothing => false,
+            Self::Any => true,
+            Self::Only(numbers) => numbers.contains(&number),
+        }
+    }
+}
+
+/// Where in a system call a thread stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallStop {
+    /// About to enter the kernel with these arguments.
+    Entry { number: u64, args: [u64; 6] },
+    /// Back from the kernel. A failure is returned as a negated errno.
+    Exit { number: u64, return_value: i64 },
+}
+
+impl SyscallStop {
+    #[must_use]
+    pub const fn number(&self) -> u64 {
+        match self {
+            Self::Entry { number, .. } | Self::Exit { number, .. } => *number,
+        }
+    }
+}
+
+/// Find out where in a system call `pid`, which is in a syscall-stop, is.
+pub fn syscall_stop(pid: nix::unistd::Pid) -> Result<SyscallStop, DebuggerError> {
+    // nix's wrapper passes a zero buffer size, which the kernel honours by copying nothing.
+    let mut info = std::mem::MaybeUninit::<nix::libc::ptrace_syscall_info>::zeroed();
+    // SAFETY: the kernel writes at most the given size into `info`.
+    let res = unsafe {
+        nix::libc::ptrace(
+            nix::libc::PTRACE_GET_SYSCALL_INFO,
+            pid.as_raw(),
+            std::mem::size_of::<nix::libc::ptrace_syscall_info>(),
+            info.as_mut_ptr(),
+        )
+    };
+    nix::errno::Errno::result(res)?;
+    // SAFETY: zeroed is a valid ptrace_syscall_info, and the kernel only filled it in further.
+    let info = unsafe { info.assume_init() };
+    match info.op {
+        nix::libc::PTRACE_SYSCALL_INFO_ENTRY => {
+            // SAFETY: the kernel fills in the entry member for entry stops.
+            let entry = unsafe { info.u.entry };
+            Ok(SyscallStop::Entry {
+                number: entry.nr,
+                args: entry.args,
+            })
+        }
+        nix::libc::PTRACE_SYSCALL_INFO_EXIT => {
+            // SAFETY: the kernel fills in the exit member for exit stops.
+            let exit = unsafe { info.u.exit };
+            // The exit info does not repeat the number, but orig_rax still holds it.
+            let number = nix::sys::ptrace::getregs(pid)?.orig_rax;
+            Ok(SyscallStop::Exit {
+                number,
+                return_value: exit.sval,
+            })
+        }
+        op => Err(DebuggerError::ErrorMessage(format!(
+            "Process {pid} is not at a syscall entry or exit (op {op})"
+        ))),
+    }
+}
+
+/// How to render one syscall argument.
+#[derive(Debug, Clone, Copy)]
+enum ArgKind {
+    Int,
+    Unsigned,
+    Hex,
+    Fd,
+    /// A directory fd for the `*at` calls, which may be `AT_FDCWD`.
+    DirFd,
+    Address,
+    /// A NUL-terminated string in the inferior.
+    Path,
+    /// A buffer whose length is the argument at the given index.
+    Buffer(usize),
+    OpenFlags,
+    Mode,
+    Protection,
+    MapFlags,
+}
+
+/// How to render a syscall's return value, besides failures which always show the errno.
+#[derive(Debug, Clone, Copy)]
+enum ReturnKind {
+    Int,
+    Address,
+}
+
+struct SyscallDescriptor {
+    name: &'static str,
+    args: &'static [ArgKind],
+    returns: ReturnKind,
+}
+
+const fn descriptor(
+    name: &'static str,
+    args: &'static [ArgKind],
+    returns: ReturnKind,
+) -> SyscallDescriptor {
+    SyscallDescriptor {
+        name,
+        args,
+        returns,
+    }
+}
+
+/// The syscalls worth decoding; anything else is shown as raw registers.
+const DESCRIPTORS: &[SyscallDescriptor] = {
+    use ArgKind::{
+        Address, Buffer, DirFd, Fd, Hex, Int, MapFlags, Mode, OpenFlags, Path, Protection, Unsigned,
+    };
+    &[
+        descriptor("read", &[Fd, Address, Unsigned], ReturnKind::Int),
+        descriptor("write", &[Fd, Buffer(2), Unsigned], ReturnKind::Int),
+        descriptor("pread64", &[Fd, Address, Unsigned, Int], ReturnKind::Int),
+        descriptor("pwrite64", &[Fd, Buffer(2), Unsigned, Int], ReturnKind::Int),
+        descriptor("open", &[Path, OpenFlags, Mode], ReturnKind::Int),
+        descriptor("openat", &[DirFd, Path, OpenFlags, Mode], ReturnKind::Int),
+        descriptor("close", &[Fd], ReturnKind::Int),
+        descriptor("stat", &[Path, Address], ReturnKind::Int),
+        descriptor("lstat", &[Path, Address], ReturnKind::Int),
+        descriptor("fstat", &[Fd, Address], ReturnKind::Int),
+        descriptor("newfstatat", &[DirFd, Path, Address, Hex], ReturnKind::Int),
+        descriptor("lseek", &[Fd, Int, Int], ReturnKind::Int),
+        descriptor("ioctl", &[Fd, Hex, Hex], ReturnKind::Int),
+        descriptor("access", &[Path, Int], ReturnKind::Int),
+        descriptor(
+            "mmap",
+            &[Address, Unsigned, Protection, MapFlags, Fd, Hex],
+            ReturnKind::Address,
+        ),
+        descriptor(
+            "mprotect",
+            &[Address, Unsigned, Protection],
+            ReturnKind::Int,
+        ),
+        descriptor("munmap", &[Address, Unsigned], ReturnKind::Int),
+        descriptor("brk", &[Address], ReturnKind::Address),
+        descriptor("execve", &[Path, Address, Address], ReturnKind::Int),
+        descriptor("exit", &[Int], ReturnKind::Int),
+        descriptor("exit_group", &[Int], ReturnKind::Int),
+    ]
+};
+
+fn find_descriptor(number: u64) -> Option<&'static SyscallDescriptor> {
+    let name = syscall_name(number)?;
+    DESCRIPTORS
+        .iter()
+        .find(|descriptor| descriptor.name == name)
+}
+
+/// How much of a string or buffer argument is shown.
+const MAX_SHOWN_BYTES: usize = 32;
+
+fn read_c_string(pid: nix::unistd::Pid, address: u64) -> Result<String, DebuggerError> {
+    let mut bytes = crate::memory::read_c_string(pid, address, MAX_SHOWN_BYTES + 1)?;
+    let truncated = bytes.len() > MAX_SHOWN_BYTES;
+    bytes.truncate(MAX_SHOWN_BYTES);
+    Ok(quote_bytes(&bytes, truncated))
+}
+
+/// Render the set bits of `value` by name, with whatever is left over in hex.
+fn format_flags(value: u64, names: &[(u64, &str)], mut parts: Vec<String>) -> String {
+    let mut rest = value;
+    for &(bit, name) in names {
+        if bit != 0 && rest & bit == bit {
+            parts.push(name.to_string());
+            rest &= !bit;
+        }
+    }
+    if rest != 0 || parts.is_empty() {
+        parts.push(format!("{rest:#x}"));
+    }
+    parts.join("|")
+}
+
+fn flag(value: nix::libc::c_int) -> u64 {
+    u64::from(value.cast_unsigned())
+}
+
+fn format_open_flags(value: u64) -> String {
+    use nix::libc::{
+        O_ACCMODE, O_APPEND, O_CLOEXEC, O_CREAT, O_DIRECTORY, O_DSYNC, O_EXCL, O_NOCTTY,
+        O_NOFOLLOW, O_NONBLOCK, O_PATH, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY,
+    };
+    let access = match value & flag(O_ACCMODE) {
+        mode if mode == flag(O_RDONLY) => "O_RDONLY",
+        mode if mode == flag(O_WRONLY) => "O_WRONLY",
+        mode if mode == flag(O_RDWR) => "O_RDWR",
+        _ => "O_ACCMODE",
+    };
+    format_flags(
+        value & !flag(O_ACCMODE),
+        &[
+            (flag(O_CREAT), "O_CREAT"),
+            (flag(O_EXCL), "O_EXCL"),
+            (flag(O_NOCTTY), "O_NOCTTY"),
+            (flag(O_TRUNC), "O_TRUNC"),
+            (flag(O_APPEND), "O_APPEND"),
+            (flag(O_NONBLOCK), "O_NONBLOCK"),
+            (flag(O_DSYNC), "O_DSYNC"),
+            (flag(O_DIRECTORY), "O_DIRECTORY"),
+            (flag(O_NOFOLLOW), "O_NOFOLLOW"),
+            (flag(O_CLOEXEC), "O_CLOEXEC"),
+            (flag(O_PATH), "O_PATH"),
+        ],
+        vec![access.to_string()],
+    )
+}
+
+fn format_protection(value: u64) -> String {
+    use nix::libc::{PROT_EXEC, PROT_READ, PROT_WRITE};
+    if value == 0 {
+        return "PROT_NONE".to_string();
+    }
+    format_flags(
+        value,
+        &[
+            (flag(PROT_READ), "PROT_READ"),
+            (flag(PROT_WRITE), "PROT_WRITE"),
+            (flag(PROT_EXEC), "PROT_EXEC"),
+        ],
+        Vec::new(),
+    )
+}
+
+fn format_map_flags(value: u64) -> String {
+    use nix::libc::{
+        MAP_ANONYMOUS, MAP_DENYWRITE, MAP_FIXED, MAP_FIXED_NOREPLACE, MAP_GROWSDOWN, MAP_NORESERVE,
+        MAP_POPULATE, MAP_PRIVATE, MAP_SHARED, MAP_STACK,
+    };
+    format_flags(
+        value,
+        &[
+            (flag(MAP_SHARED), "MAP_SHARED"),
+            (flag(MAP_PRIVATE), "MAP_PRIVATE"),
+            (flag(MAP_FIXED), "MAP_FIXED"),
+            (flag(MAP_ANONYMOUS), "MAP_ANONYMOUS"),
+            (flag(MAP_GROWSDOWN), "MAP_GROWSDOWN"),
+            (flag(MAP_DENYWRITE), "MAP_DENYWRITE"),
+            (flag(MAP_NORESERVE), "MAP_NORESERVE"),
+            (flag(MAP_POPULATE), "MAP_POPULATE"),
+            (flag(MAP_STACK), "MAP_STACK"),
+            (flag(MAP_FIXED_NOREPLACE), "MAP_FIXED_NOREPLACE"),
+        ],
+        Vec::new(),
+    )
+}
+
+fn format_argument(pid: nix::unistd::Pid, kind: ArgKind, value: u64, args: &[u64; 6]) -> String {
+    // Registers hold 64 bits; narrower C types only use the low ones.
+    let int = (value & 0xffff_ffff).cast_signed() << 32 >> 32;
+    match kind {
+        ArgKind::DirFd if int == i64::from(nix::libc::AT_FDCWD) => "AT_FDCWD".to_string(),
+        ArgKind::Int | ArgKind::Fd | ArgKind::DirFd => int.to_string(),
+        ArgKind::Unsigned => value.to_string(),
+        ArgKind::Address | ArgKind::Path if value == 0 => "NULL".to_string(),
+        ArgKind::Hex | ArgKind::Address => format!("{value:#x}"),
+        ArgKind::Path => read_c_string(pid, value).unwrap_or_else(|_| format!("{value:#x}")),
+        ArgKind::Buffer(len_index) => {
+            let len = usize::try_from(args[len_index]).unwrap_or(usize::MAX);
+            crate::memory::read_memory(pid, value, len.min(MAX_SHOWN_BYTES)).map_or_else(
+                |_| format!("{value:#x}"),
+                |bytes| quote_bytes(&bytes, len > MAX_SHOWN_BYTES),
+            )
+        }
+        ArgKind::OpenFlags => format_open_flags(value),
+        ArgKind::Mode => format!("0{value:o}"),
+        ArgKind::Protection => format_protection(value),
+        ArgKind::MapFlags => format_map_flags(value),
+    }
+}
+
+/// Render the arguments of system call `number` made by `pid`.
+///
+/// `pid` must still be stopped at the syscall entry so pointed-to strings can be read.
+/// Syscalls we know nothing about get all six argument registers in hex.
+#[must_use]
+pub fn decode_syscall_arguments(
+    pid: nix::unistd::Pid,
+    number: u64,
+    args: &[u64; 6],
+) -> Vec<String> {
+    find_descriptor(number).map_or_else(
+        || args.iter().map(|arg| format!("{arg:#x}")).collect(),
+        |descriptor| {
+            descriptor
+                .args
+                .iter()
+                .zip(args)
+                .map(|(kind, value)| format_argument(pid, *kind, *value, args))
+                .collect()
+        },
+    )
+}
+
+/// Render the return value of system call `number`, naming the errno of failures.
+#[must_use]
+pub fn decode_syscall_return(number: u64, return_value: i64) -> String {
+    // The kernel reports failures as -errno, in the range it reserves for them.
+    if (-4095..0).contains(&return_value) {
+        let errno = nix::errno::Errno::from_raw(i32::try_from(-return_value).unwrap_or_default());
+        return format!("-1 {errno:?} ({})", errno.desc());
+    }
+    match find_descriptor(number).map(|descriptor| descriptor.returns) {
+        Some(ReturnKind::Address) => format!("{:#x}", return_value.cast_unsigned()),
+        Some(ReturnKind::Int) | None => return_value.to_string(),
+    }
+}
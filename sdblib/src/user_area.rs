@@ -0,0 +1,59 @@
+//! Access to the `struct user` the kernel exposes through `PTRACE_PEEKUSER`/`POKEUSER`.
+//!
+//! Offsets are byte offsets into `struct user` from `<sys/user.h>`, and must be word aligned.
+//! On x86-64 it starts with the general purpose registers (`user_regs_struct`, 27 words, so
+//! `fs_base` is at 21 * 8) and ends with `u_debugreg[8]`; `DRn` lives at
+//! `offsetof(struct user, u_debugreg) + n * 8`, which is 848 for DR0.
+
+use crate::DebuggerError;
+use nix::libc::{c_long, user, user_regs_struct};
+
+const WORD_SIZE: usize = std::mem::size_of::<c_long>();
+
+/// The offset of debug register `DR<index>` in the user area. `DR4` and `DR5` are aliases the
+/// kernel refuses to write, but they still have a slot.
+/// # Errors
+///
+/// Will return `Err` if there is no such debug register.
+pub fn debug_register_offset(index: usize) -> Result<u64, DebuggerError> {
+    if index >= 8 {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "There is no debug register DR{index}"
+        )));
+    }
+    Ok((std::mem::offset_of!(user, u_debugreg) + index * WORD_SIZE) as u64)
+}
+
+/// The offset of `fs_base`, the base of the thread-local storage segment.
+#[must_use]
+pub const fn fs_base_offset() -> u64 {
+    (std::mem::offset_of!(user, regs) + std::mem::offset_of!(user_regs_struct, fs_base)) as u64
+}
+
+/// The offset of `gs_base`.
+#[must_use]
+pub const fn gs_base_offset() -> u64 {
+    (std::mem::offset_of!(user, regs) + std::mem::offset_of!(user_regs_struct, gs_base)) as u64
+}
+
+fn check_offset(offset: u64) -> Result<nix::sys::ptrace::AddressType, DebuggerError> {
+    let valid = usize::try_from(offset).is_ok_and(|offset| {
+        offset % WORD_SIZE == 0 && offset + WORD_SIZE <= std::mem::size_of::<user>()
+    });
+    if !valid {
+        return Err(DebuggerError::ErrorMessage(format!(
+            "{offset:#x} is not a word of the user area"
+        )));
+    }
+    Ok(offset as nix::sys::ptrace::AddressType)
+}
+
+pub fn read_user(pid: nix::unistd::Pid, offset: u64) -> Result<u64, DebuggerError> {
+    let word = nix::sys::ptrace::read_user(pid, check_offset(offset)?)?;
+    Ok(word.cast_unsigned())
+}
+
+pub fn write_user(pid: nix::unistd::Pid, offset: u64, value: u64) -> Result<(), DebuggerError> {
+    nix::sys::ptrace::write_user(pid, check_offset(offset)?, value.cast_signed())?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use crate::DebuggerError;
+use crate::memory::read_memory;
+use crate::user_area::{debug_register_offset, read_user, write_user};
+
+/// How many addresses the debug registers can watch at once: DR0 to DR3.
+pub const HARDWARE_SLOTS: usize = 4;
+
+/// Which accesses to the watched memory stop the inferior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Writes that change the value.
+    Write,
+    /// Reads. x86 cannot trap on reads alone, so writes are caught too and told apart by
+    /// whether the value changed.
+    Read,
+    /// Reads and writes.
+    Access,
+}
+
+impl WatchKind {
+    /// DR7's encoding of the condition: `01` breaks on writes, `11` on reads and writes.
+    const fn condition(self) -> u64 {
+        match self {
+            Self::Write => 0b01,
+            Self::Read | Self::Access => 0b11,
+        }
+    }
+}
+
+/// What keeps an eye on the watched memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// One of the debug registers, which trap on the write itself.
+    Hardware { slot: usize },
+    /// Single stepping the inferior and comparing the memory after every instruction.
+    Software,
+}
+
+/// A watchpoint on `length` bytes at `address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Shares its numbering with breakpoints.
+    pub id: usize,
+    pub pid: nix::unistd::Pid,
+    /// What the user asked to watch, as they wrote it.
+    pub expression: String,
+    pub address: u64,
+    pub length: usize,
+    pub kind: WatchKind,
+    pub backend: WatchBackend,
+    /// How many times it stopped the inferior.
+    pub hit_count: usize,
+    old_value: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl Watchpoint {
+    pub(crate) fn new(
+        id: usize,
+        pid: nix::unistd::Pid,
+        expression: String,
+        address: u64,
+        length: usize,
+        kind: WatchKind,
+        backend: WatchBackend,
+    ) -> Result<Self, DebuggerError> {
+        let value = read_memory(pid, address, length)?;
+        Ok(Self {
+            id,
+            pid,
+            expression,
+            address,
+            length,
+            kind,
+            backend,
+            hit_count: 0,
+            old_value: value.clone(),
+            value,
+        })
+    }
+
+    /// The watched bytes before the last time they were looked at.
+    #[must_use]
+    pub fn old_value(&self) -> &[u8] {
+        &self.old_value
+    }
+
+    /// The watched bytes as last seen.
+    #[must_use]
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Read the watched memory again, and tell whether it changed since last time.
+    pub(crate) fn refresh(&mut self) -> Result<bool, DebuggerError> {
+        let value = read_memory(self.pid, self.address, self.length)?;
+        self.old_value = std::mem::replace(&mut self.value, value);
+        Ok(self.old_value != self.value)
+    }
+
+    /// Whether an access that left the memory `changed` or not is one to stop for.
+    pub(crate) const fn stops_for(&self, changed: bool) -> bool {
+        match self.kind {
+            WatchKind::Write => changed,
+            WatchKind::Read => !changed,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+/// What a `SIGTRAP` meant for the hardware watchpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trap {
+    /// No debug register caused it.
+    Unrelated,
+    /// A debug register caused it, but not with an access its watchpoint stops for, like a
+    /// write of the value already there.
+    Ignored,
+    /// The watchpoint with this id stops the inferior.
+    Hit(usize),
+}
+
+/// Whether the debug registers can watch `length` bytes at `address`: they only take 1, 2, 4
+/// or 8 bytes, aligned to their length.
+pub const fn hardware_can_watch(address: u64, length: usize) -> bool {
+    matches!(length, 1 | 2 | 4 | 8) && address.is_multiple_of(length as u64)
+}
+
+/// DR7's encoding of a watched length.
+const fn length_bits(length: usize) -> u64 {
+    match length {
+        1 => 0b00,
+        2 => 0b01,
+        8 => 0b10,
+        _ => 0b11,
+    }
+}
+
+/// Program debug register `slot` of thread `tid` to trap on `kind` accesses to the watched
+/// memory.
+pub fn arm(
+    tid: nix::unistd::Pid,
+    slot: usize,
+    watchpoint: &Watchpoint,
+) -> Result<(), DebuggerError> {
+    write_user(tid, debug_register_offset(slot)?, watchpoint.address)?;
+    let shift = 16 + 4 * slot;
+    let mut dr7 = read_user(tid, debug_register_offset(7)?)?;
+    dr7 &= !(0b1111 << shift);
+    dr7 |= (watchpoint.kind.condition() | length_bits(watchpoint.length) << 2) << shift;
+    // The local enable bit.
+    dr7 |= 1 << (2 * slot);
+    write_user(tid, debug_register_offset(7)?, dr7)
+}
+
+/// Free debug register `slot` of thread `tid`, clearing its enable, condition and length bits
+/// so a later watchpoint starts from a clean slot.
+pub fn disarm(tid: nix::unistd::Pid, slot: usize) -> Result<(), DebuggerError> {
+    let mut dr7 = read_user(tid, debug_register_offset(7)?)?;
+    dr7 &= !(0b1111 << (16 + 4 * slot));
+    dr7 &= !(0b11 << (2 * slot));
+    write_user(tid, debug_register_offset(7)?, dr7)?;
+    write_user(tid, debug_register_offset(slot)?, 0)
+}
+
+/// Which debug registers made `tid` trap, according to DR6, clearing them for the next time.
+pub fn triggered_slots(tid: nix::unistd::Pid) -> Result<Vec<usize>, DebuggerError> {
+    let dr6_offset = debug_register_offset(6)?;
+    let dr6 = read_user(tid, dr6_offset)?;
+    let slots = (0..HARDWARE_SLOTS)
+        .filter(|slot| dr6 & (1 << slot) != 0)
+        .collect::<Vec<_>>();
+    if !slots.is_empty() {
+        write_user(tid, dr6_offset, 0)?;
+    }
+    Ok(slots)
+}
@@ -0,0 +1,74 @@
+//! Tiny inferiors for the tests: C programs compiled at test time into the test's scratch
+//! directory, and ways to drive a [`Debugger`] to a known place in them.
+
+use sdblib::{Debugger, StopReason};
+
+/// Tests share one process, and the debugger reaps whichever child changes state first, so
+/// only one test may have inferiors at a time.
+static TRACEE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+pub fn lock_tracees() -> std::sync::MutexGuard<'static, ()> {
+    TRACEE_LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Compile `source` into the test's scratch directory, with `flags` and then through
+/// `strip`, if given.
+pub fn compile_fixture(
+    name: &str,
+    source: &str,
+    flags: &[&str],
+    strip: Option<&str>,
+) -> std::path::PathBuf {
+    let source_path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.c"));
+    std::fs::write(&source_path, source).unwrap();
+    let binary = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let status = std::process::Command::new("cc")
+        .args(["-g", "-O0", "-fno-omit-frame-pointer", "-o"])
+        .arg(&binary)
+        .args(flags)
+        .arg(&source_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    if let Some(option) = strip {
+        let status = std::process::Command::new("strip")
+            .arg(option)
+            .arg(&binary)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    binary
+}
+
+/// Launch `binary` and run it to `main`, past the dynamic loader and the C runtime's start
+/// up. Returns the program's output and its PID.
+pub fn run_to_main(
+    debugger: &mut Debugger,
+    binary: &std::path::Path,
+) -> (std::process::ChildStdout, sdblib::Pid) {
+    let stdout = debugger
+        .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+        .unwrap();
+    let main = debugger.resolve_function("main").unwrap();
+    debugger.set_breakpoint(main).unwrap();
+    let reason = debugger.continue_all().unwrap();
+    let StopReason::Breakpoint(pid, _) = reason else {
+        panic!("expected to stop at main, got {reason:?}");
+    };
+    (stdout, pid)
+}
+
+/// The address `binary`'s symbol table gives `symbol`. Fixtures built with `-no-pie` are loaded
+/// right there, which makes for known addresses to check against.
+pub fn symbol_address(binary: &std::path::Path, symbol: &str) -> u64 {
+    use object::{Object, ObjectSymbol};
+    let data = std::fs::read(binary).unwrap();
+    let file = object::File::parse(data.as_slice()).unwrap();
+    file.symbols()
+        .find(|candidate| candidate.name() == Ok(symbol))
+        .unwrap_or_else(|| panic!("{} has no symbol {symbol}", binary.display()))
+        .address()
+}
@@ -1,9 +1,1697 @@
-#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod fixtures;
+
+#[cfg(all(test, target_os = "linux"))]
 mod tests {
+    use crate::fixtures::{compile_fixture, lock_tracees, run_to_main, symbol_address};
+    use sdblib::{Debugger, Signal, SignalReport, StopReason, SyscallStop};
 
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn symbol_names_are_demangled_or_passed_through() {
+        assert_eq!(
+            sdblib::demangle("_ZN3std2rt10lang_start17h0123456789abcdefE"),
+            "std::rt::lang_start"
+        );
+        assert_eq!(
+            sdblib::demangle("_RNvNtCs1234_7mycrate3foo3bar"),
+            "mycrate::foo::bar"
+        );
+        assert_eq!(sdblib::demangle("main"), "main");
+    }
+
+    #[test]
+    fn continue_all_reports_exit() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        debugger.add_program("/bin/sh", ["-c", "exit 3"]).unwrap();
+        assert_eq!(debugger.exit_status(), None);
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 3)
+        ));
+        assert_eq!(
+            debugger.exit_status().map(sdblib::ExitStatus::code),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn attaching_to_ourselves_or_init_is_refused() {
+        let mut debugger = Debugger::new();
+        for pid in [u64::from(std::process::id()), 1, 0] {
+            assert!(
+                matches!(
+                    debugger.add_proc(pid),
+                    Err(sdblib::DebuggerError::ErrorMessage(message)) if message.starts_with("Refusing")
+                ),
+                "{pid}"
+            );
+        }
+        // Outside of containers, PID 2 is the kernel thread that starts the others.
+        if std::fs::read_to_string("/proc/2/comm").is_ok_and(|comm| comm.trim() == "kthreadd") {
+            assert!(matches!(
+                debugger.add_proc(2),
+                Err(sdblib::DebuggerError::ErrorMessage(message)) if message.ends_with("kernel thread.")
+            ));
+        }
+        assert!(!debugger.has_process());
+    }
+
+    #[test]
+    fn segfaults_report_the_faulting_address() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture(
+            "segfault",
+            "int main(void) {\n  volatile int *p = (int *)0x10;\n  return *p;\n}\n",
+            &[],
+            None,
+        );
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let reason = debugger.continue_all().unwrap();
+        let StopReason::Signaled(pid, Signal::SIGSEGV) = reason else {
+            panic!("{reason:?}");
+        };
+        let info = debugger.signal_info(pid).unwrap();
+        assert_eq!(sdblib::fault_address(&info), Some(0x10));
+        assert_eq!(
+            sdblib::describe_si_code(Signal::SIGSEGV, info.si_code),
+            Some("Address not mapped to object")
+        );
+    }
+
+    #[test]
+    fn killed_programs_exit_with_128_plus_the_signal() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        debugger
+            .add_program("/bin/sh", ["-c", "kill -KILL $$"])
+            .unwrap();
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Killed(_, Signal::SIGKILL)
+        ));
+        assert_eq!(
+            debugger.exit_status().map(sdblib::ExitStatus::code),
+            Some(137)
+        );
+    }
+
+    /// `sleep seconds`, once it is done with `execve` and asleep. Attached before that, it
+    /// would stop for the exec rather than for the attach.
+    fn spawn_sleeping(seconds: u32) -> std::process::Child {
+        let child = std::process::Command::new("/bin/sleep")
+            .arg(seconds.to_string())
+            .spawn()
+            .unwrap();
+        let pid = sdblib::Pid::from_raw(child.id().cast_signed());
+        let task = pid.to_string();
+        while std::fs::read_to_string(format!("/proc/{pid}/comm")).unwrap() != "sleep\n"
+            || thread_state(pid, task.as_ref()) != 'S'
+        {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        child
+    }
+
+    fn tracer_of(pid: u64) -> String {
+        std::fs::read_to_string(format!("/proc/{pid}/status"))
+            .unwrap()
+            .lines()
+            .find_map(|line| line.strip_prefix("TracerPid:"))
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn launched_programs_run_without_address_randomization_unless_asked() {
+        let _guard = lock_tracees();
+        let randomized = |debugger: &mut Debugger| {
+            let _stdout = debugger.add_program("/bin/sleep", ["30"]).unwrap();
+            let pid = debugger.current_process().unwrap();
+            let persona = std::fs::read_to_string(format!("/proc/{pid}/personality")).unwrap();
+            let persona = u64::from_str_radix(persona.trim(), 16).unwrap();
+            debugger.teardown();
+            persona & 0x0040000 == 0
+        };
+        let mut debugger = Debugger::new();
+        assert!(debugger.disable_randomization());
+        assert!(!randomized(&mut debugger));
+        debugger.set_disable_randomization(false);
+        assert!(randomized(&mut debugger));
+    }
+
+    #[test]
+    fn teardown_detaches_attached_processes_and_follows_the_exit_policy() {
+        let _guard = lock_tracees();
+        let mut attached = spawn_sleeping(30);
+        let pid = sdblib::Pid::from_raw(attached.id().cast_signed());
+        let task = pid.to_string();
+        let mut debugger = Debugger::new();
+        debugger.add_proc(u64::from(attached.id())).unwrap();
+        assert_ne!(tracer_of(u64::from(attached.id())), "0");
+        drop(debugger);
+        assert_eq!(tracer_of(u64::from(attached.id())), "0");
+        assert_eq!(attached.try_wait().unwrap(), None, "still running");
+        assert!(
+            !matches!(thread_state(pid, task.as_ref()), 'T' | 't'),
+            "left stopped"
+        );
+        attached.kill().unwrap();
+        attached.wait().unwrap();
+
+        let mut debugger = Debugger::new();
+        let _stdout = debugger.add_program("/bin/sleep", ["30"]).unwrap();
+        let launched = debugger.current_process().unwrap();
+        assert_eq!(debugger.exit_policy(), sdblib::ExitPolicy::Kill);
+        debugger.set_exit_policy(sdblib::ExitPolicy::Detach);
+        debugger.teardown();
+        assert!(!debugger.has_process());
+        assert_eq!(tracer_of(launched.as_raw().unsigned_abs().into()), "0");
+        unsafe { libc::kill(launched.as_raw(), libc::SIGKILL) };
+        let mut status = 0;
+        assert_eq!(
+            unsafe { libc::waitpid(launched.as_raw(), &raw mut status, 0) },
+            launched.as_raw()
+        );
+        assert!(libc::WIFSIGNALED(status));
+    }
+
+    #[test]
+    fn process_times_tell_spinning_from_sleeping() {
+        let mut sleeping = spawn_sleeping(1);
+        let pid = sdblib::Pid::from_raw(sleeping.id().cast_signed());
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let times = sdblib::process_times(pid).unwrap();
+        assert!(
+            times.elapsed >= std::time::Duration::from_millis(200),
+            "{times:?}"
+        );
+        assert!(
+            times.elapsed < std::time::Duration::from_secs(60),
+            "{times:?}"
+        );
+        assert!(
+            times.cpu() < std::time::Duration::from_millis(100),
+            "{times:?}"
+        );
+        sleeping.kill().unwrap();
+        sleeping.wait().unwrap();
+        assert!(sdblib::process_times(pid).is_err());
+
+        let start = sdblib::process_times(sdblib::Pid::this()).unwrap();
+        let spin_until = std::time::Instant::now() + std::time::Duration::from_millis(200);
+        while std::time::Instant::now() < spin_until {
+            std::hint::spin_loop();
+        }
+        let spun = sdblib::process_times(sdblib::Pid::this()).unwrap();
+        assert!(spun.cpu() >= start.cpu() + std::time::Duration::from_millis(100));
+    }
+
+    #[test]
+    fn attaching_to_a_stopped_process_takes_it_over() {
+        let _guard = lock_tracees();
+        let mut stopped = spawn_sleeping(1);
+        let pid = sdblib::Pid::from_raw(stopped.id().cast_signed());
+        let task = pid.to_string();
+        unsafe { libc::kill(pid.as_raw(), libc::SIGSTOP) };
+        // As its parent we would hear of the stop; a debugger attaching to it would not.
+        let mut status = 0;
+        unsafe { libc::waitpid(pid.as_raw(), &raw mut status, libc::WUNTRACED) };
+        assert_eq!(thread_state(pid, task.as_ref()), 'T');
+        assert_eq!(
+            sdblib::process_state(pid).unwrap(),
+            sdblib::ProcessState::Stopped
+        );
+        let mut debugger = Debugger::new();
+        debugger.add_proc(u64::from(stopped.id())).unwrap();
+        debugger.wait().unwrap();
+        // The `SIGSTOP` attaching sends does not come as a stop of its own.
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+        // Reaped by the debugger already.
+        stopped.wait().unwrap_err();
+    }
+
+    #[test]
+    fn attached_processes_are_interrupted_without_a_signal() {
+        let _guard = lock_tracees();
+        let mut sleeping = spawn_sleeping(30);
+        let pid = sdblib::Pid::from_raw(sleeping.id().cast_signed());
+        let mut debugger = Debugger::attach(u64::from(sleeping.id())).unwrap();
+        // Interrupting a stopped thread stops it again as soon as it is resumed.
+        debugger.interrupt(pid).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Interrupted(pid)
+        );
+        assert!(debugger.take_resumed_signals().is_empty());
+        assert!(
+            debugger
+                .interrupt(sdblib::Pid::from_raw(pid.as_raw() + 1))
+                .is_err()
+        );
+        drop(debugger);
+        sleeping.kill().unwrap();
+        sleeping.wait().unwrap();
+    }
+
+    #[test]
+    fn continuing_the_current_process_leaves_the_others_stopped() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let _first_stdout = debugger.add_program("/bin/true", ["first"]).unwrap();
+        let first = debugger.current_process().unwrap();
+        let _second_stdout = debugger.add_program("/bin/true", ["second"]).unwrap();
+        let second = debugger.current_process().unwrap();
+        assert_eq!(
+            debugger.continue_current().unwrap(),
+            StopReason::Exited(second, 0)
+        );
+        let task = first.to_string();
+        assert_eq!(thread_state(first, task.as_ref()), 't');
+        assert_eq!(debugger.current_process().unwrap(), first);
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(first, 0)
+        );
+    }
+
+    #[test]
+    fn launch_and_attach_make_a_debugger_for_one_process() {
+        let _guard = lock_tracees();
+        let (mut debugger, stdout) = Debugger::launch("/bin/echo", ["hi"]).unwrap();
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+        assert_eq!(std::io::read_to_string(stdout).unwrap(), "hi\n");
+
+        let mut sleeping = spawn_sleeping(30);
+        let pid = sdblib::Pid::from_raw(sleeping.id().cast_signed());
+        let task = pid.to_string();
+        let debugger = Debugger::attach(u64::from(sleeping.id())).unwrap();
+        assert_eq!(debugger.current_process().unwrap(), pid);
+        assert_eq!(debugger.current_thread().unwrap(), pid);
+        assert_eq!(thread_state(pid, task.as_ref()), 't');
+        drop(debugger);
+        sleeping.kill().unwrap();
+        sleeping.wait().unwrap();
+
+        assert!(Debugger::attach(1).is_err());
+    }
+
+    #[test]
+    fn attaching_twice_is_refused_and_changes_nothing() {
+        let _guard = lock_tracees();
+        let mut sleeping = spawn_sleeping(30);
+        let pid = sdblib::Pid::from_raw(sleeping.id().cast_signed());
+        let mut debugger = Debugger::attach(u64::from(sleeping.id())).unwrap();
+        let _stdout = debugger
+            .add_program("/bin/true", std::iter::empty::<&str>())
+            .unwrap();
+        let launched = debugger.current_process().unwrap();
+        assert!(matches!(
+            debugger.add_proc(u64::from(sleeping.id())),
+            Err(sdblib::DebuggerError::ErrorMessage(message))
+                if message == format!("Already attached to process {pid}.")
+        ));
+        // Still the last one added, and both are let go of exactly once.
+        assert_eq!(debugger.current_process().unwrap(), launched);
+        drop(debugger);
+        assert_eq!(tracer_of(u64::from(sleeping.id())), "0");
+        assert_eq!(sleeping.try_wait().unwrap(), None, "still running");
+        sleeping.kill().unwrap();
+        sleeping.wait().unwrap();
+    }
+
+    #[test]
+    fn nothing_is_read_from_a_process_until_it_stops() {
+        let _guard = lock_tracees();
+        let mut sleeping = spawn_sleeping(30);
+        let pid = sdblib::Pid::from_raw(sleeping.id().cast_signed());
+        let mut debugger = Debugger::new();
+        debugger.add_proc(u64::from(sleeping.id())).unwrap();
+        assert!(!debugger.is_stopped(pid));
+        let running = |error: sdblib::DebuggerError| {
+            assert!(
+                matches!(&error, sdblib::DebuggerError::ErrorMessage(message)
+                    if message == "Cannot do this while the process is running."),
+                "{error}"
+            );
+        };
+        running(debugger.registers().unwrap_err());
+        running(debugger.read_memory(0x1000, 8).unwrap_err());
+        running(debugger.set_breakpoint(0x1000).unwrap_err());
+        running(debugger.continue_all().unwrap_err());
+        debugger.wait().unwrap();
+        assert!(debugger.is_stopped(pid));
+        assert!(debugger.registers().is_ok());
+        drop(debugger);
+        assert!(!Debugger::new().is_stopped(pid));
+        sleeping.kill().unwrap();
+        sleeping.wait().unwrap();
+    }
+
+    #[test]
+    fn breakpoints_go_in_executable_memory_only() {
+        let _guard = lock_tracees();
+        let (mut debugger, _stdout) =
+            Debugger::launch("/bin/true", std::iter::empty::<&str>()).unwrap();
+        let refused = |result: Result<usize, sdblib::DebuggerError>, reason: &str| {
+            assert!(
+                matches!(&result, Err(sdblib::DebuggerError::ErrorMessage(message))
+                    if message.ends_with(reason)),
+                "{result:?}"
+            );
+        };
+        refused(debugger.set_breakpoint(0x10), "the address is not mapped.");
+        let stack = debugger.register("rsp").unwrap().value;
+        refused(debugger.set_breakpoint(stack), "[stack] is not executable.");
+        assert!(debugger.breakpoints().is_empty());
+        let entry = debugger.program_counter().unwrap();
+        assert_eq!(debugger.set_breakpoint(entry).unwrap(), 1);
+    }
+
+    #[test]
+    fn commands_without_a_program_say_none_is_being_debugged() {
+        let mut debugger = Debugger::new();
+        let no_program = |result: Result<(), sdblib::DebuggerError>| {
+            assert!(matches!(
+                result,
+                Err(sdblib::DebuggerError::ErrorMessage(message))
+                    if message == "No program is being debugged."
+            ));
+        };
+        no_program(debugger.current_process().map(drop));
+        no_program(debugger.registers().map(drop));
+        no_program(debugger.backtrace(1).map(drop));
+        no_program(debugger.step_instructions(1).map(drop));
+        no_program(debugger.continue_all().map(drop));
+    }
+
+    #[test]
+    fn commands_after_exit_say_the_program_is_not_being_run() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program("/bin/true", std::iter::empty::<&str>())
+            .unwrap();
+        let pid = debugger.current_process().unwrap();
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+        let not_running = |result: Result<(), sdblib::DebuggerError>| {
+            let message = result.unwrap_err().to_string();
+            assert!(
+                message.contains("The program is not being run."),
+                "{message}"
+            );
+        };
+        not_running(debugger.require_live(pid));
+        not_running(debugger.registers().map(drop));
+        not_running(debugger.program_counter().map(drop));
+        not_running(debugger.set_register("rax", 0));
+        not_running(debugger.backtrace(1).map(drop));
+        not_running(debugger.memory_map().map(drop));
+        not_running(debugger.read_user(pid, 0).map(drop));
+        not_running(debugger.step_instructions(1).map(drop));
+        not_running(debugger.source_step(pid).map(drop));
+        not_running(debugger.step_syscall(pid).map(drop));
+        not_running(debugger.continue_all().map(drop));
+    }
+
+    #[test]
+    fn continue_all_stops_for_signals_then_delivers_them() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        debugger
+            .add_program("/bin/sh", ["-c", "kill -USR1 $$; exit 0"])
+            .unwrap();
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Signaled(_, Signal::SIGUSR1)
+        ));
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Killed(_, Signal::SIGUSR1)
+        ));
+    }
+
+    #[test]
+    fn resumed_signals_say_whether_they_were_delivered() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        debugger
+            .add_program(
+                "/bin/sh",
+                ["-c", "trap '' USR1; kill -USR1 $$; kill -USR1 $$; exit 3"],
+            )
+            .unwrap();
+        let StopReason::Signaled(pid, Signal::SIGUSR1) = debugger.continue_all().unwrap() else {
+            panic!("expected a stop for SIGUSR1");
+        };
+        assert!(debugger.take_resumed_signals().is_empty());
+        let mut disposition = debugger.signal_disposition(Signal::SIGUSR1);
+        disposition.pass = false;
+        debugger.set_signal_disposition(Signal::SIGUSR1, disposition);
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Signaled(pid, Signal::SIGUSR1)
+        );
+        // The disposition counts at the time the signal arrived.
+        assert_eq!(
+            debugger.take_resumed_signals(),
+            [SignalReport {
+                pid,
+                signal: Signal::SIGUSR1,
+                passed: true,
+            }]
+        );
+        assert_eq!(debugger.continue_all().unwrap(), StopReason::Exited(pid, 3));
+        assert_eq!(
+            debugger.take_resumed_signals(),
+            [SignalReport {
+                pid,
+                signal: Signal::SIGUSR1,
+                passed: false,
+            }]
+        );
+    }
+
+    /// Not a test on its own: the inferior of `all_threads_stop_together`, which runs it by
+    /// re-executing this binary.
+    #[test]
+    #[ignore = "inferior of all_threads_stop_together"]
+    fn two_threads_fixture() {
+        // The tracer sees the signal anyway; once passed on it should not kill us.
+        unsafe { libc::signal(libc::SIGUSR2, libc::SIG_IGN) };
+        let sleeper = std::thread::spawn(|| std::thread::sleep(std::time::Duration::from_secs(1)));
+        let signaller = std::thread::spawn(|| unsafe { libc::raise(libc::SIGUSR2) });
+        signaller.join().unwrap();
+        sleeper.join().unwrap();
+    }
+
+    fn thread_state(pid: sdblib::Pid, tid: &std::ffi::OsStr) -> char {
+        let stat =
+            std::fs::read_to_string(format!("/proc/{pid}/task/{}/stat", tid.to_string_lossy()))
+                .unwrap();
+        // The command name may contain spaces or parentheses, the state follows the last ')'.
+        stat[stat.rfind(')').unwrap() + 1..]
+            .trim_start()
+            .chars()
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn all_threads_stop_together() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let exe = std::env::current_exe().unwrap();
+        // Keep the pipe open, the harness in the inferior prints to it.
+        let _stdout = debugger
+            .add_program(
+                exe.to_str().unwrap(),
+                [
+                    "--exact",
+                    "tests::two_threads_fixture",
+                    "--ignored",
+                    "--test-threads=1",
+                    "--quiet",
+                ],
+            )
+            .unwrap();
+        let reason = debugger.continue_all().unwrap();
+        let StopReason::Signaled(tid, Signal::SIGUSR2) = reason else {
+            panic!("expected a SIGUSR2 stop, got {reason:?}");
+        };
+        let tasks = std::fs::read_dir(format!("/proc/{tid}/task"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert!(tasks.len() >= 3, "{tasks:?}");
+        for task in &tasks {
+            assert_eq!(
+                thread_state(tid, task),
+                't',
+                "thread {task:?} is not stopped"
+            );
+        }
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    #[test]
+    fn caught_syscalls_stop_at_entry_and_exit() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let _stdout = debugger.add_program("/bin/sh", ["-c", "echo hi"]).unwrap();
+        debugger.catch_syscall(sdblib::syscall_number("write"));
+        let reason = debugger.continue_all().unwrap();
+        let StopReason::Syscall(pid, SyscallStop::Entry { number: 1, args }) = reason else {
+            panic!("expected to enter write, got {reason:?}");
+        };
+        assert_eq!(
+            sdblib::decode_syscall_arguments(pid, 1, &args),
+            ["1", "\"hi\\n\"", "3"]
+        );
+        assert_eq!(
+            debugger.step_syscall(pid).unwrap(),
+            StopReason::Syscall(
+                pid,
+                SyscallStop::Exit {
+                    number: 1,
+                    return_value: 3
+                }
+            )
+        );
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    const SYSCALL_AND_BREAKPOINT_SOURCE: &str = "#include <unistd.h>
+void marker(void) {}
+long raw_getpid(void);
+__asm__(\".globl raw_getpid\\n\"
+        \".type raw_getpid, @function\\n\"
+        \"raw_getpid:\\n\"
+        \"    mov $39, %eax\\n\"
+        \"    syscall\\n\"
+        \"    ret\\n\");
+
+int main(void)
+{
+    write(1, \"a\\n\", 2);
+    marker();
+    raw_getpid();
+    return 0;
+}
+";
+
+    #[test]
+    fn caught_syscalls_and_breakpoints_both_stop() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture(
+            "syscall-breakpoint",
+            SYSCALL_AND_BREAKPOINT_SOURCE,
+            &[],
+            None,
+        );
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let marker = debugger.resolve_function("marker").unwrap();
+        let at_marker = debugger.set_breakpoint(marker).unwrap();
+        // Right on the `syscall` instruction, past the 5 bytes of the `mov`.
+        let syscall = debugger.resolve_function("raw_getpid").unwrap() + 5;
+        let at_syscall = debugger.set_breakpoint(syscall).unwrap();
+        let write = sdblib::syscall_number("write").unwrap();
+        let getpid = sdblib::syscall_number("getpid").unwrap();
+        debugger.catch_syscall(Some(write));
+        debugger.catch_syscall(Some(getpid));
+        let mut stops = Vec::new();
+        loop {
+            match debugger.continue_all().unwrap() {
+                StopReason::Syscall(tid, stop) => {
+                    assert_eq!(tid, pid);
+                    let name = sdblib::syscall_name(stop.number()).unwrap();
+                    stops.push(if matches!(stop, SyscallStop::Entry { .. }) {
+                        format!("enter {name}")
+                    } else {
+                        format!("leave {name}")
+                    });
+                }
+                StopReason::Breakpoint(tid, id) => {
+                    assert_eq!(tid, pid);
+                    stops.push(if id == at_marker { "marker" } else { "syscall" }.to_string());
+                    assert!(id == at_marker || id == at_syscall);
+                }
+                StopReason::Exited(_, 0) => break,
+                reason => panic!("unexpected stop {reason:?}"),
+            }
+        }
+        // Stepping off a breakpoint on `syscall` still stops for the call it makes.
+        assert_eq!(
+            stops,
+            [
+                "enter write",
+                "leave write",
+                "marker",
+                "syscall",
+                "enter getpid",
+                "leave getpid"
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_syscalls_name_their_errno() {
+        let open = sdblib::syscall_number("open").unwrap();
+        assert_eq!(
+            sdblib::decode_syscall_return(open, -2),
+            "-1 ENOENT (No such file or directory)"
+        );
+        let mmap = sdblib::syscall_number("mmap").unwrap();
+        assert_eq!(
+            sdblib::decode_syscall_return(mmap, 0x7f00_0000_0000),
+            "0x7f0000000000"
+        );
+    }
+
+    #[test]
+    fn rflags_decode_into_flag_names() {
+        assert_eq!(sdblib::decode_rflags(0x246), ["PF", "ZF", "IF"]);
+        assert_eq!(
+            sdblib::decode_rflags(0xfd5),
+            ["CF", "PF", "AF", "ZF", "SF", "TF", "IF", "DF", "OF"]
+        );
+        assert!(sdblib::decode_rflags(0x2).is_empty());
+    }
+
+    #[test]
+    fn registers_are_read_and_written_through_their_aliases() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program("/bin/true", std::iter::empty::<&str>())
+            .unwrap();
+        let pc = debugger.register("$pc").unwrap();
+        assert_eq!(pc.name, "rip");
+        assert_eq!(pc.value, debugger.program_counter().unwrap());
+        assert_eq!(debugger.register("sp").unwrap().name, "rsp");
+        assert_eq!(debugger.register("$fp").unwrap().name, "rbp");
+        assert_eq!(debugger.register("eflags").unwrap().name, "rflags");
+        debugger.set_register("$rax", 0x1234).unwrap();
+        assert_eq!(debugger.register("rax").unwrap().value, 0x1234);
+        assert!(debugger.register("$xyz").is_err());
+        assert!(
+            debugger
+                .registers()
+                .unwrap()
+                .iter()
+                .all(|register| sdblib::is_register_name(register.name))
+        );
+        assert!(sdblib::is_register_name("$pc") && !sdblib::is_register_name("$_"));
+    }
+
+    #[test]
+    fn auxv_names_the_entry_point_and_executable() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("auxv", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let auxv = sdblib::read_auxv(debugger.current_process().unwrap()).unwrap();
+        let value_of = |name| {
+            auxv.iter()
+                .find(|entry| entry.name() == Some(name))
+                .unwrap()
+                .value
+        };
+        // Launching stops in the dynamic loader, which AT_BASE locates.
+        let interpreter = value_of("AT_BASE");
+        let pc = debugger.program_counter().unwrap();
+        assert!(pc > interpreter, "{pc:#x} {interpreter:#x}");
+        let entry = value_of("AT_ENTRY");
+        assert_eq!(
+            debugger.symbolize(entry).unwrap().to_string(),
+            "_start",
+            "{entry:#x}"
+        );
+        let execfn = debugger.read_c_string(value_of("AT_EXECFN"), 4096).unwrap();
+        assert_eq!(execfn, binary.to_str().unwrap().as_bytes());
+        assert_eq!(value_of("AT_PAGESZ"), 4096);
+    }
+
+    #[test]
+    fn memory_search_finds_patterns_across_chunks() {
+        let _guard = lock_tracees();
+        let source = "__attribute__((aligned(4096))) char haystack[3 * 4096] = {
+    [100] = 'n', 'e', 'e', 'd', 'l', 'e',
+    [4094] = 'n', 'e', 'e', 'd', 'l', 'e',
+    [8000] = 'n', 'e', 'e', 'd', 'l', 'e',
+};
+int main(void) { return haystack[0]; }
+";
+        let binary = compile_fixture("find", source, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let pid = debugger.current_process().unwrap();
+        let haystack = debugger
+            .global_variables()
+            .unwrap()
+            .into_iter()
+            .find(|global| global.name == "haystack")
+            .unwrap()
+            .address
+            .unwrap();
+        let range = haystack..haystack + 3 * 4096;
+        assert_eq!(
+            debugger
+                .search_memory(pid, range.clone(), b"needle", 10)
+                .unwrap(),
+            [haystack + 100, haystack + 4094, haystack + 8000]
+        );
+        assert_eq!(
+            debugger.search_memory(pid, range, b"needle", 2).unwrap(),
+            [haystack + 100, haystack + 4094]
+        );
+        assert!(
+            debugger
+                .search_memory(pid, haystack..haystack + 4098, b"needle", 10)
+                .unwrap()
+                .len()
+                == 1,
+            "a match must fit in the range"
+        );
+    }
+
+    #[test]
+    fn debug_registers_round_trip_through_the_user_area() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program("/bin/true", std::iter::empty::<&str>())
+            .unwrap();
+        // Stopping at the first syscall is the simplest way to learn the PID.
+        debugger.catch_syscall(None);
+        let reason = debugger.continue_all().unwrap();
+        let StopReason::Syscall(pid, _) = reason else {
+            panic!("expected a syscall stop, got {reason:?}");
+        };
+        assert_eq!(sdblib::debug_register_offset(0).unwrap(), 848);
+        assert_eq!(sdblib::fs_base_offset(), 21 * 8);
+        let dr0 = sdblib::debug_register_offset(0).unwrap();
+        let dr7 = sdblib::debug_register_offset(7).unwrap();
+        assert_eq!(debugger.read_user(pid, dr7).unwrap(), 0);
+        debugger.write_user(pid, dr0, 0x1000).unwrap();
+        assert_eq!(debugger.read_user(pid, dr0).unwrap(), 0x1000);
+        assert!(debugger.read_user(pid, dr0 + 1).is_err());
+        assert!(sdblib::debug_register_offset(8).is_err());
+    }
+
+    #[test]
+    fn waiting_after_exit_is_not_an_error() {
+        let _guard = lock_tracees();
+        let mut debugger = Debugger::new();
+        debugger.add_program("/bin/sh", ["-c", "exit 0"]).unwrap();
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+        debugger.wait().unwrap();
+        let error = debugger.continue_all().unwrap_err();
+        assert!(
+            matches!(&error, sdblib::DebuggerError::ErrorMessage(message) if message == "The program is not being run."),
+            "{error:?}"
+        );
+    }
+
+    /// A program whose `main` calls `target` once and exits with 0.
+    const BREAKPOINT_SOURCE: &str =
+        "int target(int x) { return x * 2; }\nint main(void) { return target(21) - 42; }\n";
+
+    #[test]
+    fn breakpoints_resolve_with_or_without_debug_info() {
+        let _guard = lock_tracees();
+        for (name, flags, strip) in [
+            ("breakpoint-dwarf", &[][..], None),
+            ("breakpoint-symtab", &[][..], Some("--strip-debug")),
+            ("breakpoint-dynsym", &["-rdynamic"][..], Some("--strip-all")),
+        ] {
+            let binary = compile_fixture(name, BREAKPOINT_SOURCE, flags, strip);
+            let mut debugger = Debugger::new();
+            let _stdout = debugger
+                .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+                .unwrap();
+            let address = debugger.resolve_function("target").unwrap();
+            let id = debugger.set_breakpoint(address).unwrap();
+            assert!(
+                matches!(debugger.continue_all().unwrap(), StopReason::Breakpoint(_, hit) if hit == id),
+                "{name}"
+            );
+            assert_eq!(debugger.program_counter().unwrap(), address, "{name}");
+            assert_eq!(
+                debugger.symbolize(address).unwrap().to_string(),
+                "target",
+                "{name}"
+            );
+            assert!(
+                matches!(debugger.continue_all().unwrap(), StopReason::Exited(_, 0)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_pie_fixtures_run_at_their_link_addresses() {
+        let _guard = lock_tracees();
+        let source = "int answer = 42;\nint main(void) { return answer - 42; }\n";
+        let binary = compile_fixture("no-pie", source, &["-no-pie"], None);
+        let main = symbol_address(&binary, "main");
+        let answer = symbol_address(&binary, "answer");
+        let mut debugger = Debugger::new();
+        let (_stdout, _) = run_to_main(&mut debugger, &binary);
+        assert_eq!(debugger.program_counter().unwrap(), main);
+        assert_eq!(debugger.resolve_function("main").unwrap(), main);
+        assert_eq!(
+            debugger.read_memory(answer, 4).unwrap(),
+            42_i32.to_le_bytes()
+        );
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    const HOT_LOOP_SOURCE: &str = "volatile int total;
+void add(int i) { total += i; }
+int main(void) {
+    for (int i = 0; i < 500; i++)
+        add(i);
+    return total == 124750 ? 0 : 1;
+}
+";
+
+    #[test]
+    fn a_breakpoint_in_a_loop_is_stepped_off_every_time() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("hot-loop", HOT_LOOP_SOURCE, &["-no-pie"], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let add = debugger
+            .set_breakpoint(symbol_address(&binary, "add"))
+            .unwrap();
+        for _ in 0..500 {
+            assert_eq!(
+                debugger.continue_all().unwrap(),
+                StopReason::Breakpoint(pid, add)
+            );
+        }
+        assert_eq!(debugger.continue_all().unwrap(), StopReason::Exited(pid, 0));
+    }
+
+    #[test]
+    fn breakpoints_are_set_again_after_a_relaunch() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("breakpoint-relaunch", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let id = debugger.set_function_breakpoint("target", 0).unwrap();
+        let missing = debugger.set_breakpoint(0x10).map(drop);
+        assert!(missing.is_err(), "nothing is mapped at 0x10");
+        for _ in 0..2 {
+            assert!(matches!(
+                debugger.continue_all().unwrap(),
+                StopReason::Breakpoint(_, hit) if hit == id
+            ));
+            let rearmed = debugger.relaunch().unwrap();
+            assert_eq!(rearmed.count, 1);
+            assert!(rearmed.failures.is_empty(), "{:?}", rearmed.failures);
+        }
+    }
+
+    #[test]
+    fn breakpoints_set_on_a_loaded_program_are_set_when_it_starts() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("breakpoint-file", BREAKPOINT_SOURCE, &[], None);
+        let stripped = compile_fixture(
+            "breakpoint-file-stripped",
+            BREAKPOINT_SOURCE,
+            &[],
+            Some("--strip-debug"),
+        );
+        let mut debugger = Debugger::new();
+        assert!(debugger.set_function_breakpoint("target", 0).is_err());
+        let file = debugger.load_program(stripped.to_str().unwrap()).unwrap();
+        assert!(!file.has_debug_info);
+        let file = debugger.load_program(binary.to_str().unwrap()).unwrap();
+        assert!(file.has_debug_info);
+        assert_eq!(file.entry_point, symbol_address(&binary, "_start"));
+        assert!(!debugger.has_process());
+        let missing = debugger.set_function_breakpoint("missing", 0).unwrap();
+        let id = debugger.set_function_breakpoint("target", 0).unwrap();
+        let rearmed = debugger.relaunch().unwrap();
+        assert_eq!(rearmed.count, 1);
+        assert!(rearmed.failures.is_empty(), "{:?}", rearmed.failures);
+        let pending = debugger
+            .pending_breakpoints()
+            .into_iter()
+            .map(|(id, ..)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(pending, [missing]);
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(_, hit) if hit == id
+        ));
+        assert_eq!(
+            debugger.program_counter().unwrap(),
+            debugger.resolve_function("target").unwrap()
+        );
+    }
+
+    const PENDING_BREAKPOINT_SOURCE: &str =
+        "#include <stdio.h>\nint main(void)\n{\n    puts(\"loaded\");\n    return 0;\n}\n";
+
+    #[test]
+    fn breakpoints_pending_on_a_library_function_are_set_once_it_is_loaded() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("breakpoint-pending", PENDING_BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        // Only the loader is mapped at the entry point.
+        let id = debugger.set_function_breakpoint("puts", 0).unwrap();
+        let past_the_end = debugger.set_function_breakpoint("puts", u64::MAX).unwrap();
+        assert_eq!(debugger.pending_breakpoints().len(), 2);
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(_, hit) if hit == id
+        ));
+        let puts = debugger.resolve_function("puts").unwrap();
+        assert_eq!(debugger.program_counter().unwrap(), puts);
+        let activated = debugger.take_activated_breakpoints();
+        assert_eq!(activated.len(), 1);
+        assert_eq!((activated[0].id, activated[0].address), (id, puts));
+        let failed = debugger.take_failed_breakpoints();
+        assert!(
+            matches!(&failed[..], [(failed, _, sdblib::DebuggerError::ErrorMessage(message))]
+                if *failed == past_the_end && message.contains("out of range")),
+            "{failed:?}"
+        );
+        assert!(debugger.pending_breakpoints().is_empty());
+        assert!(
+            debugger
+                .breakpoints()
+                .iter()
+                .all(sdblib::Breakpoint::is_user_breakpoint),
+            "the loader hook is gone once nothing is pending"
+        );
+    }
+
+    #[test]
+    fn disabled_breakpoints_are_kept_but_not_hit() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("breakpoint-disable", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let target = debugger.resolve_function("target").unwrap();
+        let original = debugger.read_memory(target, 1).unwrap();
+        let id = debugger.set_function_breakpoint("target", 0).unwrap();
+        let shared = debugger.set_breakpoint(target).unwrap();
+        assert!(debugger.disable_breakpoints(Some(42)).is_err());
+        debugger.disable_breakpoints(Some(id)).unwrap();
+        assert_eq!(
+            debugger.read_memory(target, 1).unwrap(),
+            [0xcc],
+            "{shared} is enabled"
+        );
+        debugger.disable_breakpoints(None).unwrap();
+        assert_eq!(debugger.read_memory(target, 1).unwrap(), original);
+        assert!(debugger.breakpoints().iter().all(|b| !b.enabled));
+        debugger.enable_breakpoints(Some(id)).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, id)
+        );
+        // Disabled, it stays so in the relaunched process.
+        debugger.disable_breakpoints(Some(id)).unwrap();
+        debugger.relaunch().unwrap();
+        assert!(debugger.breakpoints().iter().all(|b| !b.enabled));
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    #[test]
+    fn running_to_a_function_goes_through_the_loader_and_leaves_no_breakpoint() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("run-to-main", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        assert_eq!(debugger.run_to_function("main").unwrap(), None);
+        assert_eq!(
+            debugger.program_counter().unwrap(),
+            debugger.resolve_function("main").unwrap()
+        );
+        assert!(debugger.breakpoints().is_empty());
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    #[test]
+    fn functions_are_listed_once_by_address() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("functions", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let functions = debugger.functions().unwrap();
+        assert!(functions.is_sorted_by_key(|function| function.address));
+        let targets = functions
+            .iter()
+            .filter(|function| function.name == "target")
+            .collect::<Vec<_>>();
+        assert_eq!(targets.len(), 1, "{targets:?}");
+        assert_eq!(
+            targets[0].address,
+            debugger.resolve_function("target").unwrap()
+        );
+    }
+
+    #[test]
+    fn globals_are_readable_without_a_frame() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture(
+            "globals",
+            "int counter = 42;\n__thread int per_thread = 7;\nint main(void) { return counter + per_thread - 49; }\n",
+            &[],
+            None,
+        );
+        let mut debugger = Debugger::new();
+        let _stdout = debugger
+            .add_program(binary.to_str().unwrap(), std::iter::empty::<&str>())
+            .unwrap();
+        let globals = debugger.global_variables().unwrap();
+        let counter = globals
+            .iter()
+            .find(|global| global.name == "counter")
+            .unwrap();
+        assert_eq!(counter.type_name, "int");
+        assert!(counter.address.is_some());
+        let per_thread = globals
+            .iter()
+            .find(|global| global.name == "per_thread")
+            .unwrap();
+        assert_eq!(per_thread.address, None);
+
+        // Still stopped at the exec, long before main has a frame.
+        assert_eq!(
+            debugger.global_value("counter").unwrap().value,
+            Some(sdblib::Value::Signed(42))
+        );
+        assert!(debugger.global_value("per_thread").is_err());
+        assert!(debugger.global_value("missing").is_err());
+    }
+
+    const SET_VARIABLE_SOURCE: &str = "short narrow = 1;\ndouble ratio = 0.5;\nint check(int x)\n{\n    return x + narrow;\n}\nint main(void)\n{\n    return check(3);\n}\n";
+
+    #[test]
+    fn variables_are_set_where_dwarf_says_they_live() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("set-variable", SET_VARIABLE_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let id = debugger.set_function_breakpoint("check", 0).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, id)
+        );
+        // Past the prologue, where `x` is on the stack.
+        debugger.source_step(pid).unwrap();
+        debugger.set_variable("x", 40).unwrap();
+        let x = debugger
+            .arguments()
+            .unwrap()
+            .into_iter()
+            .find(|argument| argument.name == "x")
+            .unwrap();
+        assert_eq!(x.value, Some(sdblib::Value::Signed(40)));
+        // Too wide for a short, so only the low bytes are kept.
+        debugger.set_variable("narrow", 0x1_0002).unwrap();
+        assert_eq!(
+            debugger.global_value("narrow").unwrap().value,
+            Some(sdblib::Value::Signed(2))
+        );
+        let error = debugger.set_variable("ratio", 1).unwrap_err();
+        assert!(error.to_string().contains("double"), "{error}");
+        assert!(debugger.set_variable("missing", 1).is_err());
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(pid, 42)
+        );
+    }
+
+    /// A program writing `counter` 1, 2 and 3, then 3 again, and leaving `others` alone.
+    const WATCH_SOURCE: &str = "int counter;\nlong others[4];\nint main(void) {\n  for (int i = 0; i < 3; i++)\n    counter += 1;\n  counter = 3;\n  return counter - 3;\n}\n";
+
+    #[test]
+    fn stepping_instructions_stops_early_at_breakpoints() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("stepi", BREAKPOINT_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let main = debugger.program_counter().unwrap();
+        let stepped = debugger.step_instructions(2).unwrap();
+        assert_eq!(stepped.steps, 2);
+        assert_eq!(stepped.reason, None);
+        assert_ne!(debugger.program_counter().unwrap(), main);
+        let target = debugger.set_function_breakpoint("target", 0).unwrap();
+        let stepped = debugger.step_instructions(1000).unwrap();
+        assert!(stepped.steps < 1000);
+        assert_eq!(stepped.reason, Some(StopReason::Breakpoint(pid, target)));
+        assert_eq!(
+            debugger.program_counter().unwrap(),
+            debugger.resolve_function("target").unwrap()
+        );
+        // Stepping off the breakpoint executes the instruction it covers.
+        assert_eq!(debugger.step_instructions(1).unwrap().steps, 1);
+    }
+
+    const NEXTI_SOURCE: &str = "char buffer[4096];
+int twice(int x) { return x * 2; }
+int main(void)
+{
+    int result;
+    __asm__ volatile(
+        \"lea buffer(%%rip), %%rdi\\n\"
+        \"mov $4096, %%ecx\\n\"
+        \"mov $0x5a, %%eax\\n\"
+        \".globl fill\\nfill:\\n\"
+        \"rep stosb\\n\"
+        \".globl filled\\nfilled:\\n\"
+        \"mov $21, %%edi\\n\"
+        \".globl call_twice\\ncall_twice:\\n\"
+        \"call twice\\n\"
+        \".globl called\\ncalled:\\n\"
+        : \"=a\"(result)
+        :
+        : \"rcx\", \"rdx\", \"rsi\", \"rdi\", \"r8\", \"r9\", \"r10\", \"r11\", \"memory\");
+    return result - 42;
+}
+";
+
+    const LARGE_READ_SOURCE: &str = "#include <sys/mman.h>
+unsigned char pattern[65536];
+unsigned char *guarded;
+void filled(void) {}
+int main(void) {
+    for (int i = 0; i < (int)sizeof pattern; i++)
+        pattern[i] = (unsigned char)(i * 7);
+    guarded = mmap(0, 8192, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    for (int i = 0; i < 8192; i++)
+        guarded[i] = (unsigned char)i;
+    mprotect(guarded + 4096, 4096, PROT_NONE);
+    filled();
+    return 0;
+}
+";
+
+    #[test]
+    fn large_reads_take_one_system_call() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("large-read", LARGE_READ_SOURCE, &["-no-pie"], None);
+        let address = |symbol| symbol_address(&binary, symbol);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let filled = debugger.set_breakpoint(address("filled")).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, filled)
+        );
+        let before = debugger.word_by_word_reads();
+        let pattern = debugger.read_memory(address("pattern"), 65536).unwrap();
+        assert!(
+            pattern
+                .iter()
+                .enumerate()
+                .all(|(i, byte)| *byte == (i * 7) as u8)
+        );
+        assert_eq!(debugger.word_by_word_reads(), before);
+
+        // Only ptrace reads the page the inferior made inaccessible.
+        let guarded = debugger.read_memory(address("guarded"), 8).unwrap();
+        let guarded = u64::from_le_bytes(guarded.try_into().unwrap());
+        let bytes = debugger.read_memory(guarded + 4000, 200).unwrap();
+        assert!(
+            bytes
+                .iter()
+                .enumerate()
+                .all(|(i, byte)| *byte == (4000 + i) as u8)
+        );
+        assert_eq!(debugger.word_by_word_reads(), before + 1);
+    }
+
+    const SINGLE_STEP_SOURCE: &str = "int main(void) {
+    __asm__ volatile(\".globl before\\nbefore:\\nnop\\n\"
+                     \".globl trap\\ntrap:\\nint3\\n\"
+                     \".globl after\\nafter:\\nnop\\n\"
+                     \".globl landing\\nlanding:\\nnop\\n\");
+    return 0;
+}
+";
+
+    #[test]
+    fn single_steps_tell_steps_from_traps_and_breakpoints() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("single-step", SINGLE_STEP_SOURCE, &["-no-pie"], None);
+        let address = |symbol| symbol_address(&binary, symbol);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let before = debugger.set_breakpoint(address("before")).unwrap();
+        let landing = debugger.set_breakpoint(address("landing")).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, before)
+        );
+        // Off the breakpoint it sat on, without hitting it again.
+        assert_eq!(debugger.single_step(pid).unwrap(), StopReason::Stepped(pid));
+        assert_eq!(debugger.program_counter().unwrap(), address("trap"));
+        // The program's own `int3` is not the end of the step, but a signal.
+        let mut disposition = debugger.signal_disposition(Signal::SIGTRAP);
+        disposition.stop = true;
+        debugger.set_signal_disposition(Signal::SIGTRAP, disposition);
+        assert_eq!(
+            debugger.single_step(pid).unwrap(),
+            StopReason::Signaled(pid, Signal::SIGTRAP)
+        );
+        assert_eq!(debugger.program_counter().unwrap(), address("after"));
+        assert_eq!(
+            debugger.single_step(pid).unwrap(),
+            StopReason::Breakpoint(pid, landing)
+        );
+        assert_eq!(debugger.program_counter().unwrap(), address("landing"));
+        assert_eq!(debugger.current_thread().unwrap(), pid);
+    }
+
+    #[test]
+    fn next_instruction_runs_through_calls_and_rep_strings() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("nexti", NEXTI_SOURCE, &["-no-pie"], None);
+        let address = |symbol| symbol_address(&binary, symbol);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let fill = debugger.set_breakpoint(address("fill")).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, fill)
+        );
+        // Sitting on a breakpoint, the repetitions are stepped through rather than run.
+        let stepped = debugger.next_instructions(1).unwrap();
+        assert_eq!((stepped.steps, stepped.reason), (1, None));
+        assert_eq!(debugger.program_counter().unwrap(), address("filled"));
+        assert_eq!(debugger.register("rcx").unwrap().value, 0);
+        let buffer = debugger.read_memory(address("buffer"), 4096).unwrap();
+        assert!(buffer.iter().all(|byte| *byte == 0x5a));
+
+        debugger.step_instructions(1).unwrap();
+        assert_eq!(debugger.program_counter().unwrap(), address("call_twice"));
+        assert_eq!(debugger.next_instructions(1).unwrap().reason, None);
+        assert_eq!(debugger.program_counter().unwrap(), address("called"));
+        assert_eq!(debugger.register("rax").unwrap().value, 42);
+        assert!(
+            debugger
+                .breakpoints()
+                .iter()
+                .all(|breakpoint| breakpoint.id != 0)
+        );
+
+        // Elsewhere, the whole loop runs at once to a temporary breakpoint.
+        debugger.disable_breakpoints(Some(fill)).unwrap();
+        debugger.set_register("rip", address("fill")).unwrap();
+        debugger.set_register("rcx", 16).unwrap();
+        debugger.set_register("rdi", address("buffer")).unwrap();
+        assert_eq!(debugger.next_instructions(1).unwrap().reason, None);
+        assert_eq!(debugger.program_counter().unwrap(), address("filled"));
+        assert_eq!(
+            debugger.register("rdi").unwrap().value,
+            address("buffer") + 16
+        );
+    }
+
+    const STEP_SOURCE: &str = "#include <stdio.h>
+int twice(int x)
+{
+    int doubled = x * 2;
+    return doubled;
+}
+
+int main(void)
+{
+    int value = twice(21);
+    puts(\"stepped\");
+    value -= 42;
+    return value;
+}
+";
+
+    #[test]
+    fn source_steps_go_into_calls_with_line_information_only() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("step", STEP_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let mut lines = Vec::new();
+        for _ in 0..7 {
+            let stepped = debugger.source_step(pid).unwrap();
+            assert_eq!(stepped.reason, None);
+            let location = debugger
+                .source_location(debugger.program_counter().unwrap())
+                .unwrap();
+            assert_eq!(location.file.file_name().unwrap(), "step.c");
+            lines.push(location.line);
+        }
+        // Into `twice` past its prologue, back to the line after the call, and over `puts`.
+        assert_eq!(lines, [10, 4, 5, 6, 11, 12, 13]);
+    }
+
+    #[test]
+    fn lines_resolve_to_where_their_code_starts() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("step", STEP_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let file = debugger
+            .source_location(debugger.program_counter().unwrap())
+            .unwrap()
+            .file;
+        let address = debugger.line_address(&file, 12).unwrap();
+        let id = debugger.set_breakpoint(address).unwrap();
+        assert_eq!(
+            debugger.continue_all().unwrap(),
+            StopReason::Breakpoint(pid, id)
+        );
+        let location = debugger.source_location(address).unwrap();
+        assert_eq!(location.line, 12);
+        // A line with no code of its own stands for the next one that has.
+        let blank = debugger.line_address(&file, 7).unwrap();
+        assert!(debugger.source_location(blank).unwrap().line > 7);
+        let error = debugger.line_address(&file, 99).unwrap_err();
+        assert!(error.to_string().contains("No line 99"), "{error}");
+    }
+
+    const STUCK_SOURCE: &str = "int stuck(int x) {
+    volatile int spin = 1;
+    while (spin) {
+    }
+    return x;
+}
+int main(void) {
+    return stuck(5);
+}
+";
+
+    #[test]
+    fn returning_early_pops_the_frame_with_a_value() {
+        let _guard = lock_tracees();
+        for (name, flags) in [
+            ("stuck", &[][..]),
+            (
+                "stuck-no-frame-pointer",
+                &[
+                    "-O2",
+                    "-fomit-frame-pointer",
+                    "-fno-inline",
+                    "-fno-optimize-sibling-calls",
+                ],
+            ),
+        ] {
+            let binary = compile_fixture(name, STUCK_SOURCE, flags, None);
+            // At the very start of the function, before its prologue, and once past it.
+            for steps in [0, 3] {
+                let mut debugger = Debugger::new();
+                let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+                let id = debugger.set_function_breakpoint("stuck", 0).unwrap();
+                assert_eq!(
+                    debugger.continue_all().unwrap(),
+                    StopReason::Breakpoint(pid, id)
+                );
+                for _ in 0..steps {
+                    debugger.single_step(pid).unwrap();
+                }
+                let caller = debugger.return_from_frame(Some(42)).unwrap();
+                let symbol = caller.symbol.unwrap();
+                assert_eq!(symbol.function, "main", "{name} after {steps} steps");
+                assert_eq!(
+                    debugger.continue_all().unwrap(),
+                    StopReason::Exited(pid, 42),
+                    "{name} after {steps} steps"
+                );
+            }
+        }
+    }
+
+    const THREAD_NAMES_SOURCE: &str = "#define _GNU_SOURCE
+#include <pthread.h>
+void named(void) {}
+static void *worker(void *arg) {
+    pthread_setname_np(pthread_self(), \"worker\");
+    named();
+    return arg;
+}
+int main(void) {
+    pthread_t thread;
+    pthread_create(&thread, 0, worker, 0);
+    pthread_join(thread, 0);
+    return 0;
+}
+";
+
+    #[test]
+    fn threads_are_listed_with_their_names() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("thread-names", THREAD_NAMES_SOURCE, &["-pthread"], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let named = debugger.set_function_breakpoint("named", 0).unwrap();
+        let StopReason::Breakpoint(worker, id) = debugger.continue_all().unwrap() else {
+            panic!("expected the worker to reach its breakpoint");
+        };
+        assert_eq!(id, named);
+        let threads = debugger.threads().unwrap();
+        let names = threads
+            .iter()
+            .map(|thread| (thread.tid, thread.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [(pid, "thread-names"), (worker, "worker")]);
+        let address = debugger.resolve_function("named").unwrap();
+        assert_eq!(threads[1].pc, Some(address));
+        assert!(threads[0].pc.is_some());
+    }
+
+    #[test]
+    fn symbol_files_are_read_once() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("symbol-cache", STEP_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, _) = run_to_main(&mut debugger, &binary);
+        let functions = debugger.functions().unwrap();
+        let loads = debugger.symbol_file_loads();
+        assert!(loads > 0);
+        for _ in 0..3 {
+            assert_eq!(debugger.functions().unwrap(), functions);
+            let twice = debugger.resolve_function("twice").unwrap();
+            assert_eq!(debugger.symbolize(twice).unwrap().function, "twice");
+            assert_eq!(debugger.source_location(twice).unwrap().line, 3);
+        }
+        assert_eq!(debugger.symbol_file_loads(), loads);
+    }
+
+    #[test]
+    fn changing_the_program_forgets_the_old_files() {
+        let binary = compile_fixture("symbol-cache-old", STEP_SOURCE, &[], None);
+        let other = compile_fixture("symbol-cache-new", STEP_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let program = binary.to_str().unwrap();
+        debugger.load_program(program).unwrap();
+        debugger.load_program(program).unwrap();
+        assert_eq!(debugger.symbol_file_loads(), 1);
+        debugger.load_program(other.to_str().unwrap()).unwrap();
+        debugger.load_program(program).unwrap();
+        assert_eq!(debugger.symbol_file_loads(), 3);
+    }
+
+    #[test]
+    fn backtraces_say_where_each_frame_is() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("backtrace", STEP_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        // To the call, then past the prologue of `twice`, once its frame pointer is set up.
+        for _ in 0..2 {
+            assert_eq!(debugger.source_step(pid).unwrap().reason, None);
+        }
+        let backtrace = debugger.backtrace(2).unwrap();
+        let [inner, outer] = &backtrace.frames[..] else {
+            panic!("expected two frames, got {backtrace:?}");
+        };
+        assert_eq!((inner.index, outer.index), (0, 1));
+        assert_eq!(inner.pc, debugger.program_counter().unwrap());
+        let symbol = inner.symbol.as_ref().unwrap();
+        assert_eq!(symbol.function, "twice");
+        assert_eq!(
+            symbol.offset,
+            inner.pc - debugger.resolve_function("twice").unwrap()
+        );
+        assert_eq!(inner.source.as_ref().unwrap().line, 4);
+        // The caller is told by the call it made, not by where it resumes.
+        assert_eq!(outer.symbol.as_ref().unwrap().function, "main");
+        let source = outer.source.as_ref().unwrap();
+        assert_eq!(source.file.file_name().unwrap(), "backtrace.c");
+        assert_eq!(source.line, 10);
+        assert!(outer.stack_pointer > inner.stack_pointer);
+    }
+
+    fn watched_int(watchpoint: &sdblib::Watchpoint) -> (i32, i32) {
+        (
+            i32::from_le_bytes(watchpoint.old_value().try_into().unwrap()),
+            i32::from_le_bytes(watchpoint.value().try_into().unwrap()),
+        )
+    }
+
+    #[test]
+    fn hardware_watchpoints_stop_on_changes_only() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("watch-hardware", WATCH_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, _) = run_to_main(&mut debugger, &binary);
+        let counter = debugger.global_value("counter").unwrap();
+        assert_eq!(counter.value, Some(sdblib::Value::Signed(0)));
+        let address = debugger
+            .global_variables()
+            .unwrap()
+            .into_iter()
+            .find(|global| global.name == "counter")
+            .unwrap()
+            .address
+            .unwrap();
+        let id = debugger
+            .set_watchpoint("counter", address, 4, sdblib::WatchKind::Write)
+            .unwrap();
+        assert_eq!(
+            debugger.watchpoints()[0].backend,
+            sdblib::WatchBackend::Hardware { slot: 0 }
+        );
+        for expected in [(0, 1), (1, 2), (2, 3)] {
+            assert!(matches!(
+                debugger.continue_all().unwrap(),
+                StopReason::Watchpoint(_, hit) if hit == id
+            ));
+            assert_eq!(watched_int(&debugger.watchpoints()[0]), expected);
+        }
+        // `counter = 3` writes the value it already has.
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Exited(_, 0)
+        ));
+    }
+
+    #[test]
+    fn watchpoints_fall_back_to_software_once_debug_registers_run_out() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("watch-software", WATCH_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, _) = run_to_main(&mut debugger, &binary);
+        let globals = debugger.global_variables().unwrap();
+        let address_of = |name: &str| {
+            globals
+                .iter()
+                .find(|global| global.name == name)
+                .and_then(|global| global.address)
+                .unwrap()
+        };
+        for index in 0..4 {
+            debugger
+                .set_watchpoint(
+                    "others",
+                    address_of("others") + 8 * index,
+                    8,
+                    sdblib::WatchKind::Write,
+                )
+                .unwrap();
+        }
+        let id = debugger
+            .set_watchpoint(
+                "counter",
+                address_of("counter"),
+                4,
+                sdblib::WatchKind::Write,
+            )
+            .unwrap();
+        let watchpoint = debugger.watchpoints().last().unwrap();
+        assert_eq!(watchpoint.backend, sdblib::WatchBackend::Software);
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Watchpoint(_, hit) if hit == id
+        ));
+        assert_eq!(watched_int(debugger.watchpoints().last().unwrap()), (0, 1));
+    }
+
+    #[test]
+    fn deleted_watchpoints_free_their_debug_register() {
+        let _guard = lock_tracees();
+        let binary = compile_fixture("watch-delete", WATCH_SOURCE, &[], None);
+        let mut debugger = Debugger::new();
+        let (_stdout, pid) = run_to_main(&mut debugger, &binary);
+        let globals = debugger.global_variables().unwrap();
+        let others = globals
+            .iter()
+            .find(|global| global.name == "others")
+            .and_then(|global| global.address)
+            .unwrap();
+        let ids = (0..4)
+            .map(|index| {
+                debugger
+                    .set_watchpoint("others", others + 8 * index, 8, sdblib::WatchKind::Write)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        debugger.delete_watchpoint(ids[2]).unwrap();
+        let dr7 = debugger
+            .read_user(pid, sdblib::debug_register_offset(7).unwrap())
+            .unwrap();
+        assert_eq!(dr7 & (0b11 << 4), 0, "{dr7:#x}");
+        assert_eq!(dr7 & (0b1111 << 24), 0, "{dr7:#x}");
+
+        let counter = globals
+            .iter()
+            .find(|global| global.name == "counter")
+            .and_then(|global| global.address)
+            .unwrap();
+        let id = debugger
+            .set_watchpoint("counter", counter, 4, sdblib::WatchKind::Read)
+            .unwrap();
+        let watchpoint = debugger.watchpoints().last().unwrap();
+        assert_eq!(
+            watchpoint.backend,
+            sdblib::WatchBackend::Hardware { slot: 2 }
+        );
+        assert!(matches!(
+            debugger.continue_all().unwrap(),
+            StopReason::Watchpoint(_, hit) if hit == id
+        ));
+        let watchpoint = debugger.watchpoints().last().unwrap();
+        assert_eq!(watched_int(watchpoint), (0, 0));
+        assert_eq!(watchpoint.hit_count, 1);
+        assert!(debugger.delete_watchpoint(id).is_ok());
+        assert!(debugger.delete_watchpoint(id).is_err());
+    }
 }